@@ -0,0 +1,184 @@
+//! Client of the binary control protocol, over the serial port of a board or any other stream.
+
+use cookie_monster_common::command::{Command, Status};
+use cookie_monster_common::protocol;
+use cookie_monster_common::protocol::{
+    DELIMITER, FRAME_SIZE, FrameError, Request, Response, ResponseError, Statistics,
+};
+use smart_leds::RGB8;
+use std::fmt::{Display, Formatter};
+use std::io::{Read, Write};
+use std::thread;
+use std::time::{Duration, Instant};
+use std::{fmt, io};
+
+/// How long the frame queried can take to be published, past the longest delay between frames.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// How often the frame queried is polled until it's published.
+const FRAME_POLLING_INTERVAL: Duration = Duration::from_millis(5);
+
+#[derive(Debug)]
+pub enum Error {
+    /// The stream failed, or no response was received before its timeout.
+    Io(io::Error),
+
+    /// The frame of the response was rejected.
+    Frame(FrameError),
+
+    /// The board couldn't answer the request.
+    Board(ResponseError),
+
+    /// The response doesn't answer the request.
+    UnexpectedResponse(Response),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(error) => write!(f, "{error}"),
+            Error::Frame(FrameError::UnsupportedVersion(version)) => write!(
+                f,
+                "the board speaks version {version} of the protocol, instead of {}",
+                protocol::VERSION
+            ),
+            Error::Frame(error) => write!(f, "invalid response: {error}"),
+            Error::Board(error) => write!(f, "{error}"),
+            Error::UnexpectedResponse(response) => write!(f, "unexpected response: {response:?}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(error: io::Error) -> Self {
+        Error::Io(error)
+    }
+}
+
+impl From<FrameError> for Error {
+    fn from(error: FrameError) -> Self {
+        Error::Frame(error)
+    }
+}
+
+/// Sends the requests to a board, and waits for each response.
+pub struct Client<P> {
+    port: P,
+}
+
+impl<P: Read + Write> Client<P> {
+    pub fn new(port: P) -> Self {
+        Self { port }
+    }
+
+    /// Sends a request, and returns its response.
+    ///
+    /// # Errors
+    ///
+    /// Returns why no response was received, or why the board couldn't answer.
+    pub fn request(&mut self, request: &Request) -> Result<Response, Error> {
+        let mut frame = [0; FRAME_SIZE];
+        let length = protocol::encode(request, &mut frame)?;
+        self.port.write_all(&frame[..length])?;
+        self.port.flush()?;
+
+        match self.receive()? {
+            Response::Error(error) => Err(Error::Board(error)),
+            response => Ok(response),
+        }
+    }
+
+    /// Queues a command to the task running the animation.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the command wasn't queued.
+    pub fn execute(&mut self, command: Command) -> Result<(), Error> {
+        match self.request(&Request::Command(command))? {
+            Response::Queued => Ok(()),
+            response => Err(Error::UnexpectedResponse(response)),
+        }
+    }
+
+    /// Queries the animation and its settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the status wasn't received.
+    pub fn status(&mut self) -> Result<Status, Error> {
+        match self.request(&Request::Command(Command::Status))? {
+            Response::Status(status) => Ok(status),
+            response => Err(Error::UnexpectedResponse(response)),
+        }
+    }
+
+    /// Queries the frame timing statistics of the current period.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the statistics weren't received.
+    pub fn statistics(&mut self) -> Result<Statistics, Error> {
+        match self.request(&Request::Command(Command::Stats))? {
+            Response::Statistics(statistics) => Ok(statistics),
+            response => Err(Error::UnexpectedResponse(response)),
+        }
+    }
+
+    /// Queries the colors of the current frame, chunk by chunk. The board is busy until the
+    /// animation publishes its next frame, and the animation goes on meanwhile, so the chunks can
+    /// be of consecutive frames.
+    ///
+    /// # Errors
+    ///
+    /// Returns why a chunk wasn't received, or that the frame wasn't published in time.
+    pub fn frame(&mut self) -> Result<Vec<RGB8>, Error> {
+        let start = Instant::now();
+        let mut colors = Vec::new();
+        loop {
+            let offset = u16::try_from(colors.len()).unwrap_or(u16::MAX);
+            let chunk = match self.request(&Request::Frame { offset }) {
+                Ok(Response::Frame(chunk)) if chunk.offset == offset => chunk,
+                Err(Error::Board(ResponseError::Busy)) if start.elapsed() < FRAME_TIMEOUT => {
+                    thread::sleep(FRAME_POLLING_INTERVAL);
+                    continue;
+                }
+                Ok(response) => return Err(Error::UnexpectedResponse(response)),
+                Err(error) => return Err(error),
+            };
+            if chunk.colors().is_empty() {
+                return Ok(colors);
+            }
+            colors.extend_from_slice(chunk.colors());
+        }
+    }
+
+    /// Receives the next response frame. The bytes around it and the frames that fail to decode,
+    /// like the defmt frames of the logs sharing the port, are skipped until the timeout of the
+    /// port. A frame of another version passes its checksum, so it's returned as an error instead.
+    fn receive(&mut self) -> Result<Response, Error> {
+        let mut frame = Vec::with_capacity(FRAME_SIZE);
+        let mut framed = false;
+        let mut byte = [0];
+
+        loop {
+            self.port.read_exact(&mut byte)?;
+            match byte[0] {
+                DELIMITER if !framed || frame.is_empty() => framed = true,
+                DELIMITER => match protocol::decode(&mut frame) {
+                    Ok(response) => return Ok(response),
+                    Err(FrameError::InvalidEncoding | FrameError::InvalidChecksum) => frame.clear(),
+                    Err(error) => return Err(error.into()),
+                },
+                _ if !framed => {}
+                // Longer than any response, so the bytes are skipped until the next delimiter.
+                _ if frame.len() == FRAME_SIZE => {
+                    framed = false;
+                    frame.clear();
+                }
+                byte => frame.push(byte),
+            }
+        }
+    }
+}
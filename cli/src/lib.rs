@@ -0,0 +1,4 @@
+//! Host side of the binary control protocol of the boards, used by the command line tool.
+
+pub mod client;
+mod logs;
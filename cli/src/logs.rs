@@ -0,0 +1,21 @@
+//! The common crate logs with `defmt`, which expects the firmware to provide a global logger and a
+//! panic handler. On the host, the logs are discarded and the panics are forwarded to the standard
+//! library.
+
+#[defmt::global_logger]
+struct DiscardingLogger;
+
+unsafe impl defmt::Logger for DiscardingLogger {
+    fn acquire() {}
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {}
+
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+#[defmt::panic_handler]
+fn panic() -> ! {
+    panic!("defmt panic")
+}
@@ -0,0 +1,250 @@
+use clap::{Parser, Subcommand, ValueEnum};
+use cookie_monster_cli::client::Client;
+use cookie_monster_common::animations::{AnimationKind, LEDS_FIRST_SECTION, Section};
+use cookie_monster_common::color_correction::ColorCorrection;
+use cookie_monster_common::command::{Color, Command};
+use cookie_monster_common::console;
+use cookie_monster_common::console::DELAY_MAXIMUM;
+use serialport::{SerialPort, SerialPortType};
+use smart_leds::RGB8;
+use std::error::Error;
+use std::process::ExitCode;
+use std::thread;
+use std::time::Duration;
+
+/// The number of colors of each line of a dumped frame.
+const COLORS_PER_LINE: usize = 8;
+
+/// Controls a Cookie Monster board over its serial port.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// The serial port of the board, like /dev/ttyACM0.
+    #[arg(short, long, global = true)]
+    port: Option<String>,
+
+    /// The baud rate of the serial port, which only matters to the QuinLED-Dig-Quad.
+    #[arg(short, long, global = true, default_value_t = 115_200)]
+    baud_rate: u32,
+
+    /// How long to wait for each response, in milliseconds.
+    #[arg(short, long, global = true, default_value_t = 1_000)]
+    timeout: u64,
+
+    #[command(subcommand)]
+    action: Action,
+}
+
+#[derive(Subcommand)]
+enum Action {
+    /// Lists the serial ports.
+    Ports,
+
+    /// Switches to the animation, like uni_color_sparkle.
+    SetAnimation {
+        #[arg(value_parser = parse_animation)]
+        animation: AnimationKind,
+    },
+
+    /// Switches to the next animation of the sequence.
+    NextAnimation,
+
+    /// Switches to the previous animation of the sequence.
+    PreviousAnimation,
+
+    /// Selects the color of the uni-color animations.
+    SetColor {
+        /// The index of a color of the palette, or a custom color as #rrggbb.
+        #[arg(value_parser = parse_color)]
+        color: Color,
+    },
+
+    /// Sets the brightness, until the potentiometer is turned.
+    Brightness { level: u8 },
+
+    /// Sets the delay between frames, until the potentiometer is turned.
+    Speed {
+        /// The delay in milliseconds.
+        #[arg(value_parser = clap::value_parser!(u16).range(1..=i64::from(DELAY_MAXIMUM)))]
+        delay: u16,
+    },
+
+    /// Saves the animation and its settings in the flash, or restores them.
+    Preset { action: PresetAction },
+
+    /// Sets the color correction and the color temperature of the sections, and saves them in the
+    /// flash.
+    ColorCorrection {
+        sections: Sections,
+
+        /// The correction of the LEDs as #rrggbb, like #ffb0f0 for the SMD5050 LEDs.
+        #[arg(value_parser = parse_rgb)]
+        correction: RGB8,
+
+        /// The temperature of the white as #rrggbb, like #ff9329 for a candle.
+        #[arg(value_parser = parse_rgb)]
+        temperature: RGB8,
+    },
+
+    /// Shows the animation and its settings.
+    Status,
+
+    /// Streams the frame timing statistics of the current period.
+    Telemetry {
+        /// The delay between the statistics, in milliseconds.
+        #[arg(short, long, default_value_t = 1_000)]
+        interval: u64,
+
+        /// Stops after as many statistics, instead of streaming until interrupted.
+        #[arg(short, long)]
+        count: Option<u32>,
+    },
+
+    /// Dumps the colors of the current frame, before their corrections.
+    Frame,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum PresetAction {
+    Save,
+    Load,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Sections {
+    First,
+    Second,
+    All,
+}
+
+impl Sections {
+    fn section(self) -> Option<Section> {
+        match self {
+            Sections::First => Some(Section::First),
+            Sections::Second => Some(Section::Second),
+            Sections::All => None,
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {e}");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn run(cli: Cli) -> Result<(), Box<dyn Error>> {
+    if let Action::Ports = cli.action {
+        return list_ports();
+    }
+
+    let port = cli
+        .port
+        .ok_or("a serial port is required, see the ports command")?;
+    let port = serialport::new(port, cli.baud_rate)
+        .timeout(Duration::from_millis(cli.timeout))
+        .open()?;
+    let mut client = Client::new(port);
+
+    match cli.action {
+        Action::Ports => unreachable!("the ports are listed without a board"),
+        Action::SetAnimation { animation } => client.execute(Command::Animation(animation))?,
+        Action::NextAnimation => client.execute(Command::NextAnimation)?,
+        Action::PreviousAnimation => client.execute(Command::PreviousAnimation)?,
+        Action::SetColor { color } => client.execute(Command::Color(color))?,
+        Action::Brightness { level } => client.execute(Command::Brightness(level))?,
+        Action::Speed { delay } => client.execute(Command::Speed(delay))?,
+        Action::Preset {
+            action: PresetAction::Save,
+        } => client.execute(Command::SavePreset)?,
+        Action::Preset {
+            action: PresetAction::Load,
+        } => client.execute(Command::LoadPreset)?,
+        Action::ColorCorrection {
+            sections,
+            correction,
+            temperature,
+        } => client.execute(Command::ColorCorrection(
+            sections.section(),
+            ColorCorrection::new(correction, temperature),
+        ))?,
+        Action::Status => println!("{}", client.status()?),
+        Action::Telemetry { interval, count } => stream_telemetry(&mut client, interval, count)?,
+        Action::Frame => dump_frame(&mut client)?,
+    }
+
+    Ok(())
+}
+
+fn list_ports() -> Result<(), Box<dyn Error>> {
+    for port in serialport::available_ports()? {
+        match port.port_type {
+            SerialPortType::UsbPort(usb) => println!(
+                "{} (USB {:04x}:{:04x} {})",
+                port.port_name,
+                usb.vid,
+                usb.pid,
+                usb.product.unwrap_or_default()
+            ),
+            _ => println!("{}", port.port_name),
+        }
+    }
+
+    Ok(())
+}
+
+/// Prints the statistics at each interval, until the count is reached if there's one.
+fn stream_telemetry(
+    client: &mut Client<Box<dyn SerialPort>>, interval: u64, count: Option<u32>,
+) -> Result<(), Box<dyn Error>> {
+    let mut streamed = 0;
+    while count.is_none_or(|count| streamed < count) {
+        if streamed > 0 {
+            thread::sleep(Duration::from_millis(interval));
+        }
+        println!("{}", client.statistics()?);
+        streamed += 1;
+    }
+
+    Ok(())
+}
+
+/// Prints the colors of the frame, section by section.
+fn dump_frame(client: &mut Client<Box<dyn SerialPort>>) -> Result<(), Box<dyn Error>> {
+    let colors = client.frame()?;
+    let (first, second) = colors.split_at(LEDS_FIRST_SECTION.min(colors.len()));
+
+    for (section, colors) in [(1, first), (2, second)] {
+        println!("section {section}, {} LEDs:", colors.len());
+        for line in colors.chunks(COLORS_PER_LINE) {
+            let line: Vec<_> = line
+                .iter()
+                .map(|color| format!("#{:02x}{:02x}{:02x}", color.r, color.g, color.b))
+                .collect();
+            println!("  {}", line.join(" "));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_animation(name: &str) -> Result<AnimationKind, String> {
+    AnimationKind::from_name(name).ok_or_else(|| {
+        let names: Vec<_> = AnimationKind::ALL.iter().map(|kind| kind.name()).collect();
+        format!("expected one of {}", names.join(", "))
+    })
+}
+
+fn parse_color(color: &str) -> Result<Color, String> {
+    console::parse_color(color).map_err(|_| "expected an index or #rrggbb".to_owned())
+}
+
+fn parse_rgb(color: &str) -> Result<RGB8, String> {
+    console::parse_rgb(color).map_err(|_| "expected #rrggbb".to_owned())
+}
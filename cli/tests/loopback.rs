@@ -0,0 +1,128 @@
+//! Tests of the client and the command line tool, against a board emulated on a pseudo-terminal.
+
+use cookie_monster_common::animations::{AnimationKind, LEDS_FIRST_SECTION, LEDS_SECOND_SECTION};
+use cookie_monster_common::command::{Color, Command};
+use smart_leds::RGB8;
+use std::process;
+
+mod support;
+
+use support::{Board, eventually};
+
+/// Runs the command line tool with the arguments, and returns its output once it succeeded.
+fn run_cli(board: &Board, arguments: &[&str]) -> String {
+    let output = process::Command::new(env!("CARGO_BIN_EXE_cookie-monster-cli"))
+        .args(["--port", &board.path()])
+        .args(arguments)
+        .output()
+        .unwrap();
+
+    assert!(
+        output.status.success(),
+        "{arguments:?}: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn commands_change_the_status() {
+    let board = Board::start();
+    let mut client = board.client();
+
+    client
+        .execute(Command::Animation(AnimationKind::UniColorSolid))
+        .unwrap();
+    client
+        .execute(Command::Color(Color::Rgb(RGB8::new(255, 128, 0))))
+        .unwrap();
+    client.execute(Command::Brightness(42)).unwrap();
+    client.execute(Command::Speed(20)).unwrap();
+    client.execute(Command::NextAnimation).unwrap();
+    let status = eventually(|| client.status().unwrap(), |status| status.delay == 20);
+
+    assert_eq!(status.animation, AnimationKind::UniColorSolid.next());
+    assert_eq!(status.color, Color::Rgb(RGB8::new(255, 128, 0)));
+    assert_eq!(status.brightness, 42);
+}
+
+#[test]
+fn preset_restores_the_saved_settings() {
+    let board = Board::start();
+    let mut client = board.client();
+
+    client
+        .execute(Command::Animation(AnimationKind::Shimmer))
+        .unwrap();
+    client.execute(Command::Brightness(7)).unwrap();
+    client.execute(Command::SavePreset).unwrap();
+    client
+        .execute(Command::Animation(AnimationKind::Carrousel))
+        .unwrap();
+    client.execute(Command::Brightness(200)).unwrap();
+    eventually(
+        || client.status().unwrap(),
+        |status| status.brightness == 200,
+    );
+    client.execute(Command::LoadPreset).unwrap();
+    let status = eventually(|| client.status().unwrap(), |status| status.brightness == 7);
+
+    assert_eq!(status.animation, AnimationKind::Shimmer);
+    assert_eq!(status.brightness, 7);
+}
+
+#[test]
+fn logs_before_the_responses_are_skipped() {
+    let board = Board::with_logs();
+    let mut client = board.client();
+
+    client.execute(Command::Brightness(42)).unwrap();
+    let status = eventually(
+        || client.status().unwrap(),
+        |status| status.brightness == 42,
+    );
+    let frame = client.frame().unwrap();
+
+    assert_eq!(status.brightness, 42);
+    assert_eq!(frame.len(), LEDS_FIRST_SECTION + LEDS_SECOND_SECTION);
+}
+
+#[test]
+fn statistics_and_frame_are_received() {
+    let board = Board::start();
+    let mut client = board.client();
+
+    let statistics = eventually(
+        || client.statistics().unwrap(),
+        |statistics| statistics.frames > 0,
+    );
+    let frame = client.frame().unwrap();
+
+    assert!(statistics.frames > 0);
+    assert_eq!(frame.len(), LEDS_FIRST_SECTION + LEDS_SECOND_SECTION);
+}
+
+#[test]
+fn command_line_tool_controls_the_board() {
+    let board = Board::start();
+
+    run_cli(&board, &["set-animation", "uni_color_sparkle"]);
+    run_cli(&board, &["set-color", "#0102ff"]);
+    run_cli(&board, &["brightness", "12"]);
+    run_cli(&board, &["color-correction", "all", "#ffb0f0", "#ff9329"]);
+    let status = eventually(
+        || run_cli(&board, &["status"]),
+        |status| status.contains("brightness 12"),
+    );
+    let telemetry = run_cli(&board, &["telemetry", "--interval", "1", "--count", "2"]);
+    let frame = run_cli(&board, &["frame"]);
+
+    assert_eq!(
+        status,
+        "animation uni_color_sparkle, color #0102ff, brightness 12, delay 10 ms, dithering on\n"
+    );
+    assert_eq!(telemetry.lines().count(), 2);
+    assert!(telemetry.lines().all(|line| line.contains(" frames in ")));
+    assert!(frame.starts_with(&format!("section 1, {LEDS_FIRST_SECTION} LEDs:\n  #")));
+    assert!(frame.contains(&format!("section 2, {LEDS_SECOND_SECTION} LEDs:\n")));
+}
@@ -0,0 +1,263 @@
+//! A board emulated on the host, which answers the binary control protocol on a pseudo-terminal
+//! with the console of the common crate, while its animation runs in the background.
+
+use cookie_monster_cli::client::Client;
+use cookie_monster_common::animations::{Animation, AnimationKind, COLORS_TOTAL, Settings};
+use cookie_monster_common::benchmark::NoDelay;
+use cookie_monster_common::command;
+use cookie_monster_common::command::Change;
+use cookie_monster_common::console::Console;
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::pipeline::Pipeline;
+use cookie_monster_common::signal::COMMAND_CHANNEL;
+use cookie_monster_common::storage::{DeferredSave, SettingsStorage};
+use cookie_monster_common::telemetry;
+use embassy_futures::block_on;
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use serialport::{SerialPort, TTYPort};
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWriteAsync;
+use std::io::{ErrorKind, Read, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+// Provides the time to the animation, even when nothing else uses it.
+use embassy_time as _;
+
+const SECTOR_SIZE: usize = 4096;
+
+/// How long the client waits for a response.
+const TIMEOUT: Duration = Duration::from_secs(2);
+
+/// A log of the QuinLED-Dig-Quad, as its defmt frames are written on the serial port: a marker,
+/// then the rzcobs-encoded log ending with a zero byte.
+const LOG_FRAME: &[u8] = b"\xFF\x00\x03\x2A\x07\x11\x00";
+
+/// The boards share the statics of the common crate, so only one runs at a time.
+static RUNNING: Mutex<()> = Mutex::new(());
+
+pub struct Board {
+    /// The end of the pseudo-terminal opened by the clients, kept open until the board stops.
+    port: TTYPort,
+
+    stopped: Arc<AtomicBool>,
+    threads: Vec<JoinHandle<()>>,
+    _running: MutexGuard<'static, ()>,
+}
+
+impl Board {
+    pub fn start() -> Self {
+        Self::spawn(None)
+    }
+
+    /// Starts a board that writes a log before each reply, like the QuinLED-Dig-Quad.
+    pub fn with_logs() -> Self {
+        Self::spawn(Some(LOG_FRAME))
+    }
+
+    fn spawn(log: Option<&'static [u8]>) -> Self {
+        let running = RUNNING.lock().unwrap_or_else(PoisonError::into_inner);
+        while COMMAND_CHANNEL.try_receive().is_ok() {}
+
+        let (mut serial, port) = TTYPort::pair().unwrap();
+        serial.set_timeout(Duration::from_millis(10)).unwrap();
+        let stopped = Arc::new(AtomicBool::new(false));
+
+        let threads = vec![
+            thread::spawn({
+                let stopped = stopped.clone();
+                move || answer_console(&mut serial, log, &stopped)
+            }),
+            thread::spawn({
+                let stopped = stopped.clone();
+                move || run_animation(&stopped)
+            }),
+        ];
+
+        Self {
+            port,
+            stopped,
+            threads,
+            _running: running,
+        }
+    }
+
+    /// The path of the serial port of the board.
+    pub fn path(&self) -> String {
+        self.port.name().unwrap()
+    }
+
+    pub fn client(&self) -> Client<TTYPort> {
+        let port = serialport::new(self.path(), 115_200)
+            .timeout(TIMEOUT)
+            .open_native()
+            .unwrap();
+        Client::new(port)
+    }
+}
+
+impl Drop for Board {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::Relaxed);
+        for thread in self.threads.drain(..) {
+            thread.join().unwrap();
+        }
+    }
+}
+
+/// Returns the value once the condition holds, since the commands are applied asynchronously.
+pub fn eventually<T>(mut value: impl FnMut() -> T, condition: impl Fn(&T) -> bool) -> T {
+    let start = Instant::now();
+    loop {
+        let value = value();
+        if condition(&value) || start.elapsed() > TIMEOUT {
+            return value;
+        }
+        thread::sleep(Duration::from_millis(5));
+    }
+}
+
+fn answer_console(serial: &mut TTYPort, log: Option<&[u8]>, stopped: &AtomicBool) {
+    let mut console = Console::new();
+    let mut buffer = [0; 64];
+
+    while !stopped.load(Ordering::Relaxed) {
+        let length = match serial.read(&mut buffer) {
+            Ok(length) => length,
+            Err(e) if e.kind() == ErrorKind::TimedOut => continue,
+            Err(e) => panic!("cannot read from the console: {e}"),
+        };
+        for &byte in &buffer[..length] {
+            if let Some(reply) = console.receive(byte) {
+                if let Some(log) = log {
+                    serial.write_all(log).unwrap();
+                }
+                serial.write_all(reply).unwrap();
+            }
+        }
+    }
+}
+
+/// Runs the animation like the task of a board, applying the commands before each frame.
+fn run_animation(stopped: &AtomicBool) {
+    let mut prng = SmallRng::seed_from_u64(0);
+    let mut pipeline = Pipeline::new();
+    let mut dithering = Dithering::new();
+    let mut settings = Settings::new(3, 0, 10, 1_000, COLORS_TOTAL);
+    let mut storage = SettingsStorage::new(RamFlash::new(), 0);
+    let mut deferred_save = DeferredSave::new();
+    let mut active_kind = AnimationKind::MultiColorStrand;
+    let mut active_animation = Animation::new(active_kind, &mut prng);
+
+    while !stopped.load(Ordering::Relaxed) {
+        while let Ok(command) = COMMAND_CHANNEL.try_receive() {
+            match command::execute(command, active_kind, &mut settings, Some(&mut storage)) {
+                Some(Change::Animation(kind)) => {
+                    active_kind = kind;
+                    active_animation = Animation::new(active_kind, &mut prng);
+                    pipeline.reset();
+                    dithering.reset();
+                    telemetry::reset(embassy_time::Instant::now());
+                }
+                Some(Change::Settings) => deferred_save.changed(embassy_time::Instant::now()),
+                None => {}
+            }
+        }
+        if deferred_save.is_due(embassy_time::Instant::now()) {
+            storage.save(&settings).unwrap();
+        }
+        command::publish_status(active_kind, &settings);
+
+        block_on(pipeline.render(
+            &mut active_animation,
+            &mut NullSection,
+            &mut NullSection,
+            &mut dithering,
+            &mut NoDelay,
+            &settings,
+        ))
+        .unwrap();
+        pipeline.next_frame();
+
+        thread::sleep(Duration::from_millis(2));
+    }
+}
+
+/// A LED section that discards the frames written to it.
+struct NullSection;
+
+impl SmartLedsWriteAsync for NullSection {
+    type Color = RGB8;
+    type Error = ();
+
+    async fn write<T, I>(&mut self, _iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        Ok(())
+    }
+}
+
+/// A flash memory of a sector, for the presets.
+struct RamFlash {
+    bytes: Vec<u8>,
+}
+
+impl RamFlash {
+    fn new() -> Self {
+        Self {
+            bytes: vec![u8::MAX; SECTOR_SIZE],
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RamFlashError;
+
+impl NorFlashError for RamFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        NorFlashErrorKind::Other
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = RamFlashError;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.bytes[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        self.bytes[from as usize..to as usize].fill(u8::MAX);
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        self.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+}
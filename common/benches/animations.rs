@@ -0,0 +1,85 @@
+//! Measures the cost of computing a full frame of every animation on the host.
+//!
+//! The update and the post-processing (gamma, brightness and power limit) are measured separately.
+//! Criterion compares each run with the previous one, use `--save-baseline` and `--baseline` to
+//! compare with a specific run instead.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_INDEX_DEFAULT, COLORS_TOTAL, Settings, create_data,
+};
+use cookie_monster_common::benchmark::{DiscardingSection, NoDelay};
+use cookie_monster_common::dithering::Dithering;
+use criterion::{Criterion, criterion_group, criterion_main};
+use embassy_futures::block_on;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use std::hint::black_box;
+
+#[path = "../tests/support/mod.rs"]
+mod support;
+
+/// The seed of the animations, fixed so that every run computes the same frames.
+const SEED: u64 = 0xC00C1E;
+
+/// Same analog values as the boards, which have a 12-bit ADC and start at the middle of the range.
+const ANALOG_MAXIMUM_VALUE: u16 = 4095;
+const ANALOG_DEFAULT_VALUE: u16 = ANALOG_MAXIMUM_VALUE / 2;
+
+fn settings() -> Settings {
+    Settings::new(
+        COLORS_INDEX_DEFAULT,
+        ANALOG_DEFAULT_VALUE,
+        ANALOG_DEFAULT_VALUE,
+        ANALOG_MAXIMUM_VALUE,
+        COLORS_TOTAL,
+    )
+}
+
+fn update(c: &mut Criterion) {
+    let settings = settings();
+    let mut group = c.benchmark_group("update");
+
+    for kind in AnimationKind::ALL {
+        let mut prng = SmallRng::seed_from_u64(SEED);
+        let mut animation = Animation::new(kind, &mut prng);
+        let mut data = create_data();
+
+        group.bench_function(format!("{kind:?}"), |b| {
+            b.iter(|| animation.update(black_box(&mut data), &settings));
+        });
+    }
+
+    group.finish();
+}
+
+fn post_processing(c: &mut Criterion) {
+    let settings = settings();
+    let mut group = c.benchmark_group("post_processing");
+
+    for kind in AnimationKind::ALL {
+        let mut prng = SmallRng::seed_from_u64(SEED);
+        let mut animation = Animation::new(kind, &mut prng);
+        let mut data = create_data();
+        let mut dithering = Dithering::new();
+        animation.update(&mut data, &settings);
+
+        group.bench_function(format!("{kind:?}"), |b| {
+            b.iter(|| {
+                block_on(animation.render(
+                    black_box(&data),
+                    &mut DiscardingSection,
+                    &mut DiscardingSection,
+                    &mut dithering,
+                    &mut NoDelay,
+                    &settings,
+                ))
+                .unwrap();
+            });
+        });
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, update, post_processing);
+criterion_main!(benches);
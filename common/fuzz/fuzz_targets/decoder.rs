@@ -0,0 +1,22 @@
+//! Feeds arbitrary frames to the decoder of the binary control protocol, which must reject them
+//! without panicking. The ones it accepts must encode back to a frame decoded as the same message.
+
+#![no_main]
+
+use cookie_monster_common::protocol::{FRAME_SIZE, Request, Response, decode, encode};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|frame: &[u8]| {
+    if let Ok(request) = decode::<Request>(&mut frame.to_vec()) {
+        assert_eq!(round_trip(&request), request);
+    }
+    if let Ok(response) = decode::<Response>(&mut frame.to_vec()) {
+        assert_eq!(round_trip(&response), response);
+    }
+});
+
+fn round_trip<T: serde::Serialize + serde::de::DeserializeOwned>(message: &T) -> T {
+    let mut frame = [0; FRAME_SIZE];
+    let length = encode(message, &mut frame).unwrap();
+    decode(&mut frame[1..length - 1]).unwrap()
+}
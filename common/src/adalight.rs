@@ -0,0 +1,146 @@
+//! Adalight, the serial streaming protocol of Hyperion, Prismatik and other ambient lighting hosts.
+//!
+//! Each frame starts with the `Ada` magic word, then the number of LEDs minus one as a big-endian
+//! 16-bit integer, and a checksum of that number: its two bytes XORed with `0x55`. The colors of
+//! the LEDs follow, three bytes each in RGB order. A frame interrupted for [`FRAME_TIMEOUT`] is
+//! discarded, so that the header of the next one is recognized.
+
+use crate::stream;
+use core::iter;
+use embassy_time::{Duration, Instant};
+use smart_leds::RGB8;
+
+/// The magic word starting each frame.
+const MAGIC: &[u8; 3] = b"Ada";
+
+/// XORed with the bytes of the number of LEDs to compute the checksum of the header.
+const CHECKSUM_KEY: u8 = 0x55;
+
+/// How long a frame can go without a byte before it's discarded.
+pub const FRAME_TIMEOUT: Duration = Duration::from_millis(100);
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Looking for the magic word, of which the given number of bytes were received.
+    Magic(usize),
+
+    /// Receiving the number of LEDs and its checksum, of which the given bytes were received.
+    Header { bytes: [u8; 3], received: usize },
+
+    /// Receiving the colors of the LEDs.
+    Colors {
+        /// The number of LEDs of the frame.
+        leds: usize,
+
+        /// The number of bytes of colors received.
+        received: usize,
+
+        /// The channels of the color being received.
+        color: [u8; 3],
+    },
+}
+
+/// Parses the Adalight frames out of the bytes received by a serial port, and writes their colors
+/// to the stream.
+pub struct Parser {
+    state: State,
+
+    /// When the last byte was received, for the timeout of the frames.
+    received_at: Instant,
+}
+
+impl Parser {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: State::Magic(0),
+            received_at: Instant::from_ticks(0),
+        }
+    }
+
+    /// Parses a received byte, and returns whether it belongs to a frame.
+    ///
+    /// The bytes of the magic word don't belong to a frame until it's complete, since they can be
+    /// the start of a line of text. The LEDs past the last one of the board are ignored.
+    pub fn receive(&mut self, byte: u8, now: Instant) -> bool {
+        if self.state != State::Magic(0)
+            && now.saturating_duration_since(self.received_at) >= FRAME_TIMEOUT
+        {
+            self.state = State::Magic(0);
+        }
+        self.received_at = now;
+
+        let (state, consumed) = match self.state {
+            State::Magic(received) if byte == MAGIC[received] => match received + 1 {
+                length if length == MAGIC.len() => (
+                    State::Header {
+                        bytes: [0; 3],
+                        received: 0,
+                    },
+                    true,
+                ),
+                length => (State::Magic(length), false),
+            },
+            State::Magic(_) if byte == MAGIC[0] => (State::Magic(1), false),
+            State::Magic(_) => (State::Magic(0), false),
+            State::Header {
+                mut bytes,
+                received,
+            } => {
+                bytes[received] = byte;
+                match received + 1 {
+                    3 => (header(bytes), true),
+                    received => (State::Header { bytes, received }, true),
+                }
+            }
+            State::Colors {
+                leds,
+                received,
+                mut color,
+            } => {
+                color[received % 3] = byte;
+                let received = received + 1;
+                if received % 3 == 0 {
+                    let [r, g, b] = color;
+                    stream::write(received / 3 - 1, iter::once(RGB8::new(r, g, b)));
+                }
+                if received == leds * 3 {
+                    stream::show(now);
+                    (State::Magic(0), true)
+                } else {
+                    (
+                        State::Colors {
+                            leds,
+                            received,
+                            color,
+                        },
+                        true,
+                    )
+                }
+            }
+        };
+        self.state = state;
+
+        consumed
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Starts receiving the colors once the checksum of the header is valid, or looks for the next
+/// magic word otherwise.
+fn header([high, low, checksum]: [u8; 3]) -> State {
+    if high ^ low ^ CHECKSUM_KEY == checksum {
+        State::Colors {
+            leds: usize::from(u16::from_be_bytes([high, low])) + 1,
+            received: 0,
+            color: [0; 3],
+        }
+    } else {
+        State::Magic(0)
+    }
+}
@@ -9,28 +9,41 @@ use crate::animations::multi_color_solid_random::MultiColorSolidRandom;
 use crate::animations::multi_color_sparkle::MultiColorSparkle;
 use crate::animations::multi_color_strand::MultiColorStrand;
 use crate::animations::shimmer::Shimmer;
+use crate::animations::streaming::Streaming;
 use crate::animations::uni_color_fade_in::UniColorFadeIn;
 use crate::animations::uni_color_front_to_back_wave::UniColorFrontToBackWave;
 use crate::animations::uni_color_heartbeat::UniColorHeartbeat;
 use crate::animations::uni_color_solid::UniColorSolid;
 use crate::animations::uni_color_sparkle::UniColorSparkle;
+use crate::color_correction::ColorCorrection;
+use crate::dithering;
+use crate::dithering::{Dithering, LedColor};
+use crate::gamma::Gamma;
+use crate::power::{Draw, PowerBudget};
+use crate::telemetry;
+use crate::write_error::WriteError;
 use core::cmp;
 use core::fmt::Debug;
 use core::ops::{Range, RangeFrom};
-use defmt::{Format, trace};
+use defmt::{Debug2Format, Format, error, unwrap};
 use embassy_futures::join::join;
 use embassy_time::Instant;
 use embedded_hal_async::delay::DelayNs;
 use rand::RngExt;
 use rand::rngs::SmallRng;
+use serde::{Deserialize, Serialize};
 use smart_leds::colors::{
     BLUE, DARK_GREEN, DARK_RED, DARK_TURQUOISE, GOLD, GREEN, INDIGO, MIDNIGHT_BLUE, PURPLE, RED,
     WHITE,
 };
-use smart_leds::{RGB8, brightness, gamma};
-use smart_leds_trait::SmartLedsWrite;
+use smart_leds::{RGB8, RGB16};
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync};
 
-pub type LedData = [RGB8; LEDS_TOTAL];
+/// The colors of all the LEDs, with 16 bits per channel.
+///
+/// The animations render at this precision, and the colors are only quantized to the 8 bits of the
+/// LEDs once they're corrected for gamma, brightness and power.
+pub type LedData = [RGB16; LEDS_TOTAL];
 
 pub const COLORS_INDEX_DEFAULT: usize = 1;
 pub const COLORS_TOTAL: usize = 11;
@@ -44,18 +57,19 @@ pub const LEDS_FIRST_SECTION: usize = LED_DENSITY * 4;
 /// There are 96 LEDs per meter, and the second section is six meters.
 pub const LEDS_SECOND_SECTION: usize = LED_DENSITY * 6;
 
-pub(crate) const COLORS: [RGB8; COLORS_TOTAL] = [
-    WHITE,
-    RED,
-    DARK_RED,
-    GOLD,
-    GREEN,
-    DARK_GREEN,
-    DARK_TURQUOISE,
-    BLUE,
-    MIDNIGHT_BLUE,
-    PURPLE,
-    INDIGO,
+/// The palette of colors used by the animations.
+pub const COLORS: [RGB16; COLORS_TOTAL] = [
+    widen(WHITE),
+    widen(RED),
+    widen(DARK_RED),
+    widen(GOLD),
+    widen(GREEN),
+    widen(DARK_GREEN),
+    widen(DARK_TURQUOISE),
+    widen(BLUE),
+    widen(MIDNIGHT_BLUE),
+    widen(PURPLE),
+    widen(INDIGO),
 ];
 
 pub(crate) const DELAY_SHORTEST: u32 = 5;
@@ -416,20 +430,6 @@ pub(crate) const VERTICAL_SLICES: [[Option<u16>; 152]; 16] = [
     ],
 ];
 
-const GAMMA8: [u8; 256] = [
-    0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1,
-    1, 1, 1, 1, 1, 1, 1, 1, 1, 2, 2, 2, 2, 2, 2, 2, 2, 3, 3, 3, 3, 3, 3, 3, 4, 4, 4, 4, 4, 5, 5, 5,
-    5, 6, 6, 6, 6, 7, 7, 7, 7, 8, 8, 8, 9, 9, 9, 10, 10, 10, 11, 11, 11, 12, 12, 13, 13, 13, 14,
-    14, 15, 15, 16, 16, 17, 17, 18, 18, 19, 19, 20, 20, 21, 21, 22, 22, 23, 24, 24, 25, 25, 26, 27,
-    27, 28, 29, 29, 30, 31, 32, 32, 33, 34, 35, 35, 36, 37, 38, 39, 39, 40, 41, 42, 43, 44, 45, 46,
-    47, 48, 49, 50, 50, 51, 52, 54, 55, 56, 57, 58, 59, 60, 61, 62, 63, 64, 66, 67, 68, 69, 70, 72,
-    73, 74, 75, 77, 78, 79, 81, 82, 83, 85, 86, 87, 89, 90, 92, 93, 95, 96, 98, 99, 101, 102, 104,
-    105, 107, 109, 110, 112, 114, 115, 117, 119, 120, 122, 124, 126, 127, 129, 131, 133, 135, 137,
-    138, 140, 142, 144, 146, 148, 150, 152, 154, 156, 158, 160, 162, 164, 167, 169, 171, 173, 175,
-    177, 180, 182, 184, 186, 189, 191, 193, 196, 198, 200, 203, 205, 208, 210, 213, 215, 218, 220,
-    223, 225, 228, 231, 233, 236, 239, 241, 244, 247, 249, 252, 255,
-];
-
 /// The number of LEDs per meter.
 const LED_DENSITY: usize = 96;
 
@@ -446,6 +446,7 @@ pub enum Animation {
     MultiColorSparkle(MultiColorSparkle),
     MultiColorStrand(MultiColorStrand),
     Shimmer(Shimmer),
+    Streaming(Streaming),
     UniColorFadeIn(UniColorFadeIn),
     UniColorFrontToBackWave(UniColorFrontToBackWave),
     UniColorHeartbeat(UniColorHeartbeat),
@@ -481,6 +482,7 @@ impl Animation {
                 Animation::MultiColorStrand(MultiColorStrand::new(prng.random()))
             }
             AnimationKind::Shimmer => Animation::Shimmer(Shimmer::new(prng.random())),
+            AnimationKind::Streaming => Animation::Streaming(Streaming::new()),
             AnimationKind::UniColorFadeIn => Animation::UniColorFadeIn(UniColorFadeIn::new()),
             AnimationKind::UniColorFrontToBackWave => {
                 Animation::UniColorFrontToBackWave(UniColorFrontToBackWave::new())
@@ -496,69 +498,60 @@ impl Animation {
     }
 
     /// Renders the animation.
+    ///
+    /// # Errors
+    ///
+    /// Returns the sections that couldn't be written when any of the LED drivers fails.
     pub async fn render(
         &mut self, data: &LedData,
-        leds_section_1: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        leds_section_2: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        delay: &mut impl DelayNs, settings: &Settings,
-    ) {
-        macro_rules! default_render {
-            ($type:ident) => {
-                render(
-                    data,
-                    leds_section_1,
-                    leds_section_2,
-                    $type::BRIGHTNESS_DAMPING_FACTOR,
-                    delay,
-                    settings,
-                )
-                .await
-            };
-        }
+        leds_section_1: &mut impl SmartLedsWrite<Color = impl LedColor, Error = impl Debug>,
+        leds_section_2: &mut impl SmartLedsWrite<Color = impl LedColor, Error = impl Debug>,
+        dithering: &mut Dithering, delay: &mut impl DelayNs, settings: &Settings,
+    ) -> Result<(), WriteError> {
+        let frame = self.frame(settings);
+
+        write_sections(
+            data,
+            leds_section_1,
+            leds_section_2,
+            dithering,
+            settings,
+            |color| frame.correct(color, settings),
+        )
+        .await?;
+
+        wait_next_frame(delay, frame.delay()).await;
+
+        Ok(())
+    }
 
+    /// How the frame of the last update is written.
+    pub(crate) fn frame(&mut self, settings: &Settings) -> Frame {
         match self {
-            Animation::Carrousel(_) => default_render!(Carrousel),
-            Animation::DoubleCarrousel(_) => default_render!(DoubleCarrousel),
-            Animation::ForwardWave(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::MultiColorFadeIn(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::MultiColorFrontToBackWave(_) => default_render!(MultiColorFrontToBackWave),
-            Animation::MultiColorHeartbeat(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::MultiColorSolid(_) => default_render!(MultiColorSolid),
-            Animation::MultiColorSolidRandom(_) => default_render!(MultiColorSolidRandom),
-            Animation::MultiColorSparkle(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::MultiColorStrand(_) => default_render!(MultiColorStrand),
-            Animation::Shimmer(_) => default_render!(Shimmer),
-            Animation::UniColorFadeIn(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::UniColorFrontToBackWave(_) => default_render!(UniColorFrontToBackWave),
-            Animation::UniColorHeartbeat(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
-            Animation::UniColorSolid(_) => default_render!(UniColorSolid),
-            Animation::UniColorSparkle(a) => {
-                a.render(data, leds_section_1, leds_section_2, delay, settings)
-                    .await;
-            }
+            Animation::ForwardWave(a) => a.frame(settings),
+            Animation::MultiColorFadeIn(a) => a.frame(settings),
+            Animation::MultiColorHeartbeat(a) => a.frame(settings),
+            Animation::MultiColorSparkle(a) => a.frame(settings),
+            Animation::Streaming(a) => a.frame(settings),
+            Animation::UniColorFadeIn(a) => a.frame(settings),
+            Animation::UniColorHeartbeat(a) => a.frame(settings),
+            Animation::UniColorSparkle(a) => a.frame(settings),
+            Animation::Carrousel(_)
+            | Animation::DoubleCarrousel(_)
+            | Animation::MultiColorFrontToBackWave(_)
+            | Animation::MultiColorSolid(_)
+            | Animation::MultiColorSolidRandom(_)
+            | Animation::MultiColorStrand(_)
+            | Animation::Shimmer(_)
+            | Animation::UniColorFrontToBackWave(_)
+            | Animation::UniColorSolid(_) => Frame::dimmed(settings.brightness(), settings.delay()),
         }
     }
 
     /// Updates the state of the animation based on the settings.
     pub fn update(&mut self, data: &mut LedData, settings: &Settings) {
+        let start = Instant::now();
+
         match self {
             Animation::Carrousel(a) => a.update(data),
             Animation::DoubleCarrousel(a) => a.update(data),
@@ -571,16 +564,60 @@ impl Animation {
             Animation::MultiColorSparkle(a) => a.update(data, settings),
             Animation::MultiColorStrand(a) => a.update(data),
             Animation::Shimmer(a) => a.update(data),
+            Animation::Streaming(a) => a.update(data),
             Animation::UniColorFadeIn(a) => a.update(data, settings),
             Animation::UniColorFrontToBackWave(a) => a.update(data, settings),
             Animation::UniColorHeartbeat(a) => a.update(data, settings),
             Animation::UniColorSolid(a) => a.update(data, settings),
             Animation::UniColorSparkle(a) => a.update(data, settings),
         }
+
+        telemetry::record_update(start.elapsed());
     }
 }
 
-#[derive(Copy, Clone)]
+/// How a frame of an animation is written: the brightness its colors are corrected with, and the
+/// time to wait before the next one.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Frame {
+    /// No brightness when the colors are already corrected by the update.
+    brightness: Option<u8>,
+    delay: u32,
+}
+
+impl Frame {
+    /// A frame whose colors are corrected with the gamma curve, then the given brightness.
+    pub(crate) fn dimmed(brightness: u8, delay: u32) -> Self {
+        Self {
+            brightness: Some(brightness),
+            delay,
+        }
+    }
+
+    /// A frame whose colors are already corrected by the update.
+    pub(crate) fn corrected(delay: u32) -> Self {
+        Self {
+            brightness: None,
+            delay,
+        }
+    }
+
+    /// The delay in milliseconds before the next frame.
+    pub(crate) fn delay(self) -> u32 {
+        self.delay
+    }
+
+    /// Corrects a color of the frame.
+    pub(crate) fn correct(self, color: RGB16, settings: &Settings) -> RGB16 {
+        match self.brightness {
+            Some(brightness) => brightness_correct(settings.gamma().correct(color), brightness),
+            None => color,
+        }
+    }
+}
+
+/// The animations, whose order is part of the control protocol.
+#[derive(Copy, Clone, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
 pub enum AnimationKind {
     Carrousel,
     DoubleCarrousel,
@@ -598,29 +635,129 @@ pub enum AnimationKind {
     UniColorHeartbeat,
     UniColorSolid,
     UniColorSparkle,
+    /// Shows the frames streamed by a host, which isn't part of the sequence.
+    Streaming,
 }
 
 impl AnimationKind {
-    /// Returns the next animation in the sequence.
+    /// The animations of the sequence, in its order.
+    pub const ALL: [AnimationKind; 16] = [
+        AnimationKind::MultiColorStrand,
+        AnimationKind::MultiColorFrontToBackWave,
+        AnimationKind::Shimmer,
+        AnimationKind::Carrousel,
+        AnimationKind::DoubleCarrousel,
+        AnimationKind::UniColorSparkle,
+        AnimationKind::MultiColorSparkle,
+        AnimationKind::ForwardWave,
+        AnimationKind::UniColorFadeIn,
+        AnimationKind::MultiColorFadeIn,
+        AnimationKind::UniColorFrontToBackWave,
+        AnimationKind::UniColorHeartbeat,
+        AnimationKind::MultiColorHeartbeat,
+        AnimationKind::MultiColorSolid,
+        AnimationKind::MultiColorSolidRandom,
+        AnimationKind::UniColorSolid,
+    ];
+
+    /// Returns the animation with the given name, ignoring the case.
+    #[must_use]
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|kind| kind.name().eq_ignore_ascii_case(name))
+    }
+
+    /// The name of the animation in the consoles and the control protocols.
+    #[must_use]
+    pub fn name(self) -> &'static str {
+        match self {
+            AnimationKind::Carrousel => "carrousel",
+            AnimationKind::DoubleCarrousel => "double_carrousel",
+            AnimationKind::ForwardWave => "forward_wave",
+            AnimationKind::MultiColorFadeIn => "multi_color_fade_in",
+            AnimationKind::MultiColorFrontToBackWave => "multi_color_front_to_back_wave",
+            AnimationKind::MultiColorHeartbeat => "multi_color_heartbeat",
+            AnimationKind::MultiColorSolid => "multi_color_solid",
+            AnimationKind::MultiColorSolidRandom => "multi_color_solid_random",
+            AnimationKind::MultiColorSparkle => "multi_color_sparkle",
+            AnimationKind::MultiColorStrand => "multi_color_strand",
+            AnimationKind::Shimmer => "shimmer",
+            AnimationKind::UniColorFadeIn => "uni_color_fade_in",
+            AnimationKind::UniColorFrontToBackWave => "uni_color_front_to_back_wave",
+            AnimationKind::UniColorHeartbeat => "uni_color_heartbeat",
+            AnimationKind::UniColorSolid => "uni_color_solid",
+            AnimationKind::UniColorSparkle => "uni_color_sparkle",
+            AnimationKind::Streaming => "streaming",
+        }
+    }
+
+    /// Returns the previous animation in the sequence. The streaming isn't part of it, and goes
+    /// back to its last animation.
+    #[must_use]
+    pub fn previous(self) -> Self {
+        match self {
+            AnimationKind::Streaming => Self::ALL[Self::ALL.len() - 1],
+            kind => kind.step(Self::ALL.len() - 1),
+        }
+    }
+
+    /// Returns the next animation in the sequence. The streaming isn't part of it, and goes on to
+    /// its first animation.
     #[must_use]
     pub fn next(self) -> Self {
         match self {
-            AnimationKind::MultiColorStrand => AnimationKind::MultiColorFrontToBackWave,
-            AnimationKind::MultiColorFrontToBackWave => AnimationKind::Shimmer,
-            AnimationKind::Shimmer => AnimationKind::Carrousel,
-            AnimationKind::Carrousel => AnimationKind::DoubleCarrousel,
-            AnimationKind::DoubleCarrousel => AnimationKind::UniColorSparkle,
-            AnimationKind::UniColorSparkle => AnimationKind::MultiColorSparkle,
-            AnimationKind::MultiColorSparkle => AnimationKind::ForwardWave,
-            AnimationKind::ForwardWave => AnimationKind::UniColorFadeIn,
-            AnimationKind::UniColorFadeIn => AnimationKind::MultiColorFadeIn,
-            AnimationKind::MultiColorFadeIn => AnimationKind::UniColorFrontToBackWave,
-            AnimationKind::UniColorFrontToBackWave => AnimationKind::UniColorHeartbeat,
-            AnimationKind::UniColorHeartbeat => AnimationKind::MultiColorHeartbeat,
-            AnimationKind::MultiColorHeartbeat => AnimationKind::MultiColorSolid,
-            AnimationKind::MultiColorSolid => AnimationKind::MultiColorSolidRandom,
-            AnimationKind::MultiColorSolidRandom => AnimationKind::UniColorSolid,
-            AnimationKind::UniColorSolid => AnimationKind::MultiColorStrand,
+            AnimationKind::Streaming => Self::ALL[0],
+            kind => kind.step(1),
+        }
+    }
+
+    /// Returns the animation that follows this one in the sequence after the given steps.
+    fn step(self, steps: usize) -> Self {
+        let index = unwrap!(Self::ALL.iter().position(|&kind| kind == self));
+        Self::ALL[(index + steps) % Self::ALL.len()]
+    }
+}
+
+/// A section of LEDs, each one is written by a dedicated driver.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum Section {
+    First,
+    Second,
+}
+
+impl Section {
+    /// The sections, in the order of the LEDs.
+    pub const ALL: [Section; 2] = [Section::First, Section::Second];
+}
+
+/// How the position of the brightness potentiometer maps to the brightness of the LEDs.
+#[derive(Clone, Copy, Debug, Default, Eq, Format, PartialEq)]
+pub enum BrightnessCurve {
+    /// The brightness is proportional to the position.
+    Linear,
+
+    /// The CIE 1931 lightness curve, so that the brightness looks proportional to the position.
+    /// The midpoint looks half as bright as the maximum, although the LEDs emit less than a fifth
+    /// of the light.
+    #[default]
+    Cie1931,
+}
+
+impl BrightnessCurve {
+    /// Maps a position between 0.0 and 1.0 to a brightness between 0.0 and 1.0.
+    fn apply(self, position: f32) -> f32 {
+        match self {
+            BrightnessCurve::Linear => position,
+            BrightnessCurve::Cie1931 => {
+                let lightness = position * 100.0;
+                if lightness <= 8.0 {
+                    lightness / 903.3
+                } else {
+                    let root = (lightness + 16.0) / 116.0;
+                    root * root * root
+                }
+            }
         }
     }
 }
@@ -628,22 +765,46 @@ impl AnimationKind {
 /// Common settings for the animations.
 #[derive(Clone, Copy, Debug, Format)]
 pub struct Settings {
-    /// Brightness of the LEDs, between 0.0 and 1.0.
+    /// Brightness of the LEDs, between 0 and 255.
     brightness: u8,
 
+    brightness_curve: BrightnessCurve,
+
+    /// Value read from the brightness potentiometer, to compute the brightness again when the
+    /// curve changes.
+    brightness_reading: u16,
+
+    /// Color correction and temperature of each section.
+    color_corrections: [ColorCorrection; 2],
+
     /// Index of the color to be used in the animation.
     ///
     /// Multicolor animations generally ignore this value.
     color_index: usize,
 
+    /// Color used by the animation instead of the one of the palette, until another one is
+    /// selected.
+    #[defmt(Debug2Format)]
+    custom_color: Option<RGB8>,
+
     /// Delay between frames in milliseconds.
     delay: u32,
 
+    /// Whether the quantization error of the colors is spread over the next frames.
+    dithering: bool,
+
     /// Maximum value of the analog sensors (potentiometers).
     analog_max_value: u16,
 
+    /// Gamma curves applied to the colors before the brightness. They're referenced rather than
+    /// copied with the settings, since their tables take 1.5 KB.
+    gamma: &'static Gamma,
+
     /// Number of colors available for the animations.
     num_colors: usize,
+
+    /// Maximum current the LEDs may draw.
+    power_budget: PowerBudget,
 }
 
 impl Settings {
@@ -652,11 +813,22 @@ impl Settings {
         color_index: usize, brightness: u16, delay: u16, analog_max_value: u16, num_colors: usize,
     ) -> Self {
         Self {
-            brightness: calculate_brightness(brightness, analog_max_value),
+            brightness: calculate_brightness(
+                brightness,
+                analog_max_value,
+                BrightnessCurve::default(),
+            ),
+            brightness_curve: BrightnessCurve::default(),
+            brightness_reading: brightness,
+            color_corrections: [ColorCorrection::UNCORRECTED; 2],
             color_index,
+            custom_color: None,
             delay: calculate_delay(delay, analog_max_value),
+            dithering: true,
             analog_max_value,
+            gamma: &Gamma::DEFAULT,
             num_colors,
+            power_budget: PowerBudget::DEFAULT,
         }
     }
 
@@ -666,8 +838,19 @@ impl Settings {
     }
 
     #[must_use]
-    pub fn brightness_damped(&self, factor: f32) -> u8 {
-        (f32::from(self.brightness) * factor) as u8
+    pub fn brightness_curve(&self) -> BrightnessCurve {
+        self.brightness_curve
+    }
+
+    #[must_use]
+    pub fn color_correction(&self, section: Section) -> &ColorCorrection {
+        &self.color_corrections[section as usize]
+    }
+
+    /// The color used by the uni-color animations: the custom one, or the one of the palette.
+    #[must_use]
+    pub fn color(&self) -> RGB16 {
+        self.custom_color.map_or(COLORS[self.color_index], widen)
     }
 
     #[must_use]
@@ -675,27 +858,95 @@ impl Settings {
         self.color_index
     }
 
+    #[must_use]
+    pub fn custom_color(&self) -> Option<RGB8> {
+        self.custom_color
+    }
+
     #[must_use]
     pub fn delay(&self) -> u32 {
         self.delay
     }
 
+    #[must_use]
+    pub fn dithering(&self) -> bool {
+        self.dithering
+    }
+
+    #[must_use]
+    pub fn gamma(&self) -> &'static Gamma {
+        self.gamma
+    }
+
+    /// Number of colors in the palette.
+    #[must_use]
+    pub fn num_colors(&self) -> usize {
+        self.num_colors
+    }
+
+    #[must_use]
+    pub fn power_budget(&self) -> &PowerBudget {
+        &self.power_budget
+    }
+
     /// Increment the color index and wrap around if it exceeds the number of colors.
     pub fn increment_color_index(&mut self) {
-        self.color_index = (self.color_index + 1) % self.num_colors;
+        self.set_color_index((self.color_index + 1) % self.num_colors);
     }
 
     pub fn set_brightness(&mut self, brightness: u16) {
-        self.brightness = calculate_brightness(brightness, self.analog_max_value);
+        self.brightness_reading = brightness;
+        self.brightness =
+            calculate_brightness(brightness, self.analog_max_value, self.brightness_curve);
+    }
+
+    /// Sets the brightness directly, until the next reading of the potentiometer.
+    pub fn set_brightness_level(&mut self, brightness: u8) {
+        self.brightness = brightness;
     }
 
+    /// Changes the curve of the brightness, and applies it to the current reading.
+    pub fn set_brightness_curve(&mut self, brightness_curve: BrightnessCurve) {
+        self.brightness_curve = brightness_curve;
+        self.set_brightness(self.brightness_reading);
+    }
+
+    pub fn set_color_correction(&mut self, section: Section, color_correction: ColorCorrection) {
+        self.color_corrections[section as usize] = color_correction;
+    }
+
+    /// Selects a color of the palette, instead of the custom color.
     pub fn set_color_index(&mut self, color_index: usize) {
         self.color_index = color_index;
+        self.custom_color = None;
+    }
+
+    /// Selects a color outside of the palette, until another color is selected.
+    pub fn set_custom_color(&mut self, color: RGB8) {
+        self.custom_color = Some(color);
     }
 
     pub fn set_delay(&mut self, delay: u16) {
         self.delay = calculate_delay(delay, self.analog_max_value);
     }
+
+    /// Sets the delay between frames directly, until the next reading of the potentiometer. It's
+    /// at least 1 millisecond.
+    pub fn set_delay_milliseconds(&mut self, milliseconds: u32) {
+        self.delay = cmp::max(milliseconds, 1);
+    }
+
+    pub fn set_dithering(&mut self, dithering: bool) {
+        self.dithering = dithering;
+    }
+
+    pub fn set_gamma(&mut self, gamma: &'static Gamma) {
+        self.gamma = gamma;
+    }
+
+    pub fn set_power_budget(&mut self, power_budget: PowerBudget) {
+        self.power_budget = power_budget;
+    }
 }
 
 #[must_use]
@@ -707,51 +958,44 @@ pub fn calculate_index(value: u16, max_value: u16, num_values: usize) -> usize {
 /// Create a new [`LedData`] structure initialized with default colors.
 #[must_use]
 pub fn create_data() -> LedData {
-    [RGB8::default(); LEDS_TOTAL]
+    [RGB16::default(); LEDS_TOTAL]
 }
 
 /// Resets the LEDs data to its default state.
 pub fn reset_data(data: &mut LedData) {
-    *data = [RGB8::default(); LEDS_TOTAL];
+    *data = [RGB16::default(); LEDS_TOTAL];
 }
 
-/// Correct the RGB8 color based on the brightness value.
+/// Correct the color based on the brightness value, without losing its precision.
+///
+/// A brightness of 0 turns the LED off, and 255 leaves the color unchanged.
 ///
-/// [`gamma_correct`] should be called before this function to apply gamma correction properly.
+/// [`Gamma::correct`] should be called before this function to apply gamma correction properly.
 #[must_use]
-pub(crate) fn brightness_correct(color: RGB8, brightness: u8) -> RGB8 {
-    RGB8 {
-        r: (u16::from(color.r) * (u16::from(brightness) + 1) / 256) as u8,
-        g: (u16::from(color.g) * (u16::from(brightness) + 1) / 256) as u8,
-        b: (u16::from(color.b) * (u16::from(brightness) + 1) / 256) as u8,
+pub(crate) fn brightness_correct(color: RGB16, brightness: u8) -> RGB16 {
+    RGB16 {
+        r: (u32::from(color.r) * u32::from(brightness) / 255) as u16,
+        g: (u32::from(color.g) * u32::from(brightness) / 255) as u16,
+        b: (u32::from(color.b) * u32::from(brightness) / 255) as u16,
     }
 }
 
-/// Apply gamma correction to the provided RGB8 color.
-pub(crate) fn gamma_correct(color: RGB8) -> RGB8 {
-    RGB8 {
-        r: GAMMA8[usize::from(color.r)],
-        g: GAMMA8[usize::from(color.g)],
-        b: GAMMA8[usize::from(color.b)],
+/// Convert the RGB8 color to 16 bits per channel, without changing its value.
+#[must_use]
+pub const fn widen(color: RGB8) -> RGB16 {
+    RGB16 {
+        r: color.r as u16 * dithering::STEP,
+        g: color.g as u16 * dithering::STEP,
+        b: color.b as u16 * dithering::STEP,
     }
 }
 
-/// Measure the time taken to execute a function and log it.
-#[allow(dead_code)]
-pub(crate) fn time_function(function: impl FnOnce(), message: &str) {
-    let start = Instant::now();
-
-    function();
-
-    let elapsed = start.elapsed();
-    trace!("{}: {} us", message, elapsed.as_micros());
-}
-
-/// Calculate the brightness based on the value of the potentiometer reading.
+/// Calculate the brightness based on the value of the potentiometer reading and the curve.
 ///
 /// The value is between 0 and 255.
-fn calculate_brightness(value: u16, max_value: u16) -> u8 {
-    (f32::from(value) / f32::from(max_value) * 255_f32).clamp(0.0, 255.0) as u8
+fn calculate_brightness(value: u16, max_value: u16, curve: BrightnessCurve) -> u8 {
+    let position = f32::from(value) / f32::from(max_value);
+    (curve.apply(position) * 255_f32).clamp(0.0, 255.0) as u8
 }
 
 /// Calculate the delay in milliseconds based on the value of the potentiometer reading.
@@ -762,39 +1006,207 @@ fn calculate_delay(value: u16, max_value: u16) -> u32 {
     cmp::max((f32::from(value) / f32::from(max_value) * 1000.0) as u32, 1)
 }
 
-/// Renders LED data to two separate LED sections with brightness damping, gamma correction, and a
-/// configurable delay.
+/// Waits for the given number of milliseconds before the next frame, and records it as idle time.
+pub(crate) async fn wait_next_frame(delay: &mut impl DelayNs, milliseconds: u32) {
+    let start = Instant::now();
+
+    delay.delay_ms(milliseconds).await;
+
+    telemetry::record_idle(start.elapsed());
+}
+
+/// Writes the colors to the two LED sections simultaneously, and records the time taken by each
+/// write.
 ///
-/// This function writes LED data to two different sections (e.g., `leds_section_1` and
-/// `leds_section_2`) simultaneously, applying gamma correction and brightness damping during the
-/// process. After updating the LED sections, it inserts a delay as specified in the provided
-/// settings.
-async fn render(
-    data: &LedData, leds_section_1: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-    leds_section_2: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-    brightness_damping_factor: f32, delay: &mut impl DelayNs, settings: &Settings,
+/// Each color is corrected with 16 bits per channel before it's written, then scaled by the color
+/// correction of its section. The frame is scaled down when it would draw more current than the
+/// power budget allows, then converted to the colors of the LEDs of each section, quantized to 8
+/// bits for most of them. A section that fails doesn't prevent the other one from being written.
+pub(crate) async fn write_sections(
+    data: &LedData,
+    leds_section_1: &mut impl SmartLedsWrite<Color = impl LedColor, Error = impl Debug>,
+    leds_section_2: &mut impl SmartLedsWrite<Color = impl LedColor, Error = impl Debug>,
+    dithering: &mut Dithering, settings: &Settings, correct: impl Fn(RGB16) -> RGB16 + Copy,
+) -> Result<(), WriteError> {
+    let [colors_section_1, colors_section_2] =
+        with_residuals(correct_sections(data, settings, correct), dithering);
+
+    let (failed_section_1, failed_section_2) = join(
+        write_section(
+            Section::First,
+            leds_section_1,
+            colors_section_1,
+            settings.dithering(),
+        ),
+        write_section(
+            Section::Second,
+            leds_section_2,
+            colors_section_2,
+            settings.dithering(),
+        ),
+    )
+    .await;
+
+    sections_written(failed_section_1, failed_section_2)
+}
+
+/// Writes the colors to the two LED sections like [`write_sections`], with drivers that complete
+/// their write once it's sent. Both sections are sent at the same time.
+pub(crate) async fn write_sections_async(
+    data: &LedData,
+    leds_section_1: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    leds_section_2: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    dithering: &mut Dithering, settings: &Settings, correct: impl Fn(RGB16) -> RGB16 + Copy,
+) -> Result<(), WriteError> {
+    let colors = with_residuals(correct_sections(data, settings, correct), dithering);
+
+    write_colors_async(colors, leds_section_1, leds_section_2, settings.dithering()).await
+}
+
+/// Corrects the colors of the two sections and limits them to the power budget like
+/// [`write_sections`], so that they only have to be quantized when they're written.
+pub(crate) fn prepare_sections(
+    data: &LedData, prepared: &mut LedData, settings: &Settings,
+    correct: impl Fn(RGB16) -> RGB16 + Copy,
 ) {
-    let leds_section_1_future = async {
-        leds_section_1
-            .write(brightness(
-                gamma(data[LEDS_SECTION_1_RANGE].iter().copied()),
-                settings.brightness_damped(brightness_damping_factor),
-            ))
-            .unwrap();
-    };
+    let [colors_section_1, colors_section_2] = correct_sections(data, settings, correct);
+
+    for (led, color) in prepared
+        .iter_mut()
+        .zip(colors_section_1.chain(colors_section_2))
+    {
+        *led = color;
+    }
+}
+
+/// Writes the colors of [`prepare_sections`] to the two LED sections like
+/// [`write_sections_async`].
+pub(crate) async fn write_prepared_sections_async(
+    prepared: &LedData,
+    leds_section_1: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    leds_section_2: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    dithering: &mut Dithering, dithering_enabled: bool,
+) -> Result<(), WriteError> {
+    let sections = [
+        &prepared[LEDS_SECTION_1_RANGE],
+        &prepared[LEDS_SECTION_2_RANGE],
+    ]
+    .map(|leds| leds.iter().copied());
+
+    write_colors_async(
+        with_residuals(sections, dithering),
+        leds_section_1,
+        leds_section_2,
+        dithering_enabled,
+    )
+    .await
+}
 
-    let leds_section_2_future = async {
-        leds_section_2
-            .write(brightness(
-                gamma(data[LEDS_SECTION_2_RANGE].iter().copied()),
-                settings.brightness_damped(brightness_damping_factor),
-            ))
-            .unwrap();
+/// Corrects the colors of the two sections, and limits them to the power budget.
+fn correct_sections<'a>(
+    data: &'a LedData, settings: &'a Settings, correct: impl Fn(RGB16) -> RGB16 + Copy + 'a,
+) -> [impl Iterator<Item = RGB16> + 'a; 2] {
+    let colors = |section: Section, leds: &'a [RGB16]| {
+        let color_correction = settings.color_correction(section);
+        leds.iter()
+            .map(move |color| color_correction.apply(correct(*color)))
     };
+    let colors_section_1 = colors(Section::First, &data[LEDS_SECTION_1_RANGE]);
+    let colors_section_2 = colors(Section::Second, &data[LEDS_SECTION_2_RANGE]);
+
+    let [brightness_section_1, brightness_section_2] = settings.power_budget().limit(
+        [
+            Draw::of(colors_section_1.clone()),
+            Draw::of(colors_section_2.clone()),
+        ],
+        settings.dithering(),
+    );
+
+    let limit = |brightness: u8| move |color: RGB16| brightness_correct(color, brightness);
+
+    [
+        colors_section_1.map(limit(brightness_section_1)),
+        colors_section_2.map(limit(brightness_section_2)),
+    ]
+}
 
-    join(leds_section_1_future, leds_section_2_future).await;
+/// Pairs the colors of the two sections with their dithering residuals.
+fn with_residuals(
+    [colors_section_1, colors_section_2]: [impl Iterator<Item = RGB16>; 2],
+    dithering: &mut Dithering,
+) -> [impl Iterator<Item = (RGB16, &mut [u8; 3])>; 2] {
+    let (residuals_section_1, residuals_section_2) = dithering.sections();
 
-    delay.delay_ms(settings.delay()).await;
+    [
+        colors_section_1.zip(residuals_section_1),
+        colors_section_2.zip(residuals_section_2),
+    ]
+}
+
+/// Writes the colors of the two sections with their dithering residuals, at the same time.
+async fn write_colors_async<'a>(
+    [colors_section_1, colors_section_2]: [impl Iterator<Item = (RGB16, &'a mut [u8; 3])>; 2],
+    leds_section_1: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    leds_section_2: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+    dithering: bool,
+) -> Result<(), WriteError> {
+    let (failed_section_1, failed_section_2) = join(
+        write_section_async(Section::First, leds_section_1, colors_section_1, dithering),
+        write_section_async(Section::Second, leds_section_2, colors_section_2, dithering),
+    )
+    .await;
+
+    sections_written(failed_section_1, failed_section_2)
+}
+
+fn sections_written(failed_section_1: bool, failed_section_2: bool) -> Result<(), WriteError> {
+    if failed_section_1 || failed_section_2 {
+        Err(WriteError::new(failed_section_1, failed_section_2))
+    } else {
+        Ok(())
+    }
+}
+
+/// Writes the colors to a LED section with their dithering residuals, and returns whether it
+/// failed.
+async fn write_section<'a, C: LedColor>(
+    section: Section, leds: &mut impl SmartLedsWrite<Color = C, Error = impl Debug>,
+    colors: impl Iterator<Item = (RGB16, &'a mut [u8; 3])>, dithering: bool,
+) -> bool {
+    let start = Instant::now();
+    let colors = colors.map(|(color, residual)| C::quantize(color, residual, dithering));
+
+    section_written(section, start, leds.write(colors))
+}
+
+/// Writes the colors to a LED section like [`write_section`], once the driver completes the write.
+async fn write_section_async<'a, C: LedColor>(
+    section: Section, leds: &mut impl SmartLedsWriteAsync<Color = C, Error = impl Debug>,
+    colors: impl Iterator<Item = (RGB16, &'a mut [u8; 3])>, dithering: bool,
+) -> bool {
+    let start = Instant::now();
+    let colors = colors.map(|(color, residual)| C::quantize(color, residual, dithering));
+
+    section_written(section, start, leds.write(colors).await)
+}
+
+/// Records the time taken by the write of a section, or its error, and returns whether it failed.
+fn section_written(section: Section, start: Instant, result: Result<(), impl Debug>) -> bool {
+    match result {
+        Ok(()) => {
+            telemetry::record_write(section, start.elapsed());
+            false
+        }
+        Err(error) => {
+            error!(
+                "Cannot write the {} section: {}",
+                section,
+                Debug2Format(&error)
+            );
+            telemetry::record_write_error(section);
+            true
+        }
+    }
 }
 
 pub mod carrousel;
@@ -808,6 +1220,7 @@ pub mod multi_color_solid_random;
 pub mod multi_color_sparkle;
 pub mod multi_color_strand;
 pub mod shimmer;
+pub mod streaming;
 pub mod uni_color_fade_in;
 pub mod uni_color_front_to_back_wave;
 pub mod uni_color_heartbeat;
@@ -9,8 +9,6 @@ pub struct Carrousel {
 }
 
 impl Carrousel {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.05;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
         let color_index = prng.random_range(0..COLORS_TOTAL);
@@ -13,8 +13,6 @@ pub struct DoubleCarrousel {
 }
 
 impl DoubleCarrousel {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.05;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
         let color_index_1 = prng.random_range(0..COLORS_TOTAL);
@@ -1,13 +1,6 @@
-use crate::animations::{
-    COLORS, COLORS_TOTAL, LEDS_SECTION_1_RANGE, LEDS_SECTION_2_RANGE, LedData, Settings,
-};
-use core::fmt::Debug;
-use embassy_futures::join::join;
-use embedded_hal_async::delay::DelayNs;
+use crate::animations::{COLORS, COLORS_TOTAL, Frame, LedData, Settings};
 use rand::rngs::SmallRng;
 use rand::{RngExt, SeedableRng};
-use smart_leds::{RGB8, gamma};
-use smart_leds_trait::SmartLedsWrite;
 
 const STEP: u8 = 23;
 
@@ -19,8 +12,6 @@ pub struct MultiColorFadeIn {
 }
 
 impl MultiColorFadeIn {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.05;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
         Self {
@@ -31,37 +22,11 @@ impl MultiColorFadeIn {
         }
     }
 
-    pub(crate) async fn render(
-        &mut self, data: &LedData,
-        leds_section_1: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        leds_section_2: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        delay: &mut impl DelayNs, settings: &Settings,
-    ) {
-        let brightness = (f32::from(settings.brightness_damped(Self::BRIGHTNESS_DAMPING_FACTOR))
-            * f32::from(self.current_step)
+    pub(crate) fn frame(&self, settings: &Settings) -> Frame {
+        let brightness = (f32::from(settings.brightness()) * f32::from(self.current_step)
             / f32::from(STEP)) as u8;
 
-        let leds_section_1_future = async {
-            leds_section_1
-                .write(smart_leds::brightness(
-                    gamma(data[LEDS_SECTION_1_RANGE].iter().copied()),
-                    brightness,
-                ))
-                .unwrap();
-        };
-
-        let leds_section_2_future = async {
-            leds_section_2
-                .write(smart_leds::brightness(
-                    gamma(data[LEDS_SECTION_2_RANGE].iter().copied()),
-                    brightness,
-                ))
-                .unwrap();
-        };
-
-        join(leds_section_1_future, leds_section_2_future).await;
-
-        delay.delay_ms(settings.delay()).await;
+        Frame::dimmed(brightness, settings.delay())
     }
 
     pub(crate) fn update(&mut self, data: &mut LedData) {
@@ -13,8 +13,6 @@ pub struct MultiColorFrontToBackWave {
 }
 
 impl MultiColorFrontToBackWave {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.2;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
 
@@ -5,8 +5,6 @@ const LEDS_PER_COLOR: usize = LEDS_TOTAL / COLORS_TOTAL;
 pub struct MultiColorSolid {}
 
 impl MultiColorSolid {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.05;
-
     pub(crate) fn new() -> Self {
         Self {}
     }
@@ -1,27 +1,26 @@
+use crate::animations;
 use crate::animations::{LEDS_TOTAL, LedData};
 use rand::rngs::SmallRng;
 use rand::{RngExt, SeedableRng};
-use smart_leds::RGB8;
+use smart_leds::RGB16;
 
 pub struct MultiColorSolidRandom {
     prng: SmallRng,
-    rendered_data: [RGB8; LEDS_TOTAL],
+    rendered_data: LedData,
 }
 
 impl MultiColorSolidRandom {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.2;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut animation = Self {
             prng: SmallRng::seed_from_u64(random_seed),
-            rendered_data: [RGB8::default(); LEDS_TOTAL],
+            rendered_data: animations::create_data(),
         };
 
         for i in 0..LEDS_TOTAL {
-            let random_color = RGB8::new(
-                animation.prng.random_range(0..=u8::MAX),
-                animation.prng.random_range(0..=u8::MAX),
-                animation.prng.random_range(0..=u8::MAX),
+            let random_color = RGB16::new(
+                animation.prng.random_range(0..=u16::MAX),
+                animation.prng.random_range(0..=u16::MAX),
+                animation.prng.random_range(0..=u16::MAX),
             );
             animation.rendered_data[i] = random_color;
         }
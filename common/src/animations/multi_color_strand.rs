@@ -2,17 +2,17 @@ use crate::animations;
 use crate::animations::{LEDS_TOTAL, LedData};
 use rand::rngs::SmallRng;
 use rand::{RngExt, SeedableRng};
-use smart_leds::RGB8;
+use smart_leds::RGB16;
 use smart_leds::colors::{BLUE, DARK_RED, DARK_TURQUOISE, INDIGO, MIDNIGHT_BLUE, PURPLE, RED};
 
-const COLORS: [RGB8; 7] = [
-    RED,
-    DARK_RED,
-    DARK_TURQUOISE,
-    BLUE,
-    MIDNIGHT_BLUE,
-    PURPLE,
-    INDIGO,
+const COLORS: [RGB16; 7] = [
+    animations::widen(RED),
+    animations::widen(DARK_RED),
+    animations::widen(DARK_TURQUOISE),
+    animations::widen(BLUE),
+    animations::widen(MIDNIGHT_BLUE),
+    animations::widen(PURPLE),
+    animations::widen(INDIGO),
 ];
 const NUM_STRANDS: usize = LEDS_TOTAL / 7;
 
@@ -21,8 +21,6 @@ pub struct MultiColorStrand {
 }
 
 impl MultiColorStrand {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 1.0;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
 
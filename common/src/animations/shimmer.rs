@@ -1,3 +1,4 @@
+use crate::animations;
 use crate::animations::{LEDS_TOTAL, LedData};
 use rand::rngs::SmallRng;
 use rand::{RngExt, SeedableRng};
@@ -8,8 +9,6 @@ pub struct Shimmer {
 }
 
 impl Shimmer {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.2;
-
     pub(crate) fn new(random_seed: u64) -> Self {
         let mut prng = SmallRng::seed_from_u64(random_seed);
         Self {
@@ -23,7 +22,7 @@ impl Shimmer {
     }
 
     pub(crate) fn update(&mut self, data: &mut LedData) {
-        let rgb = hsv2rgb(self.hsv);
+        let rgb = animations::widen(hsv2rgb(self.hsv));
         *data = [rgb; LEDS_TOTAL];
         self.hsv.hue = self.hsv.hue.wrapping_add(1);
     }
@@ -0,0 +1,24 @@
+use crate::animations::{Frame, LedData, Settings};
+use crate::stream;
+
+/// The delay between the frames, short so that the stream is shown as it's received.
+const DELAY: u32 = 10;
+
+/// Shows the frame of the stream, split across the two sections in the order of the LEDs.
+pub struct Streaming {}
+
+impl Streaming {
+    pub(crate) fn new() -> Self {
+        Self {}
+    }
+
+    pub(crate) fn update(&mut self, data: &mut LedData) {
+        stream::read(data);
+    }
+
+    /// The colors are corrected like the ones of the other animations, so that the brightness
+    /// still applies.
+    pub(crate) fn frame(&self, settings: &Settings) -> Frame {
+        Frame::dimmed(settings.brightness(), DELAY)
+    }
+}
@@ -1,13 +1,11 @@
 use crate::animations;
-use crate::animations::{COLORS, LedData, Settings, VERTICAL_SLICES};
+use crate::animations::{LedData, Settings, VERTICAL_SLICES};
 
 pub struct UniColorFrontToBackWave {
     position: usize,
 }
 
 impl UniColorFrontToBackWave {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 1.0;
-
     pub(crate) fn new() -> Self {
         Self { position: 0 }
     }
@@ -19,7 +17,7 @@ impl UniColorFrontToBackWave {
 
         for led in slice {
             led.map(|l| {
-                data[usize::from(l)] = COLORS[settings.color_index()];
+                data[usize::from(l)] = settings.color();
             });
         }
 
@@ -1,9 +1,4 @@
-use crate::animations::{COLORS, LEDS_SECTION_1_RANGE, LEDS_SECTION_2_RANGE, LedData, Settings};
-use core::fmt::Debug;
-use embassy_futures::join::join;
-use embedded_hal_async::delay::DelayNs;
-use smart_leds::{RGB8, gamma};
-use smart_leds_trait::SmartLedsWrite;
+use crate::animations::{Frame, LedData, Settings};
 
 const STEP: u8 = 10;
 
@@ -13,8 +8,6 @@ pub struct UniColorHeartbeat {
 }
 
 impl UniColorHeartbeat {
-    pub(crate) const BRIGHTNESS_DAMPING_FACTOR: f32 = 0.05;
-
     pub(crate) fn new() -> Self {
         Self {
             current_step: 0,
@@ -22,45 +15,21 @@ impl UniColorHeartbeat {
         }
     }
 
-    pub(crate) async fn render(
-        &mut self, data: &LedData,
-        leds_section_1: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        leds_section_2: &mut impl SmartLedsWrite<Color = RGB8, Error = impl Debug>,
-        delay: &mut impl DelayNs, settings: &Settings,
-    ) {
-        let brightness = (f32::from(settings.brightness_damped(Self::BRIGHTNESS_DAMPING_FACTOR))
-            * f32::from(self.current_step)
+    pub(crate) fn frame(&self, settings: &Settings) -> Frame {
+        let brightness = (f32::from(settings.brightness()) * f32::from(self.current_step)
             / f32::from(STEP)) as u8;
 
-        let leds_section_1_future = async {
-            leds_section_1
-                .write(smart_leds::brightness(
-                    gamma(data[LEDS_SECTION_1_RANGE].iter().copied()),
-                    brightness,
-                ))
-                .unwrap();
-        };
-
-        let leds_section_2_future = async {
-            leds_section_2
-                .write(smart_leds::brightness(
-                    gamma(data[LEDS_SECTION_2_RANGE].iter().copied()),
-                    brightness,
-                ))
-                .unwrap();
+        let delay = match self.sequence {
+            3 => settings.delay() * 30,
+            _ => settings.delay(),
         };
 
-        join(leds_section_1_future, leds_section_2_future).await;
-
-        match self.sequence {
-            3 => delay.delay_ms(settings.delay() * 30).await,
-            _ => delay.delay_ms(settings.delay()).await,
-        }
+        Frame::dimmed(brightness, delay)
     }
 
     pub(crate) fn update(&mut self, data: &mut LedData, settings: &Settings) {
         for led in data {
-            *led = COLORS[settings.color_index()];
+            *led = settings.color();
         }
 
         match self.sequence {
@@ -0,0 +1,140 @@
+//! Driver of the APA102 and SK9822 strips, which take their data with a clock over SPI.
+//!
+//! On top of its 8-bit channels, each LED has a 5-bit global brightness dimming all of them. The
+//! colors are written with 16 bits per channel, and the global brightness of each LED is the lowest
+//! one that can still show its brightest channel. The channels then keep most of their 8 bits even
+//! in the dark, which gives much more dynamic range than the 8 bits of the WS2812.
+//!
+//! The frames end with zeros, which the SK9822 needs to latch the colors and the APA102 ignores.
+
+use crate::dithering::STEP;
+use embedded_hal::spi::SpiBus;
+use embedded_hal_async::spi::SpiBus as SpiBusAsync;
+use smart_leds::RGB16;
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync};
+
+/// The 32 zero bits starting the frames.
+const START_FRAME_SIZE: usize = 4;
+
+/// The bytes of each LED: the global brightness, then the blue, green and red channels.
+const LED_SIZE: usize = 4;
+
+/// The highest global brightness, on 5 bits.
+const MAX_BRIGHTNESS: u32 = 31;
+
+/// The size of the buffer needed to write the given number of LEDs.
+#[must_use]
+pub const fn buffer_size(leds: usize) -> usize {
+    START_FRAME_SIZE + leds * LED_SIZE + end_frame_size(leds)
+}
+
+/// The SK9822 latches the colors with 32 zero bits, and the data of the last LEDs only reaches them
+/// after half a clock edge per LED.
+const fn end_frame_size(leds: usize) -> usize {
+    4 + leds.div_ceil(16)
+}
+
+/// Encodes the color of a LED, with the lowest global brightness showing its brightest channel.
+#[must_use]
+pub fn encode(color: RGB16) -> [u8; LED_SIZE] {
+    let max = color.r.max(color.g).max(color.b);
+    let brightness = (u32::from(max) * MAX_BRIGHTNESS).div_ceil(u32::from(u16::MAX));
+    if brightness == 0 {
+        return [0xE0, 0, 0, 0];
+    }
+
+    // The channels are scaled up by as much as the global brightness dims them, and the brightest
+    // one can't exceed 255 since the brightness is rounded up.
+    let scale =
+        |channel: u16| (u32::from(channel) * MAX_BRIGHTNESS / (brightness * u32::from(STEP))) as u8;
+
+    [
+        0xE0 | brightness as u8,
+        scale(color.b),
+        scale(color.g),
+        scale(color.r),
+    ]
+}
+
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The buffer is too small for the number of LEDs, see [`buffer_size`].
+    OutOfBounds,
+
+    Spi(E),
+}
+
+/// An APA102 or SK9822 strip, with its colors encoded in a buffer before they're sent at once.
+pub struct Apa102<'a, SPI> {
+    spi: SPI,
+    buffer: &'a mut [u8],
+}
+
+impl<'a, SPI> Apa102<'a, SPI> {
+    /// The SPI bus runs in mode 0, and the buffer must hold the LEDs, see [`buffer_size`].
+    pub fn new(spi: SPI, buffer: &'a mut [u8]) -> Self {
+        Self { spi, buffer }
+    }
+
+    /// Encodes the frame in the buffer, and returns its length.
+    fn encode_frame<E>(
+        &mut self, colors: impl IntoIterator<Item = impl Into<RGB16>>,
+    ) -> Result<usize, Error<E>> {
+        self.buffer[..START_FRAME_SIZE].fill(0);
+
+        let mut leds = 0;
+        let mut chunks = self.buffer[START_FRAME_SIZE..].chunks_exact_mut(LED_SIZE);
+        for color in colors {
+            let chunk = chunks.next().ok_or(Error::OutOfBounds)?;
+            chunk.copy_from_slice(&encode(color.into()));
+            leds += 1;
+        }
+
+        let length = buffer_size(leds);
+        let end_frame = self
+            .buffer
+            .get_mut(START_FRAME_SIZE + leds * LED_SIZE..length)
+            .ok_or(Error::OutOfBounds)?;
+        end_frame.fill(0);
+
+        Ok(length)
+    }
+}
+
+impl<SPI: SpiBus<u8>> SmartLedsWrite for Apa102<'_, SPI> {
+    type Color = RGB16;
+    type Error = Error<SPI::Error>;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let length = self.encode_frame(iterator)?;
+
+        self.spi
+            .write(&self.buffer[..length])
+            .and_then(|()| self.spi.flush())
+            .map_err(Error::Spi)
+    }
+}
+
+/// Sends the frame with an async SPI bus, so that the write completes once its DMA transfer does.
+impl<SPI: SpiBusAsync<u8>> SmartLedsWriteAsync for Apa102<'_, SPI> {
+    type Color = RGB16;
+    type Error = Error<SPI::Error>;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let length = self.encode_frame(iterator)?;
+
+        self.spi
+            .write(&self.buffer[..length])
+            .await
+            .map_err(Error::Spi)?;
+        self.spi.flush().await.map_err(Error::Spi)
+    }
+}
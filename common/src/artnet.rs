@@ -0,0 +1,315 @@
+//! Art-Net, the protocol of the lighting consoles, which carries the DMX512 universes over UDP.
+//!
+//! A universe is addressed by its 15-bit port address: its net, its subnet and its universe within
+//! the subnet. The node writes the ArtDMX packets of its port addresses to the stream, mapped on
+//! the LEDs like the universes of the other DMX512 protocols in [`crate::universe`], and answers
+//! the ArtPoll packets of the controllers with the ArtPollReply packets describing them.
+
+use crate::universe::{Frame, Layout};
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::ops::RangeInclusive;
+use defmt::Format;
+use embassy_time::Instant;
+
+/// The UDP port of Art-Net, which the nodes and the controllers send to.
+pub const PORT: u16 = 6454;
+
+/// The size of an ArtPollReply packet.
+pub const POLL_REPLY_SIZE: usize = 239;
+
+/// The identifier starting every packet.
+const ID: &[u8; 8] = b"Art-Net\0";
+
+const OP_POLL: u16 = 0x2000;
+const OP_POLL_REPLY: u16 = 0x2100;
+const OP_DMX: u16 = 0x5000;
+
+/// The oldest version of the protocol with the packets as they're parsed.
+const PROTOCOL_VERSION: u16 = 14;
+
+/// The size of the identifier, the operation code and the version of the protocol.
+const HEADER_SIZE: usize = 12;
+
+/// The size of an ArtDMX packet before its channels.
+const DMX_HEADER_SIZE: usize = 18;
+
+/// The valid port addresses, of 15 bits.
+const PORT_ADDRESSES: RangeInclusive<u16> = 0..=32_767;
+
+/// The port addresses described by each ArtPollReply, which share their net and subnet.
+const PORTS_PER_REPLY: u16 = 4;
+
+/// The code of the manufacturers that don't have one.
+const OEM_UNKNOWN: u16 = 0x00FF;
+
+/// The indicators are in their normal mode, and the port addresses are set by the firmware.
+const STATUS_1: u8 = 0b1101_0000;
+
+/// The address is set by DHCP, and the port addresses are of 15 bits.
+const STATUS_2: u8 = 0b0000_1110;
+
+/// The port outputs the universe received over Art-Net to DMX512 LEDs.
+const PORT_TYPE_OUTPUT: u8 = 0x80;
+
+const SHORT_NAME: &[u8] = b"Cookie Monster";
+const LONG_NAME: &[u8] = b"Cookie Monster LED strips";
+const NODE_REPORT: &[u8] = b"#0001 [0000] Running";
+
+/// Why an Art-Net packet was rejected.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum PacketError {
+    /// The packet is shorter than its header, or than the channels it holds.
+    TooShort,
+
+    /// The packet isn't an Art-Net packet.
+    InvalidId,
+
+    /// The packet is neither an ArtPoll nor an ArtDMX packet.
+    UnsupportedOpCode,
+
+    /// The packet is of a version of the protocol older than 14.
+    UnsupportedVersion,
+
+    /// The number of channels isn't an even number from 2 to 512.
+    InvalidLength,
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketError::TooShort => "packet too short",
+            PacketError::InvalidId => "not an Art-Net packet",
+            PacketError::UnsupportedOpCode => "unsupported operation",
+            PacketError::UnsupportedVersion => "unsupported protocol version",
+            PacketError::InvalidLength => "invalid number of channels",
+        })
+    }
+}
+
+/// The packets received by the node.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Packet<'a> {
+    /// A controller discovers the nodes, which answer with their ArtPollReply packets.
+    Poll,
+
+    /// The channels of a universe.
+    Dmx(DmxPacket<'a>),
+}
+
+/// An ArtDMX packet, with the channels of a universe.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DmxPacket<'a> {
+    /// From 1 to 255 to reorder the packets, or 0 when the controller doesn't number them.
+    pub sequence: u8,
+
+    /// The input of the controller the universe comes from.
+    pub physical: u8,
+
+    pub port_address: u16,
+
+    /// From 2 to 512 channels, starting from the first one.
+    pub channels: &'a [u8],
+}
+
+/// Parses an ArtPoll or ArtDMX packet.
+///
+/// # Errors
+///
+/// Returns why the packet isn't one of them, or isn't valid.
+pub fn parse(packet: &[u8]) -> Result<Packet<'_>, PacketError> {
+    let header = packet.get(..HEADER_SIZE).ok_or(PacketError::TooShort)?;
+    if &header[..8] != ID {
+        return Err(PacketError::InvalidId);
+    }
+    let op_code = u16::from_le_bytes([header[8], header[9]]);
+    if op_code != OP_POLL && op_code != OP_DMX {
+        return Err(PacketError::UnsupportedOpCode);
+    }
+    if u16::from_be_bytes([header[10], header[11]]) < PROTOCOL_VERSION {
+        return Err(PacketError::UnsupportedVersion);
+    }
+    if op_code == OP_POLL {
+        return Ok(Packet::Poll);
+    }
+
+    let header = packet.get(..DMX_HEADER_SIZE).ok_or(PacketError::TooShort)?;
+    let length = usize::from(u16::from_be_bytes([header[16], header[17]]));
+    if !(2..=512).contains(&length) || length % 2 != 0 {
+        return Err(PacketError::InvalidLength);
+    }
+    let channels = packet
+        .get(DMX_HEADER_SIZE..DMX_HEADER_SIZE + length)
+        .ok_or(PacketError::TooShort)?;
+
+    Ok(Packet::Dmx(DmxPacket {
+        sequence: header[12],
+        physical: header[13],
+        // The subnet and the universe are in the low byte, and the net in the high one.
+        port_address: u16::from_le_bytes([header[14], header[15]]) & *PORT_ADDRESSES.end(),
+        channels,
+    }))
+}
+
+/// The port address of the universe of a subnet of a net.
+///
+/// # Panics
+///
+/// Panics when the net isn't from 0 to 127, or the subnet or the universe from 0 to 15.
+#[must_use]
+pub const fn port_address(net: u8, subnet: u8, universe: u8) -> u16 {
+    assert!(net < 128, "the net is from 0 to 127");
+    assert!(subnet < 16, "the subnet is from 0 to 15");
+    assert!(universe < 16, "the universe is from 0 to 15");
+    (net as u16) << 8 | (subnet as u16) << 4 | universe as u16
+}
+
+/// Where the port addresses are mapped on the LEDs.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub struct Mapping {
+    layout: Layout,
+}
+
+impl Mapping {
+    /// Maps the LEDs from the start channel of the port address, the first channel being 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the port address isn't from 0 to 32767, or the start channel from 1 to 510.
+    #[must_use]
+    pub const fn new(port_address: u16, start_channel: u16) -> Self {
+        assert!(
+            port_address <= *PORT_ADDRESSES.end(),
+            "the port address is from 0 to 32767"
+        );
+        Self {
+            layout: Layout::new(port_address, start_channel, *PORT_ADDRESSES.end()),
+        }
+    }
+
+    /// The port addresses of the LEDs.
+    #[must_use]
+    pub fn port_addresses(&self) -> RangeInclusive<u16> {
+        self.layout.universes()
+    }
+}
+
+/// An Art-Net node, which receives the universes of the LEDs and writes their channels to the
+/// stream.
+pub struct Node {
+    mapping: Mapping,
+
+    /// The MAC address of the network interface, reported to the controllers.
+    mac: [u8; 6],
+
+    frame: Frame,
+}
+
+impl Node {
+    #[must_use]
+    pub const fn new(mapping: Mapping, mac: [u8; 6]) -> Self {
+        Self {
+            mapping,
+            mac,
+            frame: Frame::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn mapping(&self) -> Mapping {
+        self.mapping
+    }
+
+    /// Receives a packet, and returns it so that an ArtPoll is answered with the
+    /// [`Node::poll_replies`]. The ArtDMX packets of the port addresses that aren't mapped are
+    /// ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the packet isn't an ArtPoll or a valid ArtDMX packet.
+    pub fn receive<'a>(
+        &mut self, packet: &'a [u8], now: Instant,
+    ) -> Result<Packet<'a>, PacketError> {
+        let packet = parse(packet)?;
+        if let Packet::Dmx(dmx) = packet
+            && let Some(index) = self.mapping.layout.index(dmx.port_address)
+        {
+            self.frame
+                .write(&self.mapping.layout, index, dmx.channels, now);
+        }
+
+        Ok(packet)
+    }
+
+    /// The ArtPollReply packets describing the port addresses of the LEDs, for the node at the
+    /// given IPv4 address. Each one describes up to 4 port addresses of the same net and subnet.
+    #[must_use]
+    pub fn poll_replies(&self, address: [u8; 4]) -> PollReplies<'_> {
+        PollReplies {
+            node: self,
+            address,
+            next: Some(*self.mapping.port_addresses().start()),
+            bind_index: 1,
+        }
+    }
+
+    /// The ArtPollReply of the given number of port addresses, from the first one.
+    fn poll_reply(
+        &self, address: [u8; 4], first: u16, ports: u16, bind_index: u8,
+    ) -> [u8; POLL_REPLY_SIZE] {
+        let mut reply = [0; POLL_REPLY_SIZE];
+        reply[..8].copy_from_slice(ID);
+        reply[8..10].copy_from_slice(&OP_POLL_REPLY.to_le_bytes());
+        reply[10..14].copy_from_slice(&address);
+        reply[14..16].copy_from_slice(&PORT.to_le_bytes());
+        let [net, low] = first.to_be_bytes();
+        reply[18] = net;
+        reply[19] = low >> 4;
+        reply[20..22].copy_from_slice(&OEM_UNKNOWN.to_be_bytes());
+        reply[23] = STATUS_1;
+        reply[26..26 + SHORT_NAME.len()].copy_from_slice(SHORT_NAME);
+        reply[44..44 + LONG_NAME.len()].copy_from_slice(LONG_NAME);
+        reply[108..108 + NODE_REPORT.len()].copy_from_slice(NODE_REPORT);
+        reply[172..174].copy_from_slice(&ports.to_be_bytes());
+        for port in 0..usize::from(ports) {
+            reply[174 + port] = PORT_TYPE_OUTPUT;
+            // The universe of the port, within the net and the subnet of the reply.
+            reply[190 + port] = (low & 0x0F) + u8::try_from(port).unwrap_or_default();
+        }
+        reply[201..207].copy_from_slice(&self.mac);
+        reply[207..211].copy_from_slice(&address);
+        reply[211] = bind_index;
+        reply[212] = STATUS_2;
+        reply
+    }
+}
+
+/// The ArtPollReply packets of a node, see [`Node::poll_replies`].
+pub struct PollReplies<'a> {
+    node: &'a Node,
+    address: [u8; 4],
+
+    /// The first port address of the next reply, unless all of them were described.
+    next: Option<u16>,
+
+    /// The index of the next reply, from 1.
+    bind_index: u8,
+}
+
+impl Iterator for PollReplies<'_> {
+    type Item = [u8; POLL_REPLY_SIZE];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let first = self.next?;
+        let last = *self.node.mapping.port_addresses().end();
+        // The ports of a reply stop at the end of the subnet.
+        let end = (first | 0x0F).min(last).min(first + PORTS_PER_REPLY - 1);
+        self.next = (end < last).then_some(end + 1);
+
+        let reply = self
+            .node
+            .poll_reply(self.address, first, end - first + 1, self.bind_index);
+        self.bind_index = self.bind_index.wrapping_add(1);
+        Some(reply)
+    }
+}
@@ -0,0 +1,99 @@
+//! Measures the cost of computing the frames of every animation on the device.
+//!
+//! The colors are processed exactly like they would be to write them to the LEDs, but they are
+//! discarded instead, so that only the computation is measured and not the time spent on the SPI
+//! bus.
+
+use crate::animations::{Animation, AnimationKind, LedData, Settings, create_data};
+use crate::dithering::Dithering;
+use crate::telemetry;
+use crate::telemetry::Statistic;
+use core::convert::Infallible;
+use core::hint::black_box;
+use defmt::{info, unwrap};
+use embassy_time::Instant;
+use embedded_hal_async::delay::DelayNs;
+use rand::rngs::SmallRng;
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWrite;
+
+/// The number of frames measured for each animation.
+const FRAMES: u32 = 100;
+
+/// Measures the update and the post-processing (gamma, brightness and power limit) of every
+/// animation for the full frame and logs the results.
+pub async fn run(prng: &mut SmallRng, settings: &Settings) {
+    info!("Starting benchmark of {} frames per animation...", FRAMES);
+
+    let mut data = create_data();
+    let mut dithering = Dithering::new();
+
+    for kind in AnimationKind::ALL {
+        let mut animation = Animation::new(kind, prng);
+        let mut update = Statistic::new();
+        let mut render = Statistic::new();
+
+        for _ in 0..FRAMES {
+            let start = Instant::now();
+            animation.update(&mut data, settings);
+            update.record(start.elapsed());
+
+            let start = Instant::now();
+            render_discarded(&mut animation, &data, &mut dithering, settings).await;
+            render.record(start.elapsed());
+        }
+
+        info!(
+            "{}: update {}, post-processing {} (min/avg/max)",
+            kind, update, render
+        );
+    }
+
+    // The frames of the benchmark shouldn't be part of the statistics of the first animation.
+    telemetry::reset(Instant::now());
+
+    info!("Benchmark completed");
+}
+
+async fn render_discarded(
+    animation: &mut Animation, data: &LedData, dithering: &mut Dithering, settings: &Settings,
+) {
+    unwrap!(
+        animation
+            .render(
+                data,
+                &mut DiscardingSection,
+                &mut DiscardingSection,
+                dithering,
+                &mut NoDelay,
+                settings,
+            )
+            .await
+    );
+}
+
+/// A LED section that computes all the colors written to it, but doesn't send them anywhere.
+pub struct DiscardingSection;
+
+impl SmartLedsWrite for DiscardingSection {
+    type Color = RGB8;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        for color in iterator {
+            black_box(color.into());
+        }
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately, the time between frames isn't part of the measurement.
+pub struct NoDelay;
+
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
@@ -0,0 +1,153 @@
+//! Color correction and color temperature of each section, to match strips from different reels.
+//!
+//! Like in FastLED, the correction compensates the LEDs themselves, and the temperature tints the
+//! whole section towards the white of a light source. Both scale each channel, and are applied to
+//! the colors of a section before they're written to it.
+
+use defmt::Format;
+use serde::{Deserialize, Serialize};
+use smart_leds::{RGB8, RGB16};
+
+/// Corrections of the LEDs, from FastLED.
+pub mod correction {
+    use smart_leds::RGB8;
+
+    /// Typical values for SMD5050 LEDs.
+    pub const TYPICAL_SMD5050: RGB8 = RGB8::new(255, 176, 240);
+
+    /// Typical values for generic LED strips, with SMD5050 LEDs.
+    pub const TYPICAL_LED_STRIP: RGB8 = RGB8::new(255, 176, 240);
+
+    /// Typical values for 8 mm "pixels on a string".
+    pub const TYPICAL_8MM_PIXEL: RGB8 = RGB8::new(255, 224, 140);
+
+    /// Typical values for "pixels on a string", with 8 mm LEDs.
+    pub const TYPICAL_PIXEL_STRING: RGB8 = RGB8::new(255, 224, 140);
+
+    /// Leaves the colors unchanged.
+    pub const UNCORRECTED: RGB8 = RGB8::new(255, 255, 255);
+}
+
+/// Color temperatures of light sources, from FastLED.
+pub mod temperature {
+    use smart_leds::RGB8;
+
+    /// 1900 K.
+    pub const CANDLE: RGB8 = RGB8::new(255, 147, 41);
+
+    /// 2600 K.
+    pub const TUNGSTEN_40W: RGB8 = RGB8::new(255, 197, 143);
+
+    /// 2850 K.
+    pub const TUNGSTEN_100W: RGB8 = RGB8::new(255, 214, 170);
+
+    /// 3200 K.
+    pub const HALOGEN: RGB8 = RGB8::new(255, 241, 224);
+
+    /// 5200 K.
+    pub const CARBON_ARC: RGB8 = RGB8::new(255, 250, 244);
+
+    /// 5400 K.
+    pub const HIGH_NOON_SUN: RGB8 = RGB8::new(255, 255, 251);
+
+    /// 6000 K.
+    pub const DIRECT_SUNLIGHT: RGB8 = RGB8::new(255, 255, 255);
+
+    /// 7000 K.
+    pub const OVERCAST_SKY: RGB8 = RGB8::new(201, 226, 255);
+
+    /// 20000 K.
+    pub const CLEAR_BLUE_SKY: RGB8 = RGB8::new(64, 156, 255);
+
+    pub const WARM_FLUORESCENT: RGB8 = RGB8::new(255, 244, 229);
+
+    pub const STANDARD_FLUORESCENT: RGB8 = RGB8::new(244, 255, 250);
+
+    pub const COOL_WHITE_FLUORESCENT: RGB8 = RGB8::new(212, 235, 255);
+
+    pub const FULL_SPECTRUM_FLUORESCENT: RGB8 = RGB8::new(255, 244, 242);
+
+    pub const GROW_LIGHT_FLUORESCENT: RGB8 = RGB8::new(255, 239, 247);
+
+    pub const BLACK_LIGHT_FLUORESCENT: RGB8 = RGB8::new(167, 0, 255);
+
+    pub const MERCURY_VAPOR: RGB8 = RGB8::new(216, 247, 255);
+
+    pub const SODIUM_VAPOR: RGB8 = RGB8::new(255, 209, 178);
+
+    pub const METAL_HALIDE: RGB8 = RGB8::new(242, 252, 255);
+
+    pub const HIGH_PRESSURE_SODIUM: RGB8 = RGB8::new(255, 183, 76);
+
+    /// Leaves the colors unchanged.
+    pub const UNCORRECTED: RGB8 = RGB8::new(255, 255, 255);
+}
+
+/// The factors applied to each channel of the colors written to a section.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct ColorCorrection {
+    correction: RGB8,
+    temperature: RGB8,
+}
+
+impl ColorCorrection {
+    /// Leaves the colors unchanged.
+    pub const UNCORRECTED: Self = Self::new(correction::UNCORRECTED, temperature::UNCORRECTED);
+
+    /// Each channel of the colors is scaled by the product of the channels of the correction and
+    /// of the temperature, 255 leaving it unchanged.
+    #[must_use]
+    pub const fn new(correction: RGB8, temperature: RGB8) -> Self {
+        Self {
+            correction,
+            temperature,
+        }
+    }
+
+    /// Scales the channels of the color.
+    #[must_use]
+    pub fn apply(&self, color: RGB16) -> RGB16 {
+        let scale = |channel: u16, correction: u8, temperature: u8| {
+            (u32::from(channel) * u32::from(correction) * u32::from(temperature) / (255 * 255))
+                as u16
+        };
+
+        RGB16 {
+            r: scale(color.r, self.correction.r, self.temperature.r),
+            g: scale(color.g, self.correction.g, self.temperature.g),
+            b: scale(color.b, self.correction.b, self.temperature.b),
+        }
+    }
+
+    #[must_use]
+    pub fn correction(&self) -> RGB8 {
+        self.correction
+    }
+
+    #[must_use]
+    pub fn temperature(&self) -> RGB8 {
+        self.temperature
+    }
+}
+
+impl Default for ColorCorrection {
+    fn default() -> Self {
+        Self::UNCORRECTED
+    }
+}
+
+impl Format for ColorCorrection {
+    fn format(&self, f: defmt::Formatter) {
+        let (correction, temperature) = (self.correction, self.temperature);
+        defmt::write!(
+            f,
+            "ColorCorrection {{ correction: ({}, {}, {}), temperature: ({}, {}, {}) }}",
+            correction.r,
+            correction.g,
+            correction.b,
+            temperature.r,
+            temperature.g,
+            temperature.b
+        );
+    }
+}
@@ -0,0 +1,304 @@
+//! The commands controlling and inspecting a running board, from its consoles and control
+//! protocols.
+//!
+//! The commands changing the animation are queued to the task running it, which applies them
+//! before its next frame. That task publishes its status for each frame, and its frame when a query
+//! waits for it, so that the queries are answered without waiting for it.
+//!
+//! The commands, and the types they carry, are serialized by the binary control protocol. Changing
+//! their fields or the order of their variants changes the protocol, and requires a new version.
+
+use crate::animations::{AnimationKind, LEDS_TOTAL, LedData, Section, Settings};
+use crate::color_correction::ColorCorrection;
+use crate::signal::COMMAND_CHANNEL;
+use crate::storage::SettingsStorage;
+use core::cell::RefCell;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::slice;
+use core::sync::atomic::{AtomicU8, Ordering};
+use defmt::{Format, error, info, warn};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::TrySendError;
+use embedded_storage::nor_flash::NorFlash;
+use serde::{Deserialize, Serialize};
+use smart_leds::RGB8;
+
+static STATUS: Mutex<CriticalSectionRawMutex, RefCell<Option<Status>>> =
+    Mutex::new(RefCell::new(None));
+
+/// The colors of the frame last published, with 8 bits per channel.
+static FRAME: Mutex<CriticalSectionRawMutex, RefCell<[RGB8; LEDS_TOTAL]>> =
+    Mutex::new(RefCell::new([RGB8::new(0, 0, 0); LEDS_TOTAL]));
+
+/// No query reads the frame.
+const FRAME_IDLE: u8 = 0;
+
+/// A query waits for the task running the animation to publish its next frame.
+const FRAME_REQUESTED: u8 = 1;
+
+/// The frame is published, and the query reads it until its end.
+const FRAME_PUBLISHED: u8 = 2;
+
+/// The state of the frame query, so that the frames are only converted and copied for it.
+static FRAME_QUERY: AtomicU8 = AtomicU8::new(FRAME_IDLE);
+
+/// A command, whose variants and their order are part of the control protocol.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum Command {
+    /// Switches to the given animation.
+    Animation(AnimationKind),
+
+    /// Switches to the next animation in the sequence, like the animation button.
+    NextAnimation,
+
+    /// Switches to the previous animation in the sequence.
+    PreviousAnimation,
+
+    Color(Color),
+
+    /// Sets the brightness, until the potentiometer is turned.
+    Brightness(u8),
+
+    /// Sets the delay between frames in milliseconds, until the potentiometer is turned.
+    Speed(u16),
+
+    /// Queries the animation and its settings.
+    Status,
+
+    /// Queries the frame timing statistics of the current period.
+    Stats,
+
+    /// Persists the animation and its settings in the flash, as the preset.
+    SavePreset,
+
+    /// Restores the animation and its settings from the preset.
+    LoadPreset,
+
+    /// Sets the color correction of a section, or of both sections, and persists it.
+    ColorCorrection(Option<Section>, ColorCorrection),
+}
+
+/// The color of the uni-color animations.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Color {
+    /// A color of the palette, by its index.
+    Index(u8),
+
+    /// A color outside of the palette.
+    Rgb(RGB8),
+}
+
+impl Color {
+    /// The color selected by the settings.
+    #[must_use]
+    pub fn of(settings: &Settings) -> Self {
+        match settings.custom_color() {
+            Some(color) => Color::Rgb(color),
+            None => Color::Index(settings.color_index() as u8),
+        }
+    }
+
+    /// Selects the color in the settings. An index outside of the palette is ignored.
+    pub fn select(self, settings: &mut Settings) {
+        match self {
+            Color::Index(index) if usize::from(index) < settings.num_colors() => {
+                settings.set_color_index(usize::from(index));
+            }
+            Color::Index(index) => warn!("Ignoring the color {} outside of the palette", index),
+            Color::Rgb(color) => settings.set_custom_color(color),
+        }
+    }
+}
+
+impl Format for Color {
+    fn format(&self, f: defmt::Formatter) {
+        match self {
+            Color::Index(index) => defmt::write!(f, "{}", index),
+            Color::Rgb(color) => {
+                defmt::write!(f, "#{=u8:02x}{=u8:02x}{=u8:02x}", color.r, color.g, color.b)
+            }
+        }
+    }
+}
+
+impl Display for Color {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Color::Index(index) => write!(f, "{index}"),
+            Color::Rgb(color) => write!(f, "#{:02x}{:02x}{:02x}", color.r, color.g, color.b),
+        }
+    }
+}
+
+/// The animation running on the board, and its settings.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub struct Status {
+    pub animation: AnimationKind,
+    pub brightness: u8,
+    pub color: Color,
+
+    /// Delay between frames in milliseconds.
+    pub delay: u32,
+
+    pub dithering: bool,
+}
+
+impl Status {
+    #[must_use]
+    pub fn new(animation: AnimationKind, settings: &Settings) -> Self {
+        Self {
+            animation,
+            brightness: settings.brightness(),
+            color: Color::of(settings),
+            delay: settings.delay(),
+            dithering: settings.dithering(),
+        }
+    }
+
+    /// Applies the settings of the status, like a preset, and returns its animation.
+    pub fn apply(&self, settings: &mut Settings) -> AnimationKind {
+        settings.set_brightness_level(self.brightness);
+        self.color.select(settings);
+        settings.set_delay_milliseconds(self.delay);
+        settings.set_dithering(self.dithering);
+
+        self.animation
+    }
+}
+
+impl Display for Status {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "animation {}, color {}, brightness {}, delay {} ms, dithering {}",
+            self.animation.name(),
+            self.color,
+            self.brightness,
+            self.delay,
+            if self.dithering { "on" } else { "off" }
+        )
+    }
+}
+
+/// Queues a command to the task running the animation.
+///
+/// # Errors
+///
+/// Returns the command when the queue is full.
+pub fn send(command: Command) -> Result<(), Command> {
+    COMMAND_CHANNEL
+        .try_send(command)
+        .map_err(|error| match error {
+            TrySendError::Full(command) => command,
+        })
+}
+
+/// What a command changed, that the task running the animation still has to apply.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Change {
+    /// The animation to start.
+    Animation(AnimationKind),
+
+    /// The settings persisted in the flash, which the task saves once they stop changing.
+    Settings,
+}
+
+/// Applies the command to the settings, and returns what the task running the animation still has
+/// to apply. The presets are persisted in the storage of the settings, when there's one.
+pub fn execute<F: NorFlash>(
+    command: Command, active_kind: AnimationKind, settings: &mut Settings,
+    storage: Option<&mut SettingsStorage<F>>,
+) -> Option<Change> {
+    info!("Command received: {}", command);
+
+    match command {
+        Command::Animation(kind) => return Some(Change::Animation(kind)),
+        Command::NextAnimation => return Some(Change::Animation(active_kind.next())),
+        Command::PreviousAnimation => return Some(Change::Animation(active_kind.previous())),
+        Command::Color(color) => color.select(settings),
+        Command::Brightness(brightness) => settings.set_brightness_level(brightness),
+        Command::Speed(delay) => settings.set_delay_milliseconds(u32::from(delay)),
+        Command::Status | Command::Stats => {}
+        Command::SavePreset => match storage {
+            Some(storage) => {
+                if let Err(e) = storage.save_preset(&Status::new(active_kind, settings)) {
+                    error!("Cannot save the preset: {}", e);
+                }
+            }
+            None => warn!("No storage for the preset"),
+        },
+        Command::LoadPreset => match storage.and_then(|storage| storage.load_preset()) {
+            Some(preset) => return Some(Change::Animation(preset.apply(settings))),
+            None => warn!("No preset saved"),
+        },
+        Command::ColorCorrection(section, color_correction) => {
+            for &section in section.as_ref().map_or(&Section::ALL[..], slice::from_ref) {
+                settings.set_color_correction(section, color_correction);
+            }
+            return Some(Change::Settings);
+        }
+    }
+
+    None
+}
+
+/// Publishes the status of the animation, for the queries.
+pub fn publish_status(animation: AnimationKind, settings: &Settings) {
+    let status = Status::new(animation, settings);
+    STATUS.lock(|published| *published.borrow_mut() = Some(status));
+}
+
+/// Returns the status last published, unless the animation hasn't started yet.
+#[must_use]
+pub fn status() -> Option<Status> {
+    STATUS.lock(|status| *status.borrow())
+}
+
+/// Publishes the colors of the frame computed by the animation, before their corrections, when a
+/// query waits for them.
+pub(crate) fn publish_frame(data: &LedData) {
+    if FRAME_QUERY.load(Ordering::Relaxed) != FRAME_REQUESTED {
+        return;
+    }
+
+    FRAME.lock(|frame| {
+        for (published, color) in frame.borrow_mut().iter_mut().zip(data) {
+            *published = RGB8::new(
+                (color.r >> 8) as u8,
+                (color.g >> 8) as u8,
+                (color.b >> 8) as u8,
+            );
+        }
+    });
+    FRAME_QUERY.store(FRAME_PUBLISHED, Ordering::Relaxed);
+}
+
+/// Copies the colors of the frame published for the query from the given LED, and returns how many
+/// were copied. Returns `None` while the query waits for the task running the animation to publish
+/// its next frame, which the first call requests. The query ends once it reads past the last LED.
+pub fn frame(offset: usize, colors: &mut [RGB8]) -> Option<usize> {
+    match FRAME_QUERY.compare_exchange(
+        FRAME_IDLE,
+        FRAME_REQUESTED,
+        Ordering::Relaxed,
+        Ordering::Relaxed,
+    ) {
+        Err(FRAME_PUBLISHED) => {}
+        _ => return None,
+    }
+
+    let length = FRAME.lock(|frame| {
+        let frame = frame.borrow();
+        let published = frame.get(offset..).unwrap_or_default();
+        let length = published.len().min(colors.len());
+        colors[..length].copy_from_slice(&published[..length]);
+        length
+    });
+    if length == 0 {
+        FRAME_QUERY.store(FRAME_IDLE, Ordering::Relaxed);
+    }
+
+    Some(length)
+}
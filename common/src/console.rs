@@ -0,0 +1,396 @@
+//! Line-based text console, to control and inspect a running board over a serial port.
+//!
+//! Each line is a command followed by its arguments, separated by spaces, and ends with a carriage
+//! return or a line feed. Every line gets a reply of a single line: the status or the statistics
+//! for the queries, `ok` once the other commands are queued to the task running the animation, or
+//! the error that prevented it.
+//!
+//! The console also answers the frames of the binary control protocol, which start with a zero
+//! byte that a line of text never contains. It also streams the Adalight frames, which start with
+//! a magic word, and the TPM2 packets, which start a line with a byte that isn't ASCII. Neither
+//! is answered.
+
+use crate::adalight;
+use crate::animations::{AnimationKind, Section};
+use crate::color_correction::ColorCorrection;
+use crate::command;
+use crate::command::{Color, Command};
+use crate::protocol;
+use crate::protocol::{DELIMITER, FRAME_SIZE, FrameError};
+use crate::telemetry;
+use crate::tpm2;
+use core::fmt;
+use core::fmt::{Display, Formatter, Write};
+use core::str;
+use defmt::Format;
+use embassy_time::Instant;
+use smart_leds::RGB8;
+
+/// The longest line accepted, in bytes.
+pub const LINE_SIZE: usize = 64;
+
+/// Large enough for the longest reply, the statistics.
+const REPLY_SIZE: usize = 320;
+
+/// Ends every reply, even a truncated one.
+const NEW_LINE: &[u8] = b"\r\n";
+
+const _: () = assert!(
+    FRAME_SIZE <= REPLY_SIZE,
+    "a response frame doesn't fit in the reply"
+);
+
+/// The commands, and their arguments.
+const HELP: &str = "commands: anim <name>, next, prev, color <index|#rrggbb>, bright <0-255>, \
+    speed <1-1000 ms>, status, stats, preset save|load, \
+    correct <1|2|all> <#correction> <#temperature>, help";
+
+/// The longest delay between frames, like the one of the potentiometer.
+pub const DELAY_MAXIMUM: u16 = 1_000;
+
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum ParseError {
+    /// The line is longer than [`LINE_SIZE`], so it was discarded.
+    LineTooLong,
+
+    /// The line isn't valid UTF-8.
+    InvalidEncoding,
+
+    UnknownCommand,
+    MissingArgument,
+    InvalidArgument,
+    UnexpectedArgument,
+}
+
+impl Display for ParseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ParseError::LineTooLong => "line too long",
+            ParseError::InvalidEncoding => "invalid encoding",
+            ParseError::UnknownCommand => "unknown command, try help",
+            ParseError::MissingArgument => "missing argument",
+            ParseError::InvalidArgument => "invalid argument",
+            ParseError::UnexpectedArgument => "unexpected argument",
+        })
+    }
+}
+
+/// A line of the console.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum Line {
+    Command(Command),
+
+    /// Lists the commands, which is only answered by the console.
+    Help,
+}
+
+/// Parses a line, ignoring the case of the words.
+///
+/// # Errors
+///
+/// Returns why the line isn't a valid command.
+pub fn parse(line: &str) -> Result<Line, ParseError> {
+    let mut words = line.split_ascii_whitespace();
+    let name = words.next().ok_or(ParseError::UnknownCommand)?;
+    let is = |command: &str| name.eq_ignore_ascii_case(command);
+    if is("correct") {
+        return parse_color_correction(words).map(Line::Command);
+    }
+
+    let argument = words.next();
+    if words.next().is_some() {
+        return Err(ParseError::UnexpectedArgument);
+    }
+
+    let without_argument = |line: Line| match argument {
+        Some(_) => Err(ParseError::UnexpectedArgument),
+        None => Ok(line),
+    };
+    let argument = argument.ok_or(ParseError::MissingArgument);
+
+    let line = if is("anim") {
+        Line::Command(Command::Animation(
+            AnimationKind::from_name(argument?).ok_or(ParseError::InvalidArgument)?,
+        ))
+    } else if is("next") {
+        without_argument(Line::Command(Command::NextAnimation))?
+    } else if is("prev") {
+        without_argument(Line::Command(Command::PreviousAnimation))?
+    } else if is("color") {
+        Line::Command(Command::Color(parse_color(argument?)?))
+    } else if is("bright") {
+        Line::Command(Command::Brightness(
+            argument?.parse().map_err(|_| ParseError::InvalidArgument)?,
+        ))
+    } else if is("speed") {
+        Line::Command(Command::Speed(
+            argument?
+                .parse()
+                .ok()
+                .filter(|delay| (1..=DELAY_MAXIMUM).contains(delay))
+                .ok_or(ParseError::InvalidArgument)?,
+        ))
+    } else if is("status") {
+        without_argument(Line::Command(Command::Status))?
+    } else if is("stats") {
+        without_argument(Line::Command(Command::Stats))?
+    } else if is("preset") {
+        match argument? {
+            action if action.eq_ignore_ascii_case("save") => Line::Command(Command::SavePreset),
+            action if action.eq_ignore_ascii_case("load") => Line::Command(Command::LoadPreset),
+            _ => return Err(ParseError::InvalidArgument),
+        }
+    } else if is("help") {
+        without_argument(Line::Help)?
+    } else {
+        return Err(ParseError::UnknownCommand);
+    };
+
+    Ok(line)
+}
+
+/// Parses the index of a color of the palette, or a custom color as `#rrggbb`.
+///
+/// # Errors
+///
+/// Returns [`ParseError::InvalidArgument`] when the argument is neither.
+pub fn parse_color(argument: &str) -> Result<Color, ParseError> {
+    if argument.starts_with('#') {
+        parse_rgb(argument).map(Color::Rgb)
+    } else {
+        argument
+            .parse()
+            .map(Color::Index)
+            .map_err(|_| ParseError::InvalidArgument)
+    }
+}
+
+/// Parses a color as `#rrggbb`.
+///
+/// # Errors
+///
+/// Returns [`ParseError::InvalidArgument`] when the argument isn't one.
+pub fn parse_rgb(argument: &str) -> Result<RGB8, ParseError> {
+    match argument.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.bytes().all(|byte| byte.is_ascii_hexdigit()) => {
+            let channel = |index: usize| u8::from_str_radix(&hex[index..index + 2], 16);
+            match (channel(0), channel(2), channel(4)) {
+                (Ok(r), Ok(g), Ok(b)) => Ok(RGB8::new(r, g, b)),
+                _ => Err(ParseError::InvalidArgument),
+            }
+        }
+        _ => Err(ParseError::InvalidArgument),
+    }
+}
+
+/// Parses the arguments of the color correction command: the section, `1`, `2` or `all`, then the
+/// correction and the temperature as `#rrggbb`.
+fn parse_color_correction<'a>(
+    mut arguments: impl Iterator<Item = &'a str>,
+) -> Result<Command, ParseError> {
+    let mut argument = || arguments.next().ok_or(ParseError::MissingArgument);
+    let section = match argument()? {
+        "1" => Some(Section::First),
+        "2" => Some(Section::Second),
+        all if all.eq_ignore_ascii_case("all") => None,
+        _ => return Err(ParseError::InvalidArgument),
+    };
+    let correction = parse_rgb(argument()?)?;
+    let temperature = parse_rgb(argument()?)?;
+    if arguments.next().is_some() {
+        return Err(ParseError::UnexpectedArgument);
+    }
+
+    Ok(Command::ColorCorrection(
+        section,
+        ColorCorrection::new(correction, temperature),
+    ))
+}
+
+/// The console of a serial port, which collects the received bytes into lines or frames and
+/// answers them.
+pub struct Console {
+    line: [u8; LINE_SIZE],
+    length: usize,
+
+    /// Whether the current line was longer than the buffer, and is discarded until its end.
+    overflowed: bool,
+
+    /// Whether a frame of the binary protocol is received instead of a line.
+    framed: bool,
+
+    adalight: adalight::Parser,
+
+    tpm2: tpm2::Parser,
+
+    reply: Reply,
+}
+
+impl Console {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            line: [0; LINE_SIZE],
+            length: 0,
+            overflowed: false,
+            framed: false,
+            adalight: adalight::Parser::new(),
+            tpm2: tpm2::Parser::new(),
+            reply: Reply::new(),
+        }
+    }
+
+    /// Adds a received byte to the line, and returns the reply to send once the line is complete.
+    /// Empty lines, like the line feed following a carriage return, aren't answered.
+    pub fn receive(&mut self, byte: u8) -> Option<&[u8]> {
+        if self.framed {
+            return self.receive_frame(byte);
+        }
+        let now = Instant::now();
+        if !self.tpm2.is_receiving() && self.adalight.receive(byte, now) {
+            // The magic word of the Adalight frame was collected as the start of a line.
+            self.length = 0;
+            self.overflowed = false;
+            return None;
+        }
+        let line_start = self.length == 0 && !self.overflowed;
+        if (line_start || self.tpm2.is_receiving()) && self.tpm2.receive(byte, now) {
+            return None;
+        }
+
+        match byte {
+            DELIMITER if self.length == 0 && !self.overflowed => {
+                self.framed = true;
+                None
+            }
+            b'\r' | b'\n' => {
+                let line = match (self.overflowed, self.length) {
+                    (true, _) => Err(ParseError::LineTooLong),
+                    (false, 0) => return None,
+                    (false, length) => str::from_utf8(&self.line[..length])
+                        .map_err(|_| ParseError::InvalidEncoding),
+                };
+                self.reply.clear();
+                answer(line.and_then(parse), &mut self.reply);
+
+                self.length = 0;
+                self.overflowed = false;
+                Some(self.reply.as_bytes())
+            }
+            _ => {
+                self.push(byte);
+                None
+            }
+        }
+    }
+
+    /// Adds a received byte to the frame, and returns the response frame once it's complete.
+    /// Empty frames, like consecutive delimiters, aren't answered.
+    fn receive_frame(&mut self, byte: u8) -> Option<&[u8]> {
+        match byte {
+            DELIMITER if self.length == 0 && !self.overflowed => None,
+            DELIMITER => {
+                let request = if self.overflowed {
+                    Err(FrameError::TooLong)
+                } else {
+                    protocol::decode(&mut self.line[..self.length])
+                };
+                let length = protocol::answer(request, &mut self.reply.bytes);
+
+                self.length = 0;
+                self.overflowed = false;
+                self.framed = false;
+                self.reply.length = length.ok()?;
+                Some(self.reply.as_bytes())
+            }
+            _ => {
+                self.push(byte);
+                None
+            }
+        }
+    }
+
+    /// Adds a byte to the line or the frame, which overflows once the buffer is full.
+    fn push(&mut self, byte: u8) {
+        match self.line.get_mut(self.length) {
+            Some(slot) => {
+                *slot = byte;
+                self.length += 1;
+            }
+            None => self.overflowed = true,
+        }
+    }
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Executes a line, and writes its reply followed by a new line.
+fn answer(line: Result<Line, ParseError>, reply: &mut Reply) {
+    // A reply too long for the buffer is truncated, which still ends with a new line.
+    let _ = match line {
+        Ok(Line::Help) => reply.write_str(HELP),
+        Ok(Line::Command(Command::Status)) => match command::status() {
+            Some(status) => write!(reply, "{status}"),
+            None => reply.write_str("error: the animation hasn't started yet"),
+        },
+        Ok(Line::Command(Command::Stats)) => {
+            let statistics = telemetry::snapshot(Instant::now());
+            write!(reply, "{}", protocol::Statistics::from(&statistics))
+        }
+        Ok(Line::Command(command)) => match command::send(command) {
+            Ok(()) => reply.write_str("ok"),
+            Err(_) => reply.write_str("error: busy, try again"),
+        },
+        Err(error) => write!(reply, "error: {error}"),
+    };
+    reply.finish();
+}
+
+/// The reply to a line, truncated to its buffer while keeping room for the new line.
+struct Reply {
+    bytes: [u8; REPLY_SIZE],
+    length: usize,
+}
+
+impl Reply {
+    const fn new() -> Self {
+        Self {
+            bytes: [0; REPLY_SIZE],
+            length: 0,
+        }
+    }
+
+    fn as_bytes(&self) -> &[u8] {
+        &self.bytes[..self.length]
+    }
+
+    fn clear(&mut self) {
+        self.length = 0;
+    }
+
+    /// Ends the line, in the room that the writes leave for it.
+    fn finish(&mut self) {
+        self.bytes[self.length..self.length + NEW_LINE.len()].copy_from_slice(NEW_LINE);
+        self.length += NEW_LINE.len();
+    }
+}
+
+impl Write for Reply {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        // The new line always fits, so that a truncated reply still ends the line.
+        let room = REPLY_SIZE - NEW_LINE.len();
+        let length = s.len().min(room.saturating_sub(self.length));
+        self.bytes[self.length..self.length + length].copy_from_slice(&s.as_bytes()[..length]);
+        self.length += length;
+
+        if length == s.len() {
+            Ok(())
+        } else {
+            Err(fmt::Error)
+        }
+    }
+}
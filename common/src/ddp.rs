@@ -0,0 +1,187 @@
+//! DDP, the Distributed Display Protocol of xLights and WLED, which carries the channels of the
+//! LEDs over UDP without splitting them into universes.
+//!
+//! A packet starts with a header of 10 bytes: its flags, its sequence number, the type of its data,
+//! its destination, the offset of its data in bytes as a big-endian 32-bit integer and the length
+//! of its data as a big-endian 16-bit integer. A time code of 4 bytes follows when its flag is set.
+//! The data is written to the stream at its offset, and the frame is shown by the packet with the
+//! push flag, usually the last one of the frame.
+
+use crate::stream;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use defmt::Format;
+use embassy_time::Instant;
+use smart_leds::RGB8;
+
+/// The UDP port of DDP.
+pub const PORT: u16 = 4048;
+
+/// The largest packet sent, with a time code and 1440 bytes of data, so that it fits in an Ethernet
+/// frame.
+pub const PACKET_SIZE: usize = 1454;
+
+/// The size of the header, without the time code.
+const HEADER_SIZE: usize = 10;
+
+/// The size of the time code following the header when its flag is set.
+const TIME_CODE_SIZE: usize = 4;
+
+const FLAGS_VERSION_MASK: u8 = 0b1100_0000;
+const FLAGS_VERSION_1: u8 = 0b0100_0000;
+const FLAG_TIME_CODE: u8 = 0x10;
+const FLAG_REPLY: u8 = 0x04;
+const FLAG_QUERY: u8 = 0x02;
+const FLAG_PUSH: u8 = 0x01;
+
+/// The default output of the device, the LEDs.
+const DESTINATION_DISPLAY: u8 = 1;
+
+/// All the outputs of the device.
+const DESTINATION_ALL: u8 = 255;
+
+/// Why a DDP packet was rejected.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum PacketError {
+    /// The packet is shorter than its header, or than the data it holds.
+    TooShort,
+
+    /// The packet isn't of the version 1 of the protocol.
+    UnsupportedVersion,
+
+    /// The data isn't of 8-bit RGB or RGBW channels.
+    UnsupportedDataType,
+
+    /// The packet is for another output than the LEDs, like the configuration or the status.
+    UnsupportedDestination,
+
+    /// The offset of the RGBW data isn't at the start of an LED.
+    InvalidOffset,
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketError::TooShort => "packet too short",
+            PacketError::UnsupportedVersion => "unsupported protocol version",
+            PacketError::UnsupportedDataType => "unsupported data type",
+            PacketError::UnsupportedDestination => "unsupported destination",
+            PacketError::InvalidOffset => "invalid offset",
+        })
+    }
+}
+
+/// The type of the data of a packet.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum DataType {
+    /// 3 channels of 8 bits per LED, in RGB order. The senders that don't set the type send these.
+    Rgb,
+
+    /// 4 channels of 8 bits per LED, in RGBW order. The white is added to the other channels.
+    Rgbw,
+}
+
+impl DataType {
+    /// Decodes the data type field: whether it's customer defined, then 3 bits of the type of the
+    /// pixels and 3 bits of the size of their channels.
+    fn from_field(field: u8) -> Option<Self> {
+        match field {
+            // Undefined, the RGB type of the first senders, or RGB of 8 bits per channel.
+            0x00 | 0x01 | 0x0B => Some(Self::Rgb),
+            // RGBW of 8 bits per channel.
+            0x1B => Some(Self::Rgbw),
+            _ => None,
+        }
+    }
+}
+
+/// A packet of data, with the channels of the LEDs from its offset.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DataPacket<'a> {
+    /// The last packet of the frame, which shows it.
+    pub push: bool,
+
+    /// From 1 to 15, or 0 when the sender doesn't number the packets.
+    pub sequence: u8,
+
+    pub data_type: DataType,
+
+    /// The offset of the data among the channels of the LEDs, in bytes.
+    pub offset: u32,
+
+    pub data: &'a [u8],
+}
+
+/// Parses a packet of data. The queries and the replies of the devices are `None`.
+///
+/// # Errors
+///
+/// Returns why the packet isn't a valid packet of data for the LEDs.
+pub fn parse(packet: &[u8]) -> Result<Option<DataPacket<'_>>, PacketError> {
+    let header = packet.get(..HEADER_SIZE).ok_or(PacketError::TooShort)?;
+    let flags = header[0];
+    if flags & FLAGS_VERSION_MASK != FLAGS_VERSION_1 {
+        return Err(PacketError::UnsupportedVersion);
+    }
+    if flags & (FLAG_QUERY | FLAG_REPLY) != 0 {
+        return Ok(None);
+    }
+    if header[3] != DESTINATION_DISPLAY && header[3] != DESTINATION_ALL {
+        return Err(PacketError::UnsupportedDestination);
+    }
+    let data_type = DataType::from_field(header[2]).ok_or(PacketError::UnsupportedDataType)?;
+
+    let start = match flags & FLAG_TIME_CODE {
+        0 => HEADER_SIZE,
+        _ => HEADER_SIZE + TIME_CODE_SIZE,
+    };
+    let length = usize::from(u16::from_be_bytes([header[8], header[9]]));
+    let data = packet
+        .get(start..start + length)
+        .ok_or(PacketError::TooShort)?;
+
+    Ok(Some(DataPacket {
+        push: flags & FLAG_PUSH != 0,
+        sequence: header[1] & 0x0F,
+        data_type,
+        offset: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+        data,
+    }))
+}
+
+/// Receives a packet, writes its data to the stream and shows the frame when it's pushed. The data
+/// past the last LED is ignored.
+///
+/// # Errors
+///
+/// Returns why the packet isn't a valid packet of data for the LEDs.
+pub fn receive(packet: &[u8], now: Instant) -> Result<(), PacketError> {
+    let Some(packet) = parse(packet)? else {
+        return Ok(());
+    };
+    let offset = usize::try_from(packet.offset).unwrap_or(usize::MAX);
+
+    match packet.data_type {
+        DataType::Rgb => stream::write_channels(offset, packet.data),
+        DataType::Rgbw => {
+            if offset % 4 != 0 {
+                return Err(PacketError::InvalidOffset);
+            }
+            let colors = packet.data.chunks_exact(4).map(|channels| {
+                let white = channels[3];
+                RGB8::new(
+                    channels[0].saturating_add(white),
+                    channels[1].saturating_add(white),
+                    channels[2].saturating_add(white),
+                )
+            });
+            stream::write(offset / 4, colors);
+        }
+    }
+
+    if packet.push {
+        stream::show(now);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,89 @@
+//! Temporal dithering, to show the levels between two 8-bit values by alternating them over frames.
+//!
+//! The colors are corrected with 16 bits per channel, and the part lost when quantizing them to the
+//! 8 bits of the LEDs is kept for each LED and added to the next frame. A color between two levels
+//! then averages to its exact value, which smooths the fades at low brightness.
+
+use crate::animations::{LEDS_FIRST_SECTION, LEDS_TOTAL};
+use smart_leds::{RGB8, RGB16};
+
+/// The 16-bit value of a single 8-bit step, so that 255 maps to [`u16::MAX`].
+pub(crate) const STEP: u16 = 257;
+
+/// The part of each channel of each LED that couldn't be shown in the previous frames.
+pub struct Dithering {
+    residuals: [[u8; 3]; LEDS_TOTAL],
+}
+
+impl Dithering {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            residuals: [[0; 3]; LEDS_TOTAL],
+        }
+    }
+
+    /// Discards the residuals, for instance when the animation changes.
+    pub fn reset(&mut self) {
+        self.residuals = [[0; 3]; LEDS_TOTAL];
+    }
+
+    /// The residuals of the first and second sections.
+    pub(crate) fn sections(&mut self) -> (&mut [[u8; 3]], &mut [[u8; 3]]) {
+        self.residuals.split_at_mut(LEDS_FIRST_SECTION)
+    }
+}
+
+impl Default for Dithering {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The colors taken by the LEDs of a section, converted from the corrected 16-bit colors.
+pub trait LedColor {
+    /// Converts the color of a LED, with the residual of its previous frames when it's dithered.
+    fn quantize(color: RGB16, residual: &mut [u8; 3], dithering: bool) -> Self;
+}
+
+/// Quantizes the 16-bit colors to the 8 bits of most LEDs.
+///
+/// Without dithering, the channels are rounded down so that the LEDs never draw more current than
+/// estimated. With dithering, a channel can be one step above its value, and is below it in the
+/// following frames to compensate.
+impl LedColor for RGB8 {
+    fn quantize(color: RGB16, residual: &mut [u8; 3], dithering: bool) -> Self {
+        if !dithering {
+            *residual = [0; 3];
+            return RGB8::new(
+                (color.r / STEP) as u8,
+                (color.g / STEP) as u8,
+                (color.b / STEP) as u8,
+            );
+        }
+
+        RGB8::new(
+            quantize_channel(color.r, &mut residual[0]),
+            quantize_channel(color.g, &mut residual[1]),
+            quantize_channel(color.b, &mut residual[2]),
+        )
+    }
+}
+
+/// The 16 bits of the LEDs whose drivers keep more resolution than 8 bits, like the APA102 with its
+/// global brightness. They aren't dithered.
+impl LedColor for RGB16 {
+    fn quantize(color: RGB16, residual: &mut [u8; 3], _dithering: bool) -> Self {
+        *residual = [0; 3];
+        color
+    }
+}
+
+fn quantize_channel(value: u16, residual: &mut u8) -> u8 {
+    let value = u32::from(value) + u32::from(*residual);
+    let quantized = (value / u32::from(STEP)).min(255);
+
+    // The residual can only exceed a step when the channel is already at its maximum.
+    *residual = (value - quantized * u32::from(STEP)).min(255) as u8;
+    quantized as u8
+}
@@ -0,0 +1,283 @@
+//! E1.31, or Streaming ACN, which carries the 512 channels of DMX universes over UDP.
+//!
+//! The universes are mapped on the LEDs from a configured universe and start channel, like the ones
+//! of the other DMX512 protocols in [`crate::universe`]. Each universe is received from a single
+//! source at a time, the one with the highest priority, and its packets are dropped when they
+//! arrive out of sequence.
+
+use crate::universe::{Frame, Layout, UNIVERSES_MAXIMUM};
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use core::ops::RangeInclusive;
+use defmt::Format;
+use embassy_time::{Duration, Instant};
+
+/// The UDP port of E1.31.
+pub const PORT: u16 = 5568;
+
+/// How long a source holds a universe after its last packet, before another one can take it over.
+pub const DATA_LOSS_TIMEOUT: Duration = Duration::from_millis(2_500);
+
+/// The identifier of the ACN packets, after the sizes of the preamble and the post-amble.
+const IDENTIFIER: &[u8; 16] = b"\x00\x10\x00\x00ASC-E1.17\x00\x00\x00";
+
+const VECTOR_ROOT_DATA: u32 = 0x0000_0004;
+const VECTOR_FRAMING_DATA: u32 = 0x0000_0002;
+const VECTOR_DMP_SET_PROPERTY: u8 = 0x02;
+
+/// The type of the addresses and the data of the DMP layer, the only one of E1.31.
+const ADDRESS_DATA_TYPE: u8 = 0xA1;
+
+/// The size of the packet before its channels, including the start code.
+const HEADER_SIZE: usize = 126;
+
+const OPTION_PREVIEW: u8 = 0x80;
+const OPTION_TERMINATED: u8 = 0x40;
+
+/// The start code of the channels of DMX512, the other codes carry other data.
+const START_CODE_DMX: u8 = 0x00;
+
+/// The valid universes, the others are reserved.
+const UNIVERSES: RangeInclusive<u16> = 1..=63_999;
+
+/// A packet of a sequence is dropped when it's up to this many packets behind the last one, since
+/// it arrived out of order. A larger difference means the source restarted.
+const SEQUENCE_WINDOW: i8 = -20;
+
+/// Why an E1.31 packet was rejected.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum PacketError {
+    /// The packet is shorter than its layers, or than the channels it holds.
+    TooShort,
+
+    /// The packet isn't an ACN packet.
+    InvalidIdentifier,
+
+    /// The packet isn't a data packet, like the synchronization and discovery packets.
+    UnsupportedVector,
+
+    /// The DMP layer doesn't hold the channels as E1.31 sends them.
+    InvalidLayout,
+
+    /// The universe is 0 or reserved.
+    InvalidUniverse,
+
+    /// The universe is held by a source of a higher priority.
+    LowerPriority,
+
+    /// The packet is older than the last one of its source.
+    OutOfSequence,
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketError::TooShort => "packet too short",
+            PacketError::InvalidIdentifier => "not an ACN packet",
+            PacketError::UnsupportedVector => "not a data packet",
+            PacketError::InvalidLayout => "invalid DMP layer",
+            PacketError::InvalidUniverse => "invalid universe",
+            PacketError::LowerPriority => "universe held by a higher priority",
+            PacketError::OutOfSequence => "packet out of sequence",
+        })
+    }
+}
+
+/// A data packet, with the channels of a universe.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DataPacket<'a> {
+    /// The identifier of the source, unique to each sender.
+    pub cid: [u8; 16],
+
+    /// From 0 to 200, the universe is received from the source with the highest one.
+    pub priority: u8,
+
+    pub sequence: u8,
+
+    /// The data is meant for visualizers, not for the LEDs.
+    pub preview: bool,
+
+    /// The source stops sending the universe, and its data is ignored.
+    pub terminated: bool,
+
+    pub universe: u16,
+
+    /// The start code of the channels, 0 for the levels of DMX512.
+    pub start_code: u8,
+
+    /// Up to 512 channels, starting from the first one.
+    pub channels: &'a [u8],
+}
+
+/// Parses a data packet.
+///
+/// # Errors
+///
+/// Returns why the packet isn't a valid data packet.
+pub fn parse(packet: &[u8]) -> Result<DataPacket<'_>, PacketError> {
+    let header = packet.get(..HEADER_SIZE).ok_or(PacketError::TooShort)?;
+    if &header[..16] != IDENTIFIER {
+        return Err(PacketError::InvalidIdentifier);
+    }
+    let vector = |offset: usize| {
+        u32::from_be_bytes([
+            header[offset],
+            header[offset + 1],
+            header[offset + 2],
+            header[offset + 3],
+        ])
+    };
+    if vector(18) != VECTOR_ROOT_DATA || vector(40) != VECTOR_FRAMING_DATA {
+        return Err(PacketError::UnsupportedVector);
+    }
+    let word = |offset: usize| u16::from_be_bytes([header[offset], header[offset + 1]]);
+    if header[117] != VECTOR_DMP_SET_PROPERTY
+        || header[118] != ADDRESS_DATA_TYPE
+        || word(119) != 0
+        || word(121) != 1
+    {
+        return Err(PacketError::InvalidLayout);
+    }
+
+    // The count includes the start code.
+    let count = usize::from(word(123));
+    if !(1..=513).contains(&count) {
+        return Err(PacketError::InvalidLayout);
+    }
+    let channels = packet
+        .get(HEADER_SIZE..HEADER_SIZE - 1 + count)
+        .ok_or(PacketError::TooShort)?;
+    let universe = word(113);
+    if !UNIVERSES.contains(&universe) {
+        return Err(PacketError::InvalidUniverse);
+    }
+
+    let mut cid = [0; 16];
+    cid.copy_from_slice(&header[22..38]);
+    Ok(DataPacket {
+        cid,
+        priority: header[108],
+        sequence: header[111],
+        preview: header[112] & OPTION_PREVIEW != 0,
+        terminated: header[112] & OPTION_TERMINATED != 0,
+        universe,
+        start_code: header[125],
+        channels,
+    })
+}
+
+/// The multicast group of a universe, which its sources send to.
+#[must_use]
+pub fn multicast_address(universe: u16) -> [u8; 4] {
+    let [high, low] = universe.to_be_bytes();
+    [239, 255, high, low]
+}
+
+/// Where the universes are mapped on the LEDs.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub struct Mapping {
+    layout: Layout,
+}
+
+impl Mapping {
+    /// Maps the LEDs from the start channel of the universe, the first channel being 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the universe isn't from 1 to 63999, or the start channel from 1 to 510.
+    #[must_use]
+    pub const fn new(universe: u16, start_channel: u16) -> Self {
+        assert!(
+            universe >= *UNIVERSES.start() && universe <= *UNIVERSES.end(),
+            "the universe is from 1 to 63999"
+        );
+        Self {
+            layout: Layout::new(universe, start_channel, *UNIVERSES.end()),
+        }
+    }
+
+    /// The universes of the LEDs.
+    #[must_use]
+    pub fn universes(&self) -> RangeInclusive<u16> {
+        self.layout.universes()
+    }
+}
+
+/// The source a universe is received from.
+#[derive(Clone, Copy, Debug)]
+struct Source {
+    cid: [u8; 16],
+    priority: u8,
+    sequence: u8,
+    received_at: Instant,
+}
+
+/// Receives the universes of the LEDs, and writes their channels to the stream.
+pub struct Receiver {
+    mapping: Mapping,
+
+    /// The source of each universe of the LEDs, by index.
+    sources: [Option<Source>; UNIVERSES_MAXIMUM],
+
+    frame: Frame,
+}
+
+impl Receiver {
+    #[must_use]
+    pub const fn new(mapping: Mapping) -> Self {
+        Self {
+            mapping,
+            sources: [None; UNIVERSES_MAXIMUM],
+            frame: Frame::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn mapping(&self) -> Mapping {
+        self.mapping
+    }
+
+    /// Receives a packet. The packets of the universes that aren't mapped, of previews, and of
+    /// other start codes than the DMX512 one are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the packet is invalid, or why its source isn't the one received.
+    pub fn receive(&mut self, packet: &[u8], now: Instant) -> Result<(), PacketError> {
+        let packet = parse(packet)?;
+        let Some(index) = self.mapping.layout.index(packet.universe) else {
+            return Ok(());
+        };
+        if packet.preview || packet.start_code != START_CODE_DMX {
+            return Ok(());
+        }
+
+        let source = &mut self.sources[index];
+        if let Some(current) = source
+            && now.saturating_duration_since(current.received_at) < DATA_LOSS_TIMEOUT
+        {
+            if current.cid != packet.cid && packet.priority <= current.priority {
+                return Err(PacketError::LowerPriority);
+            }
+            let behind = packet.sequence.wrapping_sub(current.sequence).cast_signed();
+            if current.cid == packet.cid && (SEQUENCE_WINDOW + 1..=0).contains(&behind) {
+                return Err(PacketError::OutOfSequence);
+            }
+        }
+        if packet.terminated {
+            *source = None;
+            return Ok(());
+        }
+        *source = Some(Source {
+            cid: packet.cid,
+            priority: packet.priority,
+            sequence: packet.sequence,
+            received_at: now,
+        });
+
+        self.frame
+            .write(&self.mapping.layout, index, packet.channels, now);
+
+        Ok(())
+    }
+}
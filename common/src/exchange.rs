@@ -0,0 +1,237 @@
+//! Hands the frames over from the core computing the animation to the core writing the LEDs.
+//!
+//! The sender updates the animation, then corrects its colors and limits them to the power budget
+//! into one of two buffers, while the receiver writes the previous frame from the other one. Each
+//! side hands its buffer over by incrementing an atomic counter, so that neither of them ever waits
+//! on a lock held by the other. The frames are all written in order, and the sender waits for a
+//! free buffer once it's two frames ahead.
+
+use crate::animations::{
+    Animation, LEDS_TOTAL, LedData, Settings, create_data, prepare_sections, reset_data,
+    wait_next_frame, write_prepared_sections_async,
+};
+use crate::command;
+use crate::dithering::{Dithering, LedColor};
+use crate::write_error::WriteError;
+use core::cell::UnsafeCell;
+use core::fmt::Debug;
+use core::future::poll_fn;
+use core::mem;
+use core::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use core::task::Poll;
+use embassy_sync::waitqueue::AtomicWaker;
+use embedded_hal_async::delay::DelayNs;
+use smart_leds::RGB16;
+use smart_leds_trait::SmartLedsWriteAsync;
+
+const BUFFERS: usize = 2;
+
+/// A frame ready to be quantized and written, with how it's written.
+struct Buffer {
+    colors: LedData,
+    delay: u32,
+    dithering: bool,
+
+    /// Whether the frame is the first one of an animation, which doesn't keep the dithering
+    /// residuals of the previous one.
+    restart: bool,
+}
+
+/// The two buffers of the frames handed over between the cores.
+pub struct FrameExchange {
+    buffers: [UnsafeCell<Buffer>; BUFFERS],
+
+    /// The number of frames sent, wrapping around.
+    sent: AtomicUsize,
+
+    /// The number of frames received, wrapping around.
+    received: AtomicUsize,
+
+    sent_waker: AtomicWaker,
+    received_waker: AtomicWaker,
+    split: AtomicBool,
+}
+
+// SAFETY: A buffer is only accessed by the sender until its frame is sent, then by the receiver
+// until it's received. The counters order these accesses with their acquire and release ordering.
+unsafe impl Sync for FrameExchange {}
+
+impl FrameExchange {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            buffers: [
+                UnsafeCell::new(Buffer::new()),
+                UnsafeCell::new(Buffer::new()),
+            ],
+            sent: AtomicUsize::new(0),
+            received: AtomicUsize::new(0),
+            sent_waker: AtomicWaker::new(),
+            received_waker: AtomicWaker::new(),
+            split: AtomicBool::new(false),
+        }
+    }
+
+    /// Splits the exchange into the sender of the frames and their receiver, which can each be
+    /// moved to their core. It can only be split once.
+    pub fn split(&self) -> Option<(FrameSender<'_>, FrameReceiver<'_>)> {
+        if self.split.swap(true, Ordering::AcqRel) {
+            return None;
+        }
+
+        Some((
+            FrameSender {
+                exchange: self,
+                data: create_data(),
+                restart: true,
+            },
+            FrameReceiver { exchange: self },
+        ))
+    }
+
+    fn buffer(&self, count: usize) -> *mut Buffer {
+        self.buffers[count % BUFFERS].get()
+    }
+}
+
+impl Default for FrameExchange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Buffer {
+    const fn new() -> Self {
+        Self {
+            colors: [RGB16::new(0, 0, 0); LEDS_TOTAL],
+            delay: 0,
+            dithering: false,
+            restart: false,
+        }
+    }
+}
+
+/// The side of the exchange computing the frames of the animation.
+pub struct FrameSender<'a> {
+    exchange: &'a FrameExchange,
+
+    /// The frame of the animation, which builds each frame on the previous one.
+    data: LedData,
+    restart: bool,
+}
+
+impl FrameSender<'_> {
+    /// Clears the frame, so that the next animation starts from its first frame.
+    pub fn reset(&mut self) {
+        reset_data(&mut self.data);
+        self.restart = true;
+    }
+
+    /// Updates the animation, then sends its frame once a buffer is free.
+    pub async fn send(&mut self, animation: &mut Animation, settings: &Settings) {
+        animation.update(&mut self.data, settings);
+        command::publish_frame(&self.data);
+        let frame = animation.frame(settings);
+
+        let exchange = self.exchange;
+        let sent = exchange.sent.load(Ordering::Relaxed);
+        poll_fn(|cx| {
+            exchange.received_waker.register(cx.waker());
+            if sent.wrapping_sub(exchange.received.load(Ordering::Acquire)) < BUFFERS {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        })
+        .await;
+
+        // SAFETY: The receiver is done with the frame of this buffer, and won't access it again
+        // until the next one is sent.
+        let buffer = unsafe { &mut *exchange.buffer(sent) };
+        prepare_sections(&self.data, &mut buffer.colors, settings, |color| {
+            frame.correct(color, settings)
+        });
+        buffer.delay = frame.delay();
+        buffer.dithering = settings.dithering();
+        buffer.restart = mem::take(&mut self.restart);
+
+        exchange.sent.store(sent.wrapping_add(1), Ordering::Release);
+        exchange.sent_waker.wake();
+    }
+}
+
+/// The side of the exchange writing the frames to the LEDs.
+pub struct FrameReceiver<'a> {
+    exchange: &'a FrameExchange,
+}
+
+impl FrameReceiver<'_> {
+    /// Writes the oldest frame sent once there's one, then waits until the next one is due.
+    ///
+    /// The same frame is written again until [`Self::next_frame`] is called, like after a failed
+    /// write. The first frame of a new animation replaces the frame of the previous one when it's
+    /// already sent.
+    ///
+    /// # Errors
+    ///
+    /// Returns the sections that couldn't be written when any of the LED drivers fails.
+    pub async fn render(
+        &mut self,
+        leds_section_1: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+        leds_section_2: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+        dithering: &mut Dithering, delay: &mut impl DelayNs,
+    ) -> Result<(), WriteError> {
+        let exchange = self.exchange;
+        let mut received = exchange.received.load(Ordering::Relaxed);
+        let sent = poll_fn(|cx| {
+            exchange.sent_waker.register(cx.waker());
+            let sent = exchange.sent.load(Ordering::Acquire);
+            if sent == received {
+                Poll::Pending
+            } else {
+                Poll::Ready(sent)
+            }
+        })
+        .await;
+
+        // SAFETY: Both buffers hold a frame sent, which the sender won't access until it's
+        // received.
+        let next = received.wrapping_add(1);
+        if sent.wrapping_sub(received) == BUFFERS && unsafe { (*exchange.buffer(next)).restart } {
+            self.next_frame();
+            received = next;
+        }
+
+        // SAFETY: The frame of this buffer is sent, and the sender won't access it until it's
+        // received.
+        let buffer = unsafe { &*exchange.buffer(received) };
+        if buffer.restart {
+            dithering.reset();
+        }
+
+        write_prepared_sections_async(
+            &buffer.colors,
+            leds_section_1,
+            leds_section_2,
+            dithering,
+            buffer.dithering,
+        )
+        .await?;
+
+        wait_next_frame(delay, buffer.delay).await;
+
+        Ok(())
+    }
+
+    /// Moves on to the next frame, once the current one is written or skipped.
+    pub fn next_frame(&mut self) {
+        let exchange = self.exchange;
+        let received = exchange.received.load(Ordering::Relaxed);
+        if exchange.sent.load(Ordering::Acquire) != received {
+            exchange
+                .received
+                .store(received.wrapping_add(1), Ordering::Release);
+            exchange.received_waker.wake();
+        }
+    }
+}
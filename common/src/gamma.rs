@@ -0,0 +1,180 @@
+//! Gamma correction of the colors, so that the steps of the animations look even to the eye.
+//!
+//! The curve of each channel is generated from its exponent, by a `const fn` so that the curves
+//! chosen at build time are computed by the compiler and kept in a `static`. The settings refer to
+//! them instead of copying their tables, so the ones changed at runtime are computed again on the
+//! device and must outlive the settings too.
+
+use crate::dithering;
+use core::fmt;
+use core::fmt::{Debug, Formatter};
+use defmt::Format;
+use smart_leds::RGB16;
+
+/// The number of samples of each curve, one for each 8-bit step of the input.
+const SAMPLES: usize = 256;
+
+/// The gamma curves of the three channels.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Gamma {
+    exponents: [f32; 3],
+
+    /// The curve of each channel, sampled at each 8-bit step of the input.
+    tables: [[u16; SAMPLES]; 3],
+}
+
+impl Gamma {
+    /// The gamma commonly used for WS2812 LEDs.
+    pub const DEFAULT: Self = Self::new(2.8);
+
+    /// The highest exponent, above which all but the brightest colors are off.
+    pub const MAX_EXPONENT: f32 = 4.0;
+
+    /// The lowest exponent, which leaves the colors unchanged.
+    pub const MIN_EXPONENT: f32 = 1.0;
+
+    /// The same curve for all the channels.
+    ///
+    /// The exponent is clamped between [`Self::MIN_EXPONENT`] and [`Self::MAX_EXPONENT`].
+    #[must_use]
+    pub const fn new(exponent: f32) -> Self {
+        Self::per_channel(exponent, exponent, exponent)
+    }
+
+    /// A curve for each channel, to compensate LEDs whose channels don't respond the same way.
+    ///
+    /// The exponents are clamped between [`Self::MIN_EXPONENT`] and [`Self::MAX_EXPONENT`].
+    #[must_use]
+    pub const fn per_channel(red: f32, green: f32, blue: f32) -> Self {
+        let exponents = [clamp(red), clamp(green), clamp(blue)];
+        Self {
+            exponents,
+            tables: [
+                table(exponents[0]),
+                table(exponents[1]),
+                table(exponents[2]),
+            ],
+        }
+    }
+
+    /// Applies the curves to the color, interpolating between their samples.
+    #[must_use]
+    pub fn correct(&self, color: RGB16) -> RGB16 {
+        RGB16 {
+            r: correct_channel(&self.tables[0], color.r),
+            g: correct_channel(&self.tables[1], color.g),
+            b: correct_channel(&self.tables[2], color.b),
+        }
+    }
+
+    /// The exponents of the red, green and blue channels.
+    #[must_use]
+    pub fn exponents(&self) -> [f32; 3] {
+        self.exponents
+    }
+}
+
+impl Debug for Gamma {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Gamma")
+            .field("exponents", &self.exponents)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Gamma {
+    fn default() -> Self {
+        Self::DEFAULT
+    }
+}
+
+impl Format for Gamma {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "Gamma {{ exponents: {} }}", self.exponents);
+    }
+}
+
+/// Clamps the exponent to the supported range, which also replaces NaN.
+const fn clamp(exponent: f32) -> f32 {
+    if exponent > Gamma::MAX_EXPONENT {
+        Gamma::MAX_EXPONENT
+    } else if exponent >= Gamma::MIN_EXPONENT {
+        exponent
+    } else {
+        Gamma::MIN_EXPONENT
+    }
+}
+
+fn correct_channel(table: &[u16; SAMPLES], value: u16) -> u16 {
+    let index = usize::from(value / dithering::STEP);
+    let low = u32::from(table[index]);
+    let Some(&high) = table.get(index + 1) else {
+        return table[index];
+    };
+
+    let fraction = u32::from(value % dithering::STEP);
+    (low + (u32::from(high) - low) * fraction / u32::from(dithering::STEP)) as u16
+}
+
+const fn table(exponent: f32) -> [u16; SAMPLES] {
+    let mut table = [0; SAMPLES];
+    let mut index = 1;
+    while index < SAMPLES {
+        let input = index as f64 / (SAMPLES - 1) as f64;
+        table[index] = (pow(input, exponent as f64) * u16::MAX as f64 + 0.5) as u16;
+        index += 1;
+    }
+    table
+}
+
+/// Raises a base between 0 and 1 to a positive exponent.
+///
+/// The core library has no floating point functions without the standard library, and these ones
+/// must also be `const`.
+const fn pow(base: f64, exponent: f64) -> f64 {
+    exp(exponent * ln(base))
+}
+
+/// The natural logarithm of a positive, normal number.
+const fn ln(x: f64) -> f64 {
+    // x = mantissa * 2^exponent, with the mantissa between 1 and 2.
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    let mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+
+    // ln(m) = 2 * atanh((m - 1) / (m + 1)), whose series converges quickly since s <= 1/3.
+    let s = (mantissa - 1.0) / (mantissa + 1.0);
+    let mut term = s;
+    let mut n = 1.0;
+    let mut sum = 0.0;
+    while term > f64::EPSILON * f64::EPSILON {
+        sum += term / n;
+        term *= s * s;
+        n += 2.0;
+    }
+
+    2.0 * sum + exponent as f64 * core::f64::consts::LN_2
+}
+
+/// The exponential of a negative number.
+const fn exp(x: f64) -> f64 {
+    // e^x = e^r * 2^k, with r between -ln(2) and 0.
+    let k = (x / core::f64::consts::LN_2) as i64;
+    let r = x - k as f64 * core::f64::consts::LN_2;
+
+    let mut term: f64 = 1.0;
+    let mut n = 1.0;
+    let mut sum = 1.0;
+    while term.abs() > f64::EPSILON * f64::EPSILON {
+        term *= r / n;
+        sum += term;
+        n += 1.0;
+    }
+
+    let mut halvings = -k;
+    while halvings > 0 {
+        sum *= 0.5;
+        halvings -= 1;
+    }
+    sum
+}
@@ -1,22 +1,54 @@
 use crate::signal::{BRIGHTNESS_READ_SIGNAL, DELAY_READ_SIGNAL};
+use core::cell::Cell;
 use defmt::info;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 
-/// Process analog sensor readings and update signals if necessary.
+/// The change of a reading, out of the 12 bits of the analog sensors, from which a potentiometer is
+/// considered turned. The smaller changes are noise, and would override the brightness and delay
+/// set by the commands.
+const ANALOG_THRESHOLD: u16 = 32;
+
+/// The readings last signaled, for the brightness and the delay.
+static SIGNALED_READINGS: Mutex<CriticalSectionRawMutex, Cell<[Option<u16>; 2]>> =
+    Mutex::new(Cell::new([None; 2]));
+
+/// Process analog sensor readings and update signals if the potentiometers were turned.
 pub fn process_analog_sensors(brightness_reading: Result<u16, ()>, delay_reading: Result<u16, ()>) {
     let mut brightness = None;
     let mut delay = None;
 
-    if let Ok(raw_brightness) = brightness_reading {
-        brightness = Some(raw_brightness);
-        BRIGHTNESS_READ_SIGNAL.signal(raw_brightness);
-    }
+    SIGNALED_READINGS.lock(|signaled| {
+        let [mut signaled_brightness, mut signaled_delay] = signaled.get();
 
-    if let Ok(raw_delay) = delay_reading {
-        delay = Some(raw_delay);
-        DELAY_READ_SIGNAL.signal(raw_delay);
-    }
+        if let Ok(raw_brightness) = brightness_reading
+            && turned(&mut signaled_brightness, raw_brightness)
+        {
+            brightness = Some(raw_brightness);
+            BRIGHTNESS_READ_SIGNAL.signal(raw_brightness);
+        }
+
+        if let Ok(raw_delay) = delay_reading
+            && turned(&mut signaled_delay, raw_delay)
+        {
+            delay = Some(raw_delay);
+            DELAY_READ_SIGNAL.signal(raw_delay);
+        }
+
+        signaled.set([signaled_brightness, signaled_delay]);
+    });
 
     if brightness.is_some() || delay.is_some() {
         info!("Brightness: {}, Delay: {}", brightness, delay);
     }
 }
+
+/// Whether the potentiometer was turned since the reading last signaled, which is then updated.
+fn turned(signaled: &mut Option<u16>, reading: u16) -> bool {
+    if signaled.is_some_and(|signaled| signaled.abs_diff(reading) < ANALOG_THRESHOLD) {
+        return false;
+    }
+
+    *signaled = Some(reading);
+    true
+}
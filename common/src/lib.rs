@@ -1,5 +1,31 @@
 #![no_std]
 
+pub mod adalight;
 pub mod animations;
+#[cfg(feature = "apa102")]
+pub mod apa102;
+pub mod artnet;
+#[cfg(feature = "benchmark")]
+pub mod benchmark;
+pub mod color_correction;
+pub mod command;
+pub mod console;
+pub mod ddp;
+pub mod dithering;
+pub mod e131;
+pub mod exchange;
+pub mod gamma;
 pub mod input;
+pub mod opc;
+pub mod pipeline;
+pub mod power;
+pub mod protocol;
 pub mod signal;
+pub mod storage;
+pub mod stream;
+pub mod strip;
+pub mod telemetry;
+pub mod tpm2;
+pub mod universe;
+pub mod write_error;
+pub mod ws2812;
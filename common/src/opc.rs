@@ -0,0 +1,236 @@
+//! Open Pixel Control, the protocol of Fadecandy and of the generative sketches in Python and
+//! Processing, which streams the colors of the LEDs over TCP.
+//!
+//! Each message starts with a header of 4 bytes: its channel, its command and the length of its
+//! data as a big-endian 16-bit integer. The channel 0 is broadcast to all the LEDs, and the
+//! channels 1 and 2 address the first and the second section. The command 0 sets the colors of the
+//! LEDs of the channel, three bytes each in RGB order, and shows the frame. The command 255 is a
+//! system-exclusive message, whose data starts with the system ID of the device it's for: the
+//! [`SYSTEM_ID`] of the board followed by [`SYSEX_COLOR_CORRECTION`] sets the color correction of
+//! the sections of the channel, with the correction then the temperature in RGB order. The other
+//! messages are skipped.
+
+use crate::animations::{LEDS_FIRST_SECTION, LEDS_SECOND_SECTION, LEDS_TOTAL, Section};
+use crate::color_correction::ColorCorrection;
+use crate::signal::COLOR_CORRECTION_CHANGED_SIGNAL;
+use crate::stream;
+use core::iter;
+use embassy_time::Instant;
+use smart_leds::RGB8;
+
+/// The TCP port of Open Pixel Control.
+pub const PORT: u16 = 7890;
+
+/// The system ID of the board in the system-exclusive messages, "CM" for Cookie Monster.
+pub const SYSTEM_ID: u16 = 0x434D;
+
+/// The system-exclusive command setting the color correction, followed by the correction and the
+/// temperature.
+pub const SYSEX_COLOR_CORRECTION: u8 = 0x01;
+
+const COMMAND_SET_PIXELS: u8 = 0;
+const COMMAND_SYSTEM_EXCLUSIVE: u8 = 255;
+
+const HEADER_SIZE: usize = 4;
+
+/// The size of the system-exclusive message setting the color correction: the system ID, the
+/// command and the two colors.
+const SYSEX_SIZE: usize = 9;
+
+/// The LEDs addressed by a channel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct Channel {
+    /// The section of the LEDs, or both sections for the broadcast channel.
+    section: Option<Section>,
+
+    /// The first LED.
+    first: usize,
+
+    /// The number of LEDs.
+    leds: usize,
+}
+
+impl Channel {
+    fn new(channel: u8) -> Option<Self> {
+        let (section, first, leds) = match channel {
+            0 => (None, 0, LEDS_TOTAL),
+            1 => (Some(Section::First), 0, LEDS_FIRST_SECTION),
+            2 => (
+                Some(Section::Second),
+                LEDS_FIRST_SECTION,
+                LEDS_SECOND_SECTION,
+            ),
+            _ => return None,
+        };
+
+        Some(Self {
+            section,
+            first,
+            leds,
+        })
+    }
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Receiving the header of a message, of which the given bytes were received.
+    Header {
+        bytes: [u8; HEADER_SIZE],
+        received: usize,
+    },
+
+    /// Receiving the colors of the LEDs of a channel.
+    Pixels {
+        channel: Channel,
+
+        /// The number of bytes of colors of the message.
+        size: usize,
+
+        /// The number of bytes of colors received.
+        received: usize,
+
+        /// The channels of the color being received.
+        color: [u8; 3],
+    },
+
+    /// Receiving a system-exclusive message of the size of the ones of the board.
+    SystemExclusive {
+        channel: Channel,
+        bytes: [u8; SYSEX_SIZE],
+        received: usize,
+    },
+
+    /// Skipping the given number of bytes of a message that isn't handled.
+    Skip(usize),
+}
+
+impl State {
+    const HEADER: Self = Self::Header {
+        bytes: [0; HEADER_SIZE],
+        received: 0,
+    };
+}
+
+/// Parses the Open Pixel Control messages out of the bytes received from a client, and writes
+/// their colors to the stream. Each connection has its own parser.
+pub struct Parser {
+    state: State,
+}
+
+impl Parser {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: State::HEADER,
+        }
+    }
+
+    /// Parses a received byte. The colors past the last LED of the channel are ignored.
+    pub fn receive(&mut self, byte: u8, now: Instant) {
+        self.state = match self.state {
+            State::Header {
+                mut bytes,
+                received,
+            } => {
+                bytes[received] = byte;
+                match received + 1 {
+                    HEADER_SIZE => header(bytes, now),
+                    received => State::Header { bytes, received },
+                }
+            }
+            State::Pixels {
+                channel,
+                size,
+                received,
+                mut color,
+            } => {
+                color[received % 3] = byte;
+                let received = received + 1;
+                let led = received / 3;
+                if received % 3 == 0 && led <= channel.leds {
+                    let [r, g, b] = color;
+                    stream::write(channel.first + led - 1, iter::once(RGB8::new(r, g, b)));
+                }
+                if received == size {
+                    stream::show(now);
+                    State::HEADER
+                } else {
+                    State::Pixels {
+                        channel,
+                        size,
+                        received,
+                        color,
+                    }
+                }
+            }
+            State::SystemExclusive {
+                channel,
+                mut bytes,
+                received,
+            } => {
+                bytes[received] = byte;
+                match received + 1 {
+                    SYSEX_SIZE => {
+                        system_exclusive(channel, bytes);
+                        State::HEADER
+                    }
+                    received => State::SystemExclusive {
+                        channel,
+                        bytes,
+                        received,
+                    },
+                }
+            }
+            State::Skip(1) => State::HEADER,
+            State::Skip(remaining) => State::Skip(remaining - 1),
+        };
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Starts receiving the data of the message of the header, or skips it when it isn't handled.
+fn header([channel, command, high, low]: [u8; HEADER_SIZE], now: Instant) -> State {
+    let size = usize::from(u16::from_be_bytes([high, low]));
+    let skip = match size {
+        0 => State::HEADER,
+        size => State::Skip(size),
+    };
+    let Some(channel) = Channel::new(channel) else {
+        return skip;
+    };
+
+    match (command, size) {
+        (COMMAND_SET_PIXELS, 0) => {
+            stream::show(now);
+            State::HEADER
+        }
+        (COMMAND_SET_PIXELS, size) => State::Pixels {
+            channel,
+            size,
+            received: 0,
+            color: [0; 3],
+        },
+        (COMMAND_SYSTEM_EXCLUSIVE, SYSEX_SIZE) => State::SystemExclusive {
+            channel,
+            bytes: [0; SYSEX_SIZE],
+            received: 0,
+        },
+        _ => skip,
+    }
+}
+
+/// Handles a system-exclusive message of the board, and ignores the ones of the other devices.
+fn system_exclusive(channel: Channel, bytes: [u8; SYSEX_SIZE]) {
+    let [id_high, id_low, command, red, green, blue, temperature @ ..] = bytes;
+    if u16::from_be_bytes([id_high, id_low]) != SYSTEM_ID || command != SYSEX_COLOR_CORRECTION {
+        return;
+    }
+    let [r, g, b] = temperature;
+    let color_correction = ColorCorrection::new(RGB8::new(red, green, blue), RGB8::new(r, g, b));
+    COLOR_CORRECTION_CHANGED_SIGNAL.signal((channel.section, color_correction));
+}
@@ -0,0 +1,125 @@
+//! Double-buffered rendering, which computes the next frame while the previous one is written.
+//!
+//! The drivers complete their write once the DMA sent the frame, and the animation is updated in
+//! the meantime into the second buffer. The animations build each frame on the previous one, so it
+//! starts as a copy of the frame being written. The buffers are swapped once the frame is written.
+//!
+//! The second frame doubles the RAM of the colors, to 12 bytes per LED.
+
+use crate::animations::{
+    Animation, Frame, LedData, Settings, create_data, reset_data, wait_next_frame,
+    write_sections_async,
+};
+use crate::command;
+use crate::dithering::{Dithering, LedColor};
+use crate::write_error::WriteError;
+use core::fmt::Debug;
+use embassy_futures::join::join;
+use embedded_hal_async::delay::DelayNs;
+use smart_leds_trait::SmartLedsWriteAsync;
+
+enum State {
+    /// The frame to write isn't computed yet, like after a reset.
+    Empty,
+
+    /// The frame to write is computed, but not the next one.
+    Computed,
+
+    /// The next frame is computed too, and the frame to write is written like this.
+    Next(Frame),
+}
+
+/// The two frames of the LEDs: the one being written, and the next one.
+pub struct Pipeline {
+    frames: [LedData; 2],
+
+    /// The index of the frame being written.
+    front: usize,
+
+    state: State,
+}
+
+impl Pipeline {
+    #[must_use]
+    pub fn new() -> Self {
+        Self {
+            frames: [create_data(), create_data()],
+            front: 0,
+            state: State::Empty,
+        }
+    }
+
+    /// Clears the frames, so that the next animation starts from its first frame.
+    pub fn reset(&mut self) {
+        self.frames.iter_mut().for_each(reset_data);
+        self.state = State::Empty;
+    }
+
+    /// Writes the current frame, then waits until the next one is due.
+    ///
+    /// The next frame is computed while the drivers wait for their DMA transfer. When the same
+    /// frame is written again after a failed write, the next one is already computed.
+    ///
+    /// # Errors
+    ///
+    /// Returns the sections that couldn't be written when any of the LED drivers fails.
+    pub async fn render(
+        &mut self, animation: &mut Animation,
+        leds_section_1: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+        leds_section_2: &mut impl SmartLedsWriteAsync<Color = impl LedColor, Error = impl Debug>,
+        dithering: &mut Dithering, delay: &mut impl DelayNs, settings: &Settings,
+    ) -> Result<(), WriteError> {
+        let [first, second] = &mut self.frames;
+        let (front, back) = if self.front == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        if let State::Empty = self.state {
+            animation.update(front, settings);
+        }
+        command::publish_frame(front);
+
+        let frame = match self.state {
+            State::Next(frame) => frame,
+            State::Empty | State::Computed => animation.frame(settings),
+        };
+        let write = write_sections_async(
+            front,
+            leds_section_1,
+            leds_section_2,
+            dithering,
+            settings,
+            |color| frame.correct(color, settings),
+        );
+
+        let result = if let State::Next(_) = self.state {
+            write.await
+        } else {
+            back.copy_from_slice(front);
+            let (result, ()) = join(write, async { animation.update(back, settings) }).await;
+            result
+        };
+        self.state = State::Next(frame);
+        result?;
+
+        wait_next_frame(delay, frame.delay()).await;
+
+        Ok(())
+    }
+
+    /// Moves on to the next frame, once the current one is written or skipped.
+    pub fn next_frame(&mut self) {
+        if let State::Next(_) = self.state {
+            self.front = 1 - self.front;
+            self.state = State::Computed;
+        }
+    }
+}
+
+impl Default for Pipeline {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,133 @@
+//! Estimation of the current drawn by the LEDs, to keep the frames within the capacity of the power
+//! supply.
+//!
+//! The current of each LED is estimated from its channels once they're gamma corrected, like they
+//! would be written to the strip. The frames above the budget are scaled down as a whole, and for
+//! each section since each one has its own power injection point.
+
+use crate::animations;
+use crate::animations::Section;
+use crate::dithering;
+use defmt::Format;
+use smart_leds::{RGB8, RGB16};
+
+/// Current drawn by the red channel of a LED at full intensity, in milliamps.
+pub const RED_MILLIAMPS: u32 = 16;
+
+/// Current drawn by the green channel of a LED at full intensity, in milliamps.
+pub const GREEN_MILLIAMPS: u32 = 11;
+
+/// Current drawn by the blue channel of a LED at full intensity, in milliamps.
+pub const BLUE_MILLIAMPS: u32 = 15;
+
+/// Current drawn by a LED that is off, in milliamps.
+pub const IDLE_MILLIAMPS: u32 = 1;
+
+/// The value of a 16-bit channel at full intensity.
+const FULL: u64 = u16::MAX as u64;
+
+/// The maximum current the LEDs may draw, for the whole strip and for each section.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub struct PowerBudget {
+    milliamps: u32,
+    section_milliamps: [u32; 2],
+}
+
+impl PowerBudget {
+    /// A 5 V, 4 A power supply, with each section limited to 2.5 A at its injection point.
+    pub const DEFAULT: Self = Self::new(4_000, 2_500, 2_500);
+
+    #[must_use]
+    pub const fn new(milliamps: u32, section_1_milliamps: u32, section_2_milliamps: u32) -> Self {
+        Self {
+            milliamps,
+            section_milliamps: [section_1_milliamps, section_2_milliamps],
+        }
+    }
+
+    #[must_use]
+    pub fn milliamps(&self) -> u32 {
+        self.milliamps
+    }
+
+    #[must_use]
+    pub fn section_milliamps(&self, section: Section) -> u32 {
+        self.section_milliamps[section as usize]
+    }
+
+    /// Computes the brightness to apply to each section so that the frame stays within the budget.
+    ///
+    /// The brightness is 255 for a section that is already within the budget, which leaves its
+    /// colors unchanged. With dithering, each channel is allowed to be one step above its value.
+    pub(crate) fn limit(&self, draws: [Draw; 2], dithering: bool) -> [u8; 2] {
+        let total = Draw {
+            channels: draws[0].channels + draws[1].channels,
+            leds: draws[0].leds + draws[1].leds,
+        };
+        let brightness = total.brightness_within(self.milliamps, dithering);
+
+        Section::ALL.map(|section| {
+            draws[section as usize]
+                .brightness_within(self.section_milliamps(section), dithering)
+                .min(brightness)
+        })
+    }
+}
+
+/// Current drawn by a set of LEDs.
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Draw {
+    /// Current drawn by the channels that are on, in 1/65535 of a milliamp so that each 16-bit
+    /// channel is counted exactly.
+    channels: u64,
+
+    /// Number of LEDs, which draw some current regardless of their color.
+    leds: u64,
+}
+
+impl Draw {
+    pub(crate) fn of(colors: impl IntoIterator<Item = RGB16>) -> Self {
+        colors
+            .into_iter()
+            .fold(Self::default(), |draw, color| Self {
+                channels: draw.channels
+                    + u64::from(color.r) * u64::from(RED_MILLIAMPS)
+                    + u64::from(color.g) * u64::from(GREEN_MILLIAMPS)
+                    + u64::from(color.b) * u64::from(BLUE_MILLIAMPS),
+                leds: draw.leds + 1,
+            })
+    }
+
+    pub(crate) fn milliamps(&self) -> u32 {
+        (self.channels + self.idle()).div_ceil(FULL) as u32
+    }
+
+    /// Highest brightness that keeps the draw within the given current once applied to the colors.
+    ///
+    /// The scaled channels are rounded down, so they never draw more than the brightness allows.
+    fn brightness_within(&self, milliamps: u32, dithering: bool) -> u8 {
+        let mut reserved = self.idle();
+        if dithering {
+            reserved += self.leds
+                * u64::from(RED_MILLIAMPS + GREEN_MILLIAMPS + BLUE_MILLIAMPS)
+                * u64::from(dithering::STEP);
+        }
+
+        let available = (u64::from(milliamps) * FULL).saturating_sub(reserved);
+        if self.channels <= available {
+            return u8::MAX;
+        }
+
+        (available * 255 / self.channels) as u8
+    }
+
+    fn idle(&self) -> u64 {
+        self.leds * u64::from(IDLE_MILLIAMPS) * FULL
+    }
+}
+
+/// Estimates the current drawn by the LEDs showing the given colors, in milliamps.
+#[must_use]
+pub fn estimate_milliamps(colors: impl IntoIterator<Item = RGB8>) -> u32 {
+    Draw::of(colors.into_iter().map(animations::widen)).milliamps()
+}
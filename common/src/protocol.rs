@@ -0,0 +1,337 @@
+//! Binary control protocol, for the host applications.
+//!
+//! The host sends a [`Request`] and the board answers it with a [`Response`], each in a frame: the
+//! version of the protocol, the message serialized with postcard, and the CRC-16 of both. The frame
+//! is COBS-encoded, so that it has no zero byte, and delimited by a zero byte on each side. A
+//! receiver resynchronizes on the next zero after a corrupted or truncated frame, and the leading
+//! zero tells the console of the board that a frame follows instead of a line of text.
+//!
+//! The version comes first and the checksum last in every version, so that a frame of another
+//! version is still recognized. It's rejected with the version found, which tells the host the one
+//! the board speaks.
+
+use crate::animations::{LEDS_TOTAL, Section};
+use crate::command;
+use crate::command::{Command, Status};
+use crate::storage::crc16;
+use crate::telemetry;
+use crate::telemetry::{FrameStatistics, Statistic};
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use defmt::Format;
+use embassy_time::Instant;
+use serde::{Deserialize, Serialize};
+use smart_leds::RGB8;
+
+/// The version of the protocol, the first byte of every frame.
+pub const VERSION: u8 = 1;
+
+/// The largest message, once serialized.
+pub const MESSAGE_SIZE: usize = 128;
+
+/// The largest frame, with its version, checksum and delimiters.
+pub const FRAME_SIZE: usize = cobs::max_encoding_length(RAW_FRAME_SIZE) + 2;
+
+/// The byte delimiting the frames.
+pub const DELIMITER: u8 = 0;
+
+/// The number of LEDs of each chunk of the frame.
+pub const CHUNK_SIZE: usize = 32;
+
+/// The largest frame before its encoding.
+const RAW_FRAME_SIZE: usize = 1 + MESSAGE_SIZE + 2;
+
+/// A request of the host. New requests are added after the existing ones, in a new version.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum Request {
+    /// Executes the command. The queries are answered with the status or the statistics, and the
+    /// other commands once queued to the task running the animation.
+    Command(Command),
+
+    /// Queries the colors of the current frame of the animation from the given LED, which are
+    /// answered in chunks of up to [`CHUNK_SIZE`] LEDs. The board is busy until it publishes the
+    /// frame, and the query ends with the empty chunk past the last LED.
+    Frame { offset: u16 },
+}
+
+/// The answer of the board to a request.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum Response {
+    /// The command was queued to the task running the animation.
+    Queued,
+
+    Status(Status),
+    Statistics(Statistics),
+    Frame(Chunk),
+    Error(ResponseError),
+}
+
+/// Why the board couldn't answer a request.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum ResponseError {
+    /// The queue of the commands is full, or the frame queried isn't published yet.
+    Busy,
+
+    /// The animation hasn't started yet, so it has no status.
+    NotStarted,
+
+    /// The frame of the request was rejected.
+    InvalidFrame(FrameError),
+}
+
+impl Display for ResponseError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            ResponseError::Busy => f.write_str("busy, try again"),
+            ResponseError::NotStarted => f.write_str("the animation hasn't started yet"),
+            ResponseError::InvalidFrame(error) => write!(f, "invalid request: {error}"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub enum FrameError {
+    /// The frame doesn't fit in the buffer.
+    TooLong,
+
+    /// The frame isn't valid COBS, or is too short for its version and checksum.
+    InvalidEncoding,
+
+    /// The checksum doesn't match, so the frame was corrupted.
+    InvalidChecksum,
+
+    /// The frame is of another version of the protocol, the one given.
+    UnsupportedVersion(u8),
+
+    /// The message isn't one of this version, or is followed by extra bytes.
+    InvalidMessage,
+}
+
+impl Display for FrameError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::TooLong => f.write_str("frame too long"),
+            FrameError::InvalidEncoding => f.write_str("invalid encoding"),
+            FrameError::InvalidChecksum => f.write_str("invalid checksum"),
+            FrameError::UnsupportedVersion(version) => {
+                write!(f, "unsupported version {version}, expected {VERSION}")
+            }
+            FrameError::InvalidMessage => f.write_str("invalid message"),
+        }
+    }
+}
+
+/// The frame timing statistics of the current period, as sent to the host.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub struct Statistics {
+    pub frames: u32,
+
+    /// Duration of the current period in milliseconds.
+    pub elapsed: u32,
+
+    pub frame_rate: u32,
+    pub busy_percentage: u8,
+    pub update: Timing,
+
+    /// Time spent writing the frames, for each section.
+    pub write: [Timing; 2],
+
+    pub idle: Timing,
+    pub write_errors: [u32; 2],
+    pub skipped_frames: u32,
+    pub reinitializations: [u32; 2],
+}
+
+impl From<&FrameStatistics> for Statistics {
+    fn from(statistics: &FrameStatistics) -> Self {
+        let per_section = |value: fn(&FrameStatistics, Section) -> u32| {
+            Section::ALL.map(|section| value(statistics, section))
+        };
+
+        Self {
+            frames: statistics.frames(),
+            elapsed: statistics.elapsed().as_millis() as u32,
+            frame_rate: statistics.frame_rate(),
+            busy_percentage: statistics.busy_percentage(),
+            update: Timing::from(statistics.update()),
+            write: Section::ALL.map(|section| Timing::from(statistics.write(section))),
+            idle: Timing::from(statistics.idle()),
+            write_errors: per_section(FrameStatistics::write_errors),
+            skipped_frames: statistics.skipped_frames(),
+            reinitializations: per_section(FrameStatistics::reinitializations),
+        }
+    }
+}
+
+impl Display for Statistics {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} frames in {} ms ({} fps, {}% busy), ",
+            self.frames, self.elapsed, self.frame_rate, self.busy_percentage
+        )?;
+        write!(
+            f,
+            "update {}, write section 1 {}, write section 2 {}, idle {} (min/avg/max)",
+            self.update, self.write[0], self.write[1], self.idle
+        )?;
+        write!(
+            f,
+            ", write errors {}/{}, skipped frames {}, reinitializations {}/{} (section 1/2)",
+            self.write_errors[0],
+            self.write_errors[1],
+            self.skipped_frames,
+            self.reinitializations[0],
+            self.reinitializations[1]
+        )
+    }
+}
+
+/// Minimum, average and maximum of the durations recorded, in microseconds.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Format, PartialEq, Serialize)]
+pub struct Timing {
+    pub min: u32,
+    pub average: u32,
+    pub max: u32,
+}
+
+impl From<&Statistic> for Timing {
+    fn from(statistic: &Statistic) -> Self {
+        Self {
+            min: statistic.min().as_micros() as u32,
+            average: statistic.average().as_micros() as u32,
+            max: statistic.max().as_micros() as u32,
+        }
+    }
+}
+
+impl Display for Timing {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}/{} us", self.min, self.average, self.max)
+    }
+}
+
+/// Colors of the current frame of the animation, before their corrections.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Chunk {
+    /// The first LED of the chunk.
+    pub offset: u16,
+
+    /// The number of LEDs of the frame.
+    pub total: u16,
+
+    length: u8,
+    colors: [RGB8; CHUNK_SIZE],
+}
+
+impl Chunk {
+    /// The chunk of the frame published for the query from the given LED, which is empty past the
+    /// end, unless the frame isn't published yet.
+    #[must_use]
+    pub fn of_frame(offset: u16) -> Option<Self> {
+        let mut colors = [RGB8::default(); CHUNK_SIZE];
+        let length = command::frame(usize::from(offset), &mut colors)?;
+
+        Some(Self {
+            offset,
+            total: LEDS_TOTAL as u16,
+            length: length as u8,
+            colors,
+        })
+    }
+
+    /// The colors of the LEDs of the chunk, in order.
+    #[must_use]
+    pub fn colors(&self) -> &[RGB8] {
+        &self.colors[..usize::from(self.length).min(CHUNK_SIZE)]
+    }
+}
+
+impl Format for Chunk {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(
+            f,
+            "{} LEDs from {} of {}",
+            self.colors().len(),
+            self.offset,
+            self.total
+        );
+    }
+}
+
+/// Encodes the message in a frame, with its delimiters, and returns the length of the frame.
+///
+/// # Errors
+///
+/// Returns [`FrameError::TooLong`] when the message or the frame doesn't fit, which a buffer of
+/// [`FRAME_SIZE`] prevents.
+pub fn encode<T: Serialize>(message: &T, frame: &mut [u8]) -> Result<usize, FrameError> {
+    let mut raw = [0; RAW_FRAME_SIZE];
+    raw[0] = VERSION;
+    let length = postcard::to_slice(message, &mut raw[1..=MESSAGE_SIZE])
+        .map_err(|_| FrameError::TooLong)?
+        .len();
+    let checksum = crc16(&raw[..=length]);
+    raw[1 + length..3 + length].copy_from_slice(&checksum.to_le_bytes());
+
+    let (first, rest) = frame.split_first_mut().ok_or(FrameError::TooLong)?;
+    *first = DELIMITER;
+    let encoded = cobs::try_encode(&raw[..3 + length], rest).map_err(|_| FrameError::TooLong)?;
+    *rest.get_mut(encoded).ok_or(FrameError::TooLong)? = DELIMITER;
+
+    Ok(encoded + 2)
+}
+
+/// Decodes the message of a frame, the bytes between its delimiters, in place.
+///
+/// # Errors
+///
+/// Returns why the frame was rejected.
+pub fn decode<'a, T: Deserialize<'a>>(frame: &'a mut [u8]) -> Result<T, FrameError> {
+    let length = cobs::decode_in_place(frame).map_err(|_| FrameError::InvalidEncoding)?;
+    if length < 3 {
+        return Err(FrameError::InvalidEncoding);
+    }
+
+    let (content, checksum) = frame[..length].split_at(length - 2);
+    if checksum != crc16(content).to_le_bytes() {
+        return Err(FrameError::InvalidChecksum);
+    }
+    if content[0] != VERSION {
+        return Err(FrameError::UnsupportedVersion(content[0]));
+    }
+
+    match postcard::take_from_bytes(&content[1..]) {
+        Ok((message, [])) => Ok(message),
+        _ => Err(FrameError::InvalidMessage),
+    }
+}
+
+/// Answers a request, and encodes the response in a frame whose length is returned.
+///
+/// # Errors
+///
+/// Returns [`FrameError::TooLong`] when the frame doesn't fit, which a buffer of [`FRAME_SIZE`]
+/// prevents.
+pub fn answer(request: Result<Request, FrameError>, frame: &mut [u8]) -> Result<usize, FrameError> {
+    let response = match request {
+        Ok(Request::Command(Command::Status)) => match command::status() {
+            Some(status) => Response::Status(status),
+            None => Response::Error(ResponseError::NotStarted),
+        },
+        Ok(Request::Command(Command::Stats)) => {
+            Response::Statistics(Statistics::from(&telemetry::snapshot(Instant::now())))
+        }
+        Ok(Request::Command(command)) => match command::send(command) {
+            Ok(()) => Response::Queued,
+            Err(_) => Response::Error(ResponseError::Busy),
+        },
+        Ok(Request::Frame { offset }) => match Chunk::of_frame(offset) {
+            Some(chunk) => Response::Frame(chunk),
+            None => Response::Error(ResponseError::Busy),
+        },
+        Err(error) => Response::Error(ResponseError::InvalidFrame(error)),
+    };
+
+    encode(&response, frame)
+}
@@ -1,7 +1,20 @@
+use crate::animations::Section;
+use crate::color_correction::ColorCorrection;
+use crate::command::Command;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_sync::channel::Channel;
 use embassy_sync::signal::Signal;
 
 pub static ANIMATION_CHANGED_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static BRIGHTNESS_READ_SIGNAL: Signal<CriticalSectionRawMutex, u16> = Signal::new();
+/// The commands of the consoles and control protocols, queued to the task running the animation.
+pub static COMMAND_CHANNEL: Channel<CriticalSectionRawMutex, Command, 4> = Channel::new();
+/// The color correction of a section, or of both sections when it's `None`, from the network
+/// protocols. The other consoles queue a command instead.
+pub static COLOR_CORRECTION_CHANGED_SIGNAL: Signal<
+    CriticalSectionRawMutex,
+    (Option<Section>, ColorCorrection),
+> = Signal::new();
 pub static COLOR_CHANGED_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
 pub static DELAY_READ_SIGNAL: Signal<CriticalSectionRawMutex, u16> = Signal::new();
+pub static DITHERING_TOGGLED_SIGNAL: Signal<CriticalSectionRawMutex, ()> = Signal::new();
@@ -0,0 +1,362 @@
+//! Persistence of the settings in the flash memory, so that they survive a restart.
+//!
+//! The settings are written as a record at the start of a sector reserved for them, followed by the
+//! record of the preset. Each record has a version and a checksum, so that an erased sector, a
+//! partly written record or one written by an incompatible firmware is ignored and the defaults are
+//! used instead. Both records are written again whenever one of them changes, since the sector is
+//! erased as a whole.
+
+use crate::animations::{AnimationKind, Section, Settings};
+use crate::color_correction::ColorCorrection;
+use crate::command::{Color, Status};
+use defmt::{Debug2Format, Format, warn};
+use embassy_time::{Duration, Instant};
+use embedded_storage::nor_flash::{NorFlash, NorFlashError, NorFlashErrorKind};
+use smart_leds::RGB8;
+
+/// The layout of a kind of record, identified by its magic.
+struct Layout {
+    magic: [u8; 4],
+
+    /// Incremented whenever the layout of the record changes.
+    version: u8,
+}
+
+/// The color corrections of the two sections, "CMST" in ASCII.
+const SETTINGS: Layout = Layout {
+    magic: *b"CMST",
+    version: 1,
+};
+
+/// The animation and its settings saved as a preset, "CMPR" in ASCII.
+const PRESET: Layout = Layout {
+    magic: *b"CMPR",
+    version: 1,
+};
+
+/// The size of the header of the records, with their magic and version.
+const HEADER_SIZE: usize = 5;
+
+/// The header, the color corrections of the two sections and the checksum.
+const RECORD_SIZE: usize = HEADER_SIZE + 2 * 6 + 2;
+
+/// The header, the animation, the brightness, the color, the delay, the dithering and the checksum.
+const PRESET_SIZE: usize = HEADER_SIZE + 1 + 1 + 4 + 4 + 1 + 2;
+
+/// Large enough for each record padded to the write size of the flash. The records are stored one
+/// after the other in slots of this size.
+const BUFFER_SIZE: usize = 32;
+
+/// The offset of the preset record from the start of the sector.
+const PRESET_SLOT: u32 = BUFFER_SIZE as u32;
+
+/// How long the settings changed by the commands must stay unchanged before they're saved.
+pub const SAVE_DELAY: Duration = Duration::from_secs(5);
+
+/// The error returned by the flash memory when the settings can't be saved.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct StorageError {
+    kind: NorFlashErrorKind,
+}
+
+impl StorageError {
+    #[must_use]
+    pub fn kind(&self) -> NorFlashErrorKind {
+        self.kind
+    }
+}
+
+impl Format for StorageError {
+    fn format(&self, f: defmt::Formatter) {
+        defmt::write!(f, "{}", Debug2Format(&self.kind));
+    }
+}
+
+impl<E: NorFlashError> From<E> for StorageError {
+    fn from(error: E) -> Self {
+        Self { kind: error.kind() }
+    }
+}
+
+/// The error returned when the preset can't be saved.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum PresetError {
+    /// The streaming animation only shows the frames of a host, so it can't be restored.
+    Streaming,
+
+    Storage(StorageError),
+}
+
+impl From<StorageError> for PresetError {
+    fn from(error: StorageError) -> Self {
+        Self::Storage(error)
+    }
+}
+
+/// The settings kept in a sector of the flash memory.
+pub struct SettingsStorage<F> {
+    flash: F,
+    offset: u32,
+}
+
+impl<F: NorFlash> SettingsStorage<F> {
+    /// The offset is the start of a sector reserved for the settings.
+    pub fn new(flash: F, offset: u32) -> Self {
+        Self { flash, offset }
+    }
+
+    /// Restores the persisted settings, and returns whether there were any.
+    pub fn load(&mut self, settings: &mut Settings) -> bool {
+        let Some(record) = self.read::<RECORD_SIZE>(0, &SETTINGS) else {
+            return false;
+        };
+
+        for (section, bytes) in Section::ALL
+            .into_iter()
+            .zip(record[HEADER_SIZE..RECORD_SIZE - 2].chunks_exact(6))
+        {
+            settings.set_color_correction(
+                section,
+                ColorCorrection::new(
+                    RGB8::new(bytes[0], bytes[1], bytes[2]),
+                    RGB8::new(bytes[3], bytes[4], bytes[5]),
+                ),
+            );
+        }
+
+        true
+    }
+
+    /// Restores the status saved as a preset, if there's one.
+    pub fn load_preset(&mut self) -> Option<Status> {
+        self.read::<PRESET_SIZE>(PRESET_SLOT, &PRESET)
+            .and_then(|record| decode_preset(&record))
+    }
+
+    /// Persists the settings, unless they're already.
+    ///
+    /// # Errors
+    ///
+    /// Returns the error of the flash memory when the sector can't be erased or written.
+    pub fn save(&mut self, settings: &Settings) -> Result<(), StorageError> {
+        let record = encode(settings);
+        if self.read(0, &SETTINGS) == Some(record) {
+            return Ok(());
+        }
+
+        let preset = self.read(PRESET_SLOT, &PRESET);
+        self.write(Some(record), preset)
+    }
+
+    /// Persists the status as the preset, unless it's already.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error when the status is the streaming animation, which isn't saved, or the
+    /// error of the flash memory when the sector can't be erased or written.
+    pub fn save_preset(&mut self, status: &Status) -> Result<(), PresetError> {
+        let preset = encode_preset(status).ok_or(PresetError::Streaming)?;
+        if self.read(PRESET_SLOT, &PRESET) == Some(preset) {
+            return Ok(());
+        }
+
+        let record = self.read(0, &SETTINGS);
+        Ok(self.write(record, Some(preset))?)
+    }
+
+    /// Reads the record of a slot, if it holds a valid one.
+    fn read<const N: usize>(&mut self, slot: u32, layout: &Layout) -> Option<[u8; N]> {
+        let mut buffer = [0; BUFFER_SIZE];
+        let length = const { padded(N, F::READ_SIZE) };
+
+        if let Err(error) = self.flash.read(self.offset + slot, &mut buffer[..length]) {
+            warn!("Cannot read the settings: {}", Debug2Format(&error.kind()));
+            return None;
+        }
+
+        let mut record = [0; N];
+        record.copy_from_slice(&buffer[..N]);
+
+        let (content, checksum) = record.split_at(N - 2);
+        if content[..layout.magic.len()] != layout.magic {
+            return None;
+        }
+        if content[layout.magic.len()] != layout.version {
+            warn!(
+                "Ignoring the settings of version {}",
+                content[layout.magic.len()]
+            );
+            return None;
+        }
+        if checksum != crc16(content).to_le_bytes() {
+            warn!("Ignoring the settings with an invalid checksum");
+            return None;
+        }
+
+        Some(record)
+    }
+
+    /// Erases the sector, then writes the records to their slot.
+    fn write(
+        &mut self, record: Option<[u8; RECORD_SIZE]>, preset: Option<[u8; PRESET_SIZE]>,
+    ) -> Result<(), StorageError> {
+        self.flash
+            .erase(self.offset, self.offset + F::ERASE_SIZE as u32)?;
+
+        if let Some(record) = record {
+            self.write_slot(0, &record)?;
+        }
+        if let Some(preset) = preset {
+            self.write_slot(PRESET_SLOT, &preset)?;
+        }
+
+        Ok(())
+    }
+
+    fn write_slot<const N: usize>(
+        &mut self, slot: u32, record: &[u8; N],
+    ) -> Result<(), StorageError> {
+        let mut buffer = [u8::MAX; BUFFER_SIZE];
+        buffer[..N].copy_from_slice(record);
+        let length = const { padded(N, F::WRITE_SIZE) };
+
+        self.flash.write(self.offset + slot, &buffer[..length])?;
+
+        Ok(())
+    }
+}
+
+/// Defers the save of the settings changed by the consoles and the network protocols until they
+/// stop changing, so that a client sending them at frame rate doesn't erase the sector on each of
+/// them and wear out the flash. Each save also stalls the LEDs while the sector is erased.
+pub struct DeferredSave {
+    /// When the settings last changed, unless they're saved.
+    changed_at: Option<Instant>,
+}
+
+impl DeferredSave {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self { changed_at: None }
+    }
+
+    /// Records a change of the settings, which delays their save again.
+    pub fn changed(&mut self, now: Instant) {
+        self.changed_at = Some(now);
+    }
+
+    /// Returns whether the settings should be saved, once they've been unchanged for
+    /// [`SAVE_DELAY`]. It only returns `true` once per change.
+    pub fn is_due(&mut self, now: Instant) -> bool {
+        let due = self
+            .changed_at
+            .is_some_and(|changed_at| now.saturating_duration_since(changed_at) >= SAVE_DELAY);
+        if due {
+            self.changed_at = None;
+        }
+        due
+    }
+}
+
+impl Default for DeferredSave {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn encode(settings: &Settings) -> [u8; RECORD_SIZE] {
+    let mut record = [0; RECORD_SIZE];
+
+    for (section, bytes) in Section::ALL
+        .into_iter()
+        .zip(record[HEADER_SIZE..RECORD_SIZE - 2].chunks_exact_mut(6))
+    {
+        let color_correction = settings.color_correction(section);
+        let (correction, temperature) = (
+            color_correction.correction(),
+            color_correction.temperature(),
+        );
+        bytes.copy_from_slice(&[
+            correction.r,
+            correction.g,
+            correction.b,
+            temperature.r,
+            temperature.g,
+            temperature.b,
+        ]);
+    }
+
+    seal(&mut record, &SETTINGS);
+    record
+}
+
+/// Returns `None` for the animations that can't be restored, which aren't in `AnimationKind::ALL`.
+fn encode_preset(status: &Status) -> Option<[u8; PRESET_SIZE]> {
+    let mut record = [0; PRESET_SIZE];
+
+    let animation = AnimationKind::ALL
+        .iter()
+        .position(|&kind| kind == status.animation)?;
+    let color = match status.color {
+        Color::Index(index) => [0, index, 0, 0],
+        Color::Rgb(color) => [1, color.r, color.g, color.b],
+    };
+    let content = &mut record[HEADER_SIZE..PRESET_SIZE - 2];
+    content[0] = animation as u8;
+    content[1] = status.brightness;
+    content[2..6].copy_from_slice(&color);
+    content[6..10].copy_from_slice(&status.delay.to_le_bytes());
+    content[10] = u8::from(status.dithering);
+
+    seal(&mut record, &PRESET);
+    Some(record)
+}
+
+fn decode_preset(record: &[u8; PRESET_SIZE]) -> Option<Status> {
+    let content = &record[HEADER_SIZE..PRESET_SIZE - 2];
+    let color = match content[2] {
+        0 => Color::Index(content[3]),
+        1 => Color::Rgb(RGB8::new(content[3], content[4], content[5])),
+        _ => return None,
+    };
+
+    Some(Status {
+        animation: *AnimationKind::ALL.get(usize::from(content[0]))?,
+        brightness: content[1],
+        color,
+        delay: u32::from_le_bytes([content[6], content[7], content[8], content[9]]),
+        dithering: content[10] != 0,
+    })
+}
+
+/// Writes the header and the checksum of the record.
+fn seal<const N: usize>(record: &mut [u8; N], layout: &Layout) {
+    record[..layout.magic.len()].copy_from_slice(&layout.magic);
+    record[layout.magic.len()] = layout.version;
+
+    let checksum = crc16(&record[..N - 2]);
+    record[N - 2..].copy_from_slice(&checksum.to_le_bytes());
+}
+
+/// The size of a record once padded to the given size, which must fit in the buffer.
+const fn padded(record_size: usize, size: usize) -> usize {
+    let padded = record_size.next_multiple_of(size);
+    assert!(
+        padded <= BUFFER_SIZE,
+        "the record doesn't fit in the buffer"
+    );
+    padded
+}
+
+/// CRC-16/CCITT-FALSE of the bytes.
+pub(crate) fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
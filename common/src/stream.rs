@@ -0,0 +1,137 @@
+//! Live frames streamed by a host, like the colors of the screen from Hyperion or Prismatik.
+//!
+//! The streaming protocols write the colors of the LEDs as they're received, then show the frame
+//! once it's complete. The streaming animation takes over the animation rotation while frames keep
+//! coming, and the rotation resumes once none was shown for [`STREAM_TIMEOUT`].
+
+use crate::animations::{AnimationKind, LEDS_TOTAL, LedData};
+use core::cell::RefCell;
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Instant};
+use smart_leds::{RGB8, RGB16};
+
+/// How long the stream stays active after its last frame.
+pub const STREAM_TIMEOUT: Duration = Duration::from_secs(3);
+
+static STREAM: Mutex<CriticalSectionRawMutex, RefCell<Stream>> =
+    Mutex::new(RefCell::new(Stream::new()));
+
+struct Stream {
+    /// The frame being received.
+    received: [RGB8; LEDS_TOTAL],
+
+    /// The last complete frame, so that the animation never shows a partial one.
+    shown: [RGB8; LEDS_TOTAL],
+
+    /// When the last frame was shown, unless none was since the board started.
+    shown_at: Option<Instant>,
+}
+
+impl Stream {
+    const fn new() -> Self {
+        Self {
+            received: [RGB8::new(0, 0, 0); LEDS_TOTAL],
+            shown: [RGB8::new(0, 0, 0); LEDS_TOTAL],
+            shown_at: None,
+        }
+    }
+}
+
+/// Writes the colors of the LEDs from the given one. The colors past the last LED are ignored.
+pub fn write(offset: usize, colors: impl IntoIterator<Item = RGB8>) {
+    STREAM.lock(|stream| {
+        let mut stream = stream.borrow_mut();
+        let received = stream.received.get_mut(offset..).unwrap_or_default();
+        for (led, color) in received.iter_mut().zip(colors) {
+            *led = color;
+        }
+    });
+}
+
+/// Writes the channels of the LEDs from the given one, three per LED in RGB order, for the
+/// protocols whose packets don't align with the LEDs. The channels past the last LED are ignored.
+pub fn write_channels(offset: usize, channels: &[u8]) {
+    STREAM.lock(|stream| {
+        let mut stream = stream.borrow_mut();
+        for (index, &channel) in (offset..).zip(channels) {
+            let Some(led) = stream.received.get_mut(index / 3) else {
+                break;
+            };
+            match index % 3 {
+                0 => led.r = channel,
+                1 => led.g = channel,
+                _ => led.b = channel,
+            }
+        }
+    });
+}
+
+/// Shows the frame written, which keeps the stream active.
+pub fn show(now: Instant) {
+    STREAM.lock(|stream| {
+        let stream = &mut *stream.borrow_mut();
+        stream.shown = stream.received;
+        stream.shown_at = Some(now);
+    });
+}
+
+/// Whether a frame was shown during the timeout.
+#[must_use]
+pub fn is_active(now: Instant) -> bool {
+    STREAM.lock(|stream| {
+        stream
+            .borrow()
+            .shown_at
+            .is_some_and(|shown_at| now.saturating_duration_since(shown_at) < STREAM_TIMEOUT)
+    })
+}
+
+/// Copies the colors of the frame shown to the LEDs.
+pub(crate) fn read(data: &mut LedData) {
+    STREAM.lock(|stream| {
+        for (led, color) in data.iter_mut().zip(stream.borrow().shown) {
+            *led = RGB16::new(
+                u16::from(color.r) * 257,
+                u16::from(color.g) * 257,
+                u16::from(color.b) * 257,
+            );
+        }
+    });
+}
+
+/// Switches to the streaming animation while the stream is active, and back to the animation it
+/// interrupted once the stream stops.
+///
+/// An animation started while streaming, like with the button, is resumed once the stream stops.
+pub struct StreamSwitch {
+    interrupted: AnimationKind,
+}
+
+impl StreamSwitch {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            interrupted: AnimationKind::ALL[0],
+        }
+    }
+
+    /// Returns the animation to start when the stream starts or stops.
+    pub fn update(&mut self, active_kind: AnimationKind, now: Instant) -> Option<AnimationKind> {
+        let streaming = active_kind == AnimationKind::Streaming;
+        match (is_active(now), streaming) {
+            (true, false) => {
+                self.interrupted = active_kind;
+                Some(AnimationKind::Streaming)
+            }
+            (false, true) => Some(self.interrupted),
+            _ => None,
+        }
+    }
+}
+
+impl Default for StreamSwitch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,200 @@
+//! Byte order of the channels and white channel of the LED strips.
+//!
+//! The animations only produce RGB colors. Before they're sent, the channels are rearranged in the
+//! order expected by the strip, and for RGBW strips like the SK6812 the white channel is extracted
+//! from them. The drivers send the channels in the GRB order of the WS2812, followed by the white
+//! channel of RGBW strips.
+
+use defmt::Format;
+use smart_leds::RGB8;
+use smart_leds_trait::{RGBW, SmartLedsWrite, SmartLedsWriteAsync, White};
+
+/// The order in which a strip expects the red, green and blue channels.
+#[derive(Clone, Copy, Debug, Default, Eq, Format, PartialEq)]
+pub enum ColorOrder {
+    Rgb,
+    Rbg,
+    #[default]
+    Grb,
+    Gbr,
+    Brg,
+    Bgr,
+}
+
+impl ColorOrder {
+    /// Rearranges the channels, so that a driver sending them in the GRB order sends them in this
+    /// order instead.
+    #[must_use]
+    pub fn arrange(self, color: RGB8) -> RGB8 {
+        let RGB8 { r, g, b } = color;
+        let [first, second, third] = match self {
+            ColorOrder::Rgb => [r, g, b],
+            ColorOrder::Rbg => [r, b, g],
+            ColorOrder::Grb => [g, r, b],
+            ColorOrder::Gbr => [g, b, r],
+            ColorOrder::Brg => [b, r, g],
+            ColorOrder::Bgr => [b, g, r],
+        };
+
+        RGB8 {
+            r: second,
+            g: first,
+            b: third,
+        }
+    }
+}
+
+/// How the white channel of RGBW strips is computed from the RGB colors.
+#[derive(Clone, Copy, Debug, Default, Eq, Format, PartialEq)]
+pub enum WhiteMode {
+    /// The white channel stays off, and the strip is used like an RGB one.
+    None,
+
+    /// The white shared by the three channels is moved to the white channel, which keeps the
+    /// colors and the draw close to the RGB ones.
+    #[default]
+    Accurate,
+
+    /// The white shared by the three channels is added by the white channel, which is brighter
+    /// but washes the colors out. The power budget doesn't account for the extra draw.
+    Brighter,
+
+    /// The white channel lights up to the brightest channel, and the RGB channels stay off. It
+    /// turns the strip into a monochrome one, following the brightness of the animations.
+    Max,
+}
+
+impl WhiteMode {
+    /// Computes the white channel of the color.
+    #[must_use]
+    pub fn extract(self, color: RGB8) -> RGBW<u8> {
+        let RGB8 { r, g, b } = color;
+        let white = r.min(g).min(b);
+
+        let (color, white) = match self {
+            WhiteMode::None => (color, 0),
+            WhiteMode::Accurate => (RGB8::new(r - white, g - white, b - white), white),
+            WhiteMode::Brighter => (color, white),
+            WhiteMode::Max => (RGB8::default(), r.max(g).max(b)),
+        };
+
+        RGBW {
+            r: color.r,
+            g: color.g,
+            b: color.b,
+            a: White(white),
+        }
+    }
+}
+
+/// The kind of strip of a section, to select its driver.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum StripKind {
+    /// RGB strips like the WS2812.
+    Rgb(ColorOrder),
+
+    /// RGBW strips like the SK6812.
+    Rgbw(ColorOrder, WhiteMode),
+}
+
+impl StripKind {
+    /// The WS2812 in its native GRB order.
+    pub const WS2812: Self = Self::Rgb(ColorOrder::Grb);
+
+    /// The SK6812 RGBW in its native GRB order, with the white extracted accurately.
+    pub const SK6812_RGBW: Self = Self::Rgbw(ColorOrder::Grb, WhiteMode::Accurate);
+
+    /// The number of channels of each LED, to size the buffers of the drivers.
+    #[must_use]
+    pub const fn channels(self) -> usize {
+        match self {
+            StripKind::Rgb(_) => 3,
+            StripKind::Rgbw(..) => 4,
+        }
+    }
+}
+
+/// A LED section written with the driver of its kind of strip, taking RGB colors either way.
+pub enum Strip<R, W> {
+    Rgb {
+        driver: R,
+        order: ColorOrder,
+    },
+    Rgbw {
+        driver: W,
+        order: ColorOrder,
+        white_mode: WhiteMode,
+    },
+}
+
+impl<R, W> SmartLedsWrite for Strip<R, W>
+where
+    R: SmartLedsWrite<Color = RGB8>,
+    W: SmartLedsWrite<Color = RGBW<u8>, Error = R::Error>,
+{
+    type Color = RGB8;
+    type Error = R::Error;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        match self {
+            Strip::Rgb { driver, order } => driver.write(
+                iterator
+                    .into_iter()
+                    .map(|color| order.arrange(color.into())),
+            ),
+            Strip::Rgbw {
+                driver,
+                order,
+                white_mode,
+            } => driver.write(
+                iterator
+                    .into_iter()
+                    .map(|color| white_mode.extract(order.arrange(color.into()))),
+            ),
+        }
+    }
+}
+
+impl<R, W> SmartLedsWriteAsync for Strip<R, W>
+where
+    R: SmartLedsWriteAsync<Color = RGB8>,
+    W: SmartLedsWriteAsync<Color = RGBW<u8>, Error = R::Error>,
+{
+    type Color = RGB8;
+    type Error = R::Error;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        match self {
+            Strip::Rgb { driver, order } => {
+                driver
+                    .write(
+                        iterator
+                            .into_iter()
+                            .map(|color| order.arrange(color.into())),
+                    )
+                    .await
+            }
+            Strip::Rgbw {
+                driver,
+                order,
+                white_mode,
+            } => {
+                driver
+                    .write(
+                        iterator
+                            .into_iter()
+                            .map(|color| white_mode.extract(order.arrange(color.into()))),
+                    )
+                    .await
+            }
+        }
+    }
+}
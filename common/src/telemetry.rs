@@ -0,0 +1,282 @@
+//! Frame timing statistics, to tell how the time of each frame is spent on the device.
+//!
+//! The statistics cover the current period. They are logged and reset when the period ends, or
+//! when the animation changes so that each report describes a single animation.
+
+use crate::animations::{AnimationKind, Section};
+use core::cell::RefCell;
+use defmt::{Format, Formatter, info, write};
+use embassy_sync::blocking_mutex::Mutex;
+use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
+use embassy_time::{Duration, Instant};
+
+/// The duration of the period covered by each report.
+const REPORT_PERIOD: Duration = Duration::from_secs(10);
+
+static FRAME_STATISTICS: Mutex<CriticalSectionRawMutex, RefCell<FrameStatistics>> =
+    Mutex::new(RefCell::new(FrameStatistics::new(Instant::MIN)));
+
+/// Statistics of the frames computed and written during the current period.
+#[derive(Clone, Copy, Debug)]
+pub struct FrameStatistics {
+    /// Time spent waiting for the next frame.
+    idle: Statistic,
+
+    /// Number of times the driver of each section was reinitialized after repeated errors.
+    reinitializations: [u32; 2],
+
+    /// Number of frames that were skipped because they couldn't be written.
+    skipped_frames: u32,
+
+    /// When the current period started.
+    start: Instant,
+
+    /// When the statistics were taken, the end of the period they describe.
+    taken: Instant,
+
+    /// Time spent computing the frames.
+    update: Statistic,
+
+    /// Time spent writing the frames, for each section.
+    write: [Statistic; 2],
+
+    /// Number of writes that failed, for each section.
+    write_errors: [u32; 2],
+}
+
+impl FrameStatistics {
+    const fn new(start: Instant) -> Self {
+        Self {
+            idle: Statistic::new(),
+            reinitializations: [0; 2],
+            skipped_frames: 0,
+            start,
+            taken: start,
+            update: Statistic::new(),
+            write: [Statistic::new(); 2],
+            write_errors: [0; 2],
+        }
+    }
+
+    /// Percentage of the period spent computing and writing the frames instead of waiting.
+    ///
+    /// An animation is CPU-bound when it's close to 100%.
+    #[must_use]
+    pub fn busy_percentage(&self) -> u8 {
+        let elapsed = self.elapsed().as_micros();
+        if elapsed == 0 {
+            return 0;
+        }
+        let idle = self.idle.total.as_micros().min(elapsed);
+        (100 - idle * 100 / elapsed) as u8
+    }
+
+    /// Duration of the period, until the statistics were taken.
+    #[must_use]
+    pub fn elapsed(&self) -> Duration {
+        self.taken.saturating_duration_since(self.start)
+    }
+
+    /// Number of frames per second during the period.
+    #[must_use]
+    pub fn frame_rate(&self) -> u32 {
+        let elapsed = self.elapsed().as_millis();
+        if elapsed == 0 {
+            return 0;
+        }
+        (u64::from(self.frames()) * 1000 / elapsed) as u32
+    }
+
+    /// Number of frames computed during the period.
+    #[must_use]
+    pub fn frames(&self) -> u32 {
+        self.update.count
+    }
+
+    #[must_use]
+    pub fn idle(&self) -> &Statistic {
+        &self.idle
+    }
+
+    #[must_use]
+    pub fn reinitializations(&self, section: Section) -> u32 {
+        self.reinitializations[section as usize]
+    }
+
+    #[must_use]
+    pub fn skipped_frames(&self) -> u32 {
+        self.skipped_frames
+    }
+
+    #[must_use]
+    pub fn update(&self) -> &Statistic {
+        &self.update
+    }
+
+    #[must_use]
+    pub fn write(&self, section: Section) -> &Statistic {
+        &self.write[section as usize]
+    }
+
+    #[must_use]
+    pub fn write_errors(&self, section: Section) -> u32 {
+        self.write_errors[section as usize]
+    }
+}
+
+impl Format for FrameStatistics {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "{} frames in {} ms ({} fps, {}% busy), ",
+            self.frames(),
+            self.elapsed().as_millis(),
+            self.frame_rate(),
+            self.busy_percentage()
+        );
+        write!(
+            f,
+            "update {}, write section 1 {}, write section 2 {}, idle {} (min/avg/max)",
+            self.update,
+            self.write[Section::First as usize],
+            self.write[Section::Second as usize],
+            self.idle
+        );
+        write!(
+            f,
+            ", write errors {}/{}, skipped frames {}, reinitializations {}/{} (section 1/2)",
+            self.write_errors[Section::First as usize],
+            self.write_errors[Section::Second as usize],
+            self.skipped_frames,
+            self.reinitializations[Section::First as usize],
+            self.reinitializations[Section::Second as usize]
+        );
+    }
+}
+
+/// Minimum, average and maximum of the durations recorded during the current period.
+#[derive(Clone, Copy, Debug)]
+pub struct Statistic {
+    count: u32,
+    max: Duration,
+    min: Duration,
+    total: Duration,
+}
+
+impl Statistic {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            count: 0,
+            max: Duration::MIN,
+            min: Duration::MAX,
+            total: Duration::MIN,
+        }
+    }
+
+    #[must_use]
+    pub fn average(&self) -> Duration {
+        self.total.checked_div(self.count).unwrap_or(Duration::MIN)
+    }
+
+    /// Number of durations recorded.
+    #[must_use]
+    pub fn count(&self) -> u32 {
+        self.count
+    }
+
+    #[must_use]
+    pub fn max(&self) -> Duration {
+        self.max
+    }
+
+    #[must_use]
+    pub fn min(&self) -> Duration {
+        if self.count == 0 {
+            Duration::MIN
+        } else {
+            self.min
+        }
+    }
+
+    pub fn record(&mut self, duration: Duration) {
+        self.count = self.count.saturating_add(1);
+        self.max = self.max.max(duration);
+        self.min = self.min.min(duration);
+        self.total += duration;
+    }
+}
+
+impl Default for Statistic {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Format for Statistic {
+    fn format(&self, f: Formatter) {
+        write!(
+            f,
+            "{}/{}/{} us",
+            self.min().as_micros(),
+            self.average().as_micros(),
+            self.max.as_micros()
+        );
+    }
+}
+
+/// Logs the statistics and starts a new period when the current one is over.
+pub fn report_if_due(kind: AnimationKind, now: Instant) {
+    let statistics = snapshot(now);
+    if statistics.elapsed() >= REPORT_PERIOD {
+        info!("{}: {}", kind, statistics);
+        reset(now);
+    }
+}
+
+/// Discards the statistics and starts a new period.
+pub fn reset(now: Instant) {
+    FRAME_STATISTICS.lock(|statistics| {
+        *statistics.borrow_mut() = FrameStatistics::new(now);
+    });
+}
+
+/// Returns the statistics of the current period, until now.
+#[must_use]
+pub fn snapshot(now: Instant) -> FrameStatistics {
+    FRAME_STATISTICS.lock(|statistics| FrameStatistics {
+        taken: now,
+        ..*statistics.borrow()
+    })
+}
+
+pub(crate) fn record_idle(duration: Duration) {
+    FRAME_STATISTICS.lock(|statistics| statistics.borrow_mut().idle.record(duration));
+}
+
+pub(crate) fn record_reinitialization(section: Section) {
+    FRAME_STATISTICS.lock(|statistics| {
+        let mut statistics = statistics.borrow_mut();
+        statistics.reinitializations[section as usize] += 1;
+    });
+}
+
+pub(crate) fn record_skipped_frame() {
+    FRAME_STATISTICS.lock(|statistics| statistics.borrow_mut().skipped_frames += 1);
+}
+
+pub(crate) fn record_update(duration: Duration) {
+    FRAME_STATISTICS.lock(|statistics| statistics.borrow_mut().update.record(duration));
+}
+
+pub(crate) fn record_write(section: Section, duration: Duration) {
+    FRAME_STATISTICS.lock(|statistics| {
+        statistics.borrow_mut().write[section as usize].record(duration);
+    });
+}
+
+pub(crate) fn record_write_error(section: Section) {
+    FRAME_STATISTICS.lock(|statistics| {
+        statistics.borrow_mut().write_errors[section as usize] += 1;
+    });
+}
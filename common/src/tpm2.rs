@@ -0,0 +1,272 @@
+//! TPM2, the streaming protocol of lighting desks and of Jinx! and Glediator, over a serial port or
+//! over UDP with TPM2.net.
+//!
+//! A serial packet starts with `0xC9`, its type and the size of its payload as a big-endian 16-bit
+//! integer, and ends with `0x36` after the payload. The payload of a data packet holds the channels
+//! of the LEDs, three per LED in RGB order. The TPM2.net packets start with `0x9C` instead, and add
+//! the number of the packet and the number of packets of the frame before the payload, so that a
+//! frame larger than a datagram is split across several packets.
+
+use crate::stream;
+use core::fmt;
+use core::fmt::{Display, Formatter};
+use defmt::Format;
+use embassy_time::{Duration, Instant};
+
+/// The first byte of a serial packet.
+pub const SERIAL_START: u8 = 0xC9;
+
+/// The first byte of a TPM2.net packet.
+pub const NET_START: u8 = 0x9C;
+
+/// The UDP port of TPM2.net.
+pub const NET_PORT: u16 = 65506;
+
+/// The largest TPM2.net packet, with the 1490 bytes of payload allowed so that it fits in an
+/// Ethernet frame.
+pub const NET_PACKET_SIZE: usize = NET_OVERHEAD + 1490;
+
+/// How long a serial packet can go without a byte before it's discarded.
+pub const PACKET_TIMEOUT: Duration = Duration::from_millis(100);
+
+/// The last byte of a packet.
+const END: u8 = 0x36;
+
+/// The type of the packets carrying the channels of the LEDs. The command and response packets are
+/// ignored.
+const DATA: u8 = 0xDA;
+
+/// The bytes of a TPM2.net packet other than its payload: the start byte, the type, the size, the
+/// packet number, the number of packets and the end byte.
+const NET_OVERHEAD: usize = 7;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum State {
+    /// Waiting for the start of a packet.
+    Idle,
+
+    /// Receiving the type and the size of the payload, of which the given bytes were received.
+    Header { bytes: [u8; 3], received: usize },
+
+    /// Receiving the payload, written to the stream when it's a data packet.
+    Payload {
+        data: bool,
+        size: usize,
+        received: usize,
+    },
+
+    /// Waiting for the end byte, which shows the frame of a data packet.
+    End { data: bool },
+}
+
+/// Parses the TPM2 packets out of the bytes received by a serial port, and writes their channels to
+/// the stream.
+pub struct Parser {
+    state: State,
+
+    /// When the last byte was received, for the timeout of the packets.
+    received_at: Instant,
+}
+
+impl Parser {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            state: State::Idle,
+            received_at: Instant::from_ticks(0),
+        }
+    }
+
+    /// Whether a packet is being received, so that its bytes aren't mistaken for text.
+    #[must_use]
+    pub fn is_receiving(&self) -> bool {
+        self.state != State::Idle
+    }
+
+    /// Parses a received byte, and returns whether it belongs to a packet.
+    ///
+    /// A packet only starts with its start byte. The channels past the last LED are ignored, and
+    /// the frame is only shown when the packet ends with its end byte.
+    pub fn receive(&mut self, byte: u8, now: Instant) -> bool {
+        if self.state != State::Idle
+            && now.saturating_duration_since(self.received_at) >= PACKET_TIMEOUT
+        {
+            self.state = State::Idle;
+        }
+        self.received_at = now;
+
+        self.state = match self.state {
+            State::Idle if byte == SERIAL_START => State::Header {
+                bytes: [0; 3],
+                received: 0,
+            },
+            State::Idle => return false,
+            State::Header {
+                mut bytes,
+                received,
+            } => {
+                bytes[received] = byte;
+                match received + 1 {
+                    3 => payload(bytes[0] == DATA, u16::from_be_bytes([bytes[1], bytes[2]])),
+                    received => State::Header { bytes, received },
+                }
+            }
+            State::Payload {
+                data,
+                size,
+                received,
+            } => {
+                if data {
+                    stream::write_channels(received, &[byte]);
+                }
+                match received + 1 {
+                    received if received == size => State::End { data },
+                    received => State::Payload {
+                        data,
+                        size,
+                        received,
+                    },
+                }
+            }
+            State::End { data } => {
+                if data && byte == END {
+                    stream::show(now);
+                }
+                State::Idle
+            }
+        };
+
+        true
+    }
+}
+
+impl Default for Parser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Starts receiving the payload, or waits for the end byte when there's none.
+fn payload(data: bool, size: u16) -> State {
+    match usize::from(size) {
+        0 => State::End { data },
+        size => State::Payload {
+            data,
+            size,
+            received: 0,
+        },
+    }
+}
+
+/// Why a TPM2.net packet was rejected.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum PacketError {
+    /// The packet is shorter than its header and end byte.
+    TooShort,
+
+    /// The packet doesn't start with the start byte of TPM2.net.
+    InvalidStart,
+
+    /// The size of the payload doesn't match the length of the packet.
+    InvalidSize,
+
+    /// The packet doesn't end with the end byte.
+    InvalidEnd,
+
+    /// The packet number is 0 or larger than the number of packets.
+    InvalidPacketNumber,
+
+    /// A packet of the frame was lost or reordered, so the frame is dropped until its next first
+    /// packet.
+    MissingPacket,
+}
+
+impl Display for PacketError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PacketError::TooShort => "packet too short",
+            PacketError::InvalidStart => "invalid start byte",
+            PacketError::InvalidSize => "invalid payload size",
+            PacketError::InvalidEnd => "invalid end byte",
+            PacketError::InvalidPacketNumber => "invalid packet number",
+            PacketError::MissingPacket => "missing packet",
+        })
+    }
+}
+
+/// Assembles the frames out of the TPM2.net packets, which are split across several packets when
+/// they're larger than a datagram.
+pub struct NetAssembler {
+    /// The number of the next packet of the frame, unless the frame was dropped.
+    next: Option<u8>,
+
+    /// The channel at which the payload of the next packet is written.
+    offset: usize,
+}
+
+impl NetAssembler {
+    #[must_use]
+    pub const fn new() -> Self {
+        Self {
+            next: None,
+            offset: 0,
+        }
+    }
+
+    /// Decodes a TPM2.net packet and writes its channels to the stream, after the ones of the
+    /// previous packets of the frame. The frame is shown once its last packet is received.
+    ///
+    /// # Errors
+    ///
+    /// Returns why the packet is invalid, or [`PacketError::MissingPacket`] when the packets of
+    /// the frame aren't received in order.
+    pub fn receive(&mut self, packet: &[u8], now: Instant) -> Result<(), PacketError> {
+        let [start, kind, high, low, number, count, ..] = *packet else {
+            return Err(PacketError::TooShort);
+        };
+        if start != NET_START {
+            return Err(PacketError::InvalidStart);
+        }
+        let size = usize::from(u16::from_be_bytes([high, low]));
+        if packet.len() < NET_OVERHEAD || packet.len() - NET_OVERHEAD != size {
+            return Err(PacketError::InvalidSize);
+        }
+        if packet.last() != Some(&END) {
+            return Err(PacketError::InvalidEnd);
+        }
+        if number == 0 || number > count {
+            return Err(PacketError::InvalidPacketNumber);
+        }
+        if kind != DATA {
+            return Ok(());
+        }
+
+        if number == 1 {
+            self.next = Some(1);
+            self.offset = 0;
+        }
+        if self.next != Some(number) {
+            self.next = None;
+            return Err(PacketError::MissingPacket);
+        }
+
+        let payload = &packet[NET_OVERHEAD - 1..packet.len() - 1];
+        stream::write_channels(self.offset, payload);
+        self.offset += payload.len();
+
+        if number == count {
+            stream::show(now);
+            self.next = None;
+        } else {
+            self.next = Some(number + 1);
+        }
+
+        Ok(())
+    }
+}
+
+impl Default for NetAssembler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
@@ -0,0 +1,118 @@
+//! The universes of 512 channels of the DMX512 protocols, like E1.31 and Art-Net, mapped on the
+//! LEDs.
+//!
+//! The LEDs take the channels from the start channel of their first universe, then the first
+//! [`LED_CHANNELS_PER_UNIVERSE`] channels of each following universe, across both sections. The
+//! frame is shown once every universe of the LEDs is received, or when one is received again before
+//! the others.
+
+use crate::animations::LEDS_TOTAL;
+use crate::stream;
+use core::ops::RangeInclusive;
+use defmt::Format;
+use embassy_time::Instant;
+
+/// The channels of each universe written to the LEDs, so that none of the 170 LEDs of a universe is
+/// split across two universes. The last two channels of the universe are ignored.
+pub const LED_CHANNELS_PER_UNIVERSE: usize = 510;
+
+/// The largest number of universes the LEDs span, when the start channel is the last one.
+pub(crate) const UNIVERSES_MAXIMUM: usize =
+    (LEDS_TOTAL * 3).div_ceil(LED_CHANNELS_PER_UNIVERSE) + 1;
+
+/// The universes of the LEDs, from the start channel of the first one.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub(crate) struct Layout {
+    first: u16,
+    start_channel: u16,
+
+    /// The last universe of the protocol, past which the LEDs aren't mapped.
+    last_valid: u16,
+}
+
+impl Layout {
+    /// Maps the LEDs from the start channel of the first universe, the first channel being 1.
+    ///
+    /// # Panics
+    ///
+    /// Panics when the start channel isn't from 1 to 510.
+    pub(crate) const fn new(first: u16, start_channel: u16, last_valid: u16) -> Self {
+        assert!(
+            start_channel >= 1 && start_channel as usize <= LED_CHANNELS_PER_UNIVERSE,
+            "the start channel is from 1 to 510"
+        );
+        Self {
+            first,
+            start_channel,
+            last_valid,
+        }
+    }
+
+    /// The universes of the LEDs.
+    pub(crate) fn universes(&self) -> RangeInclusive<u16> {
+        let others = (LEDS_TOTAL * 3)
+            .saturating_sub(self.first_channels())
+            .div_ceil(LED_CHANNELS_PER_UNIVERSE);
+        // The LEDs past the last valid universe aren't mapped.
+        let last = self
+            .first
+            .saturating_add(u16::try_from(others).unwrap_or(u16::MAX));
+        self.first..=last.min(self.last_valid)
+    }
+
+    /// The index of the universe among the ones of the LEDs.
+    pub(crate) fn index(&self, universe: u16) -> Option<usize> {
+        self.universes()
+            .contains(&universe)
+            .then(|| usize::from(universe - self.first))
+    }
+
+    /// The channels of the first universe written to the LEDs.
+    fn first_channels(&self) -> usize {
+        LED_CHANNELS_PER_UNIVERSE + 1 - usize::from(self.start_channel)
+    }
+
+    /// Writes the channels of the universe at the given index to the stream.
+    fn write(&self, index: usize, channels: &[u8]) {
+        let (offset, first) = match index {
+            0 => (0, usize::from(self.start_channel) - 1),
+            index => (
+                self.first_channels() + (index - 1) * LED_CHANNELS_PER_UNIVERSE,
+                0,
+            ),
+        };
+        let channels = channels
+            .get(first..channels.len().min(LED_CHANNELS_PER_UNIVERSE))
+            .unwrap_or_default();
+        stream::write_channels(offset, channels);
+    }
+}
+
+/// Assembles the frames out of the universes of the LEDs.
+pub(crate) struct Frame {
+    /// The universes received since the last frame was shown, one bit per index.
+    received: u32,
+}
+
+impl Frame {
+    pub(crate) const fn new() -> Self {
+        Self { received: 0 }
+    }
+
+    /// Writes the channels of the universe at the given index to the stream, and shows the frame
+    /// once all the universes of the layout are received.
+    pub(crate) fn write(&mut self, layout: &Layout, index: usize, channels: &[u8], now: Instant) {
+        // A universe received again means the others of the frame were lost, or aren't sent.
+        let bit = 1 << index;
+        if self.received & bit != 0 {
+            stream::show(now);
+            self.received = 0;
+        }
+        layout.write(index, channels);
+        self.received |= bit;
+        if self.received == (1 << layout.universes().len()) - 1 {
+            stream::show(now);
+            self.received = 0;
+        }
+    }
+}
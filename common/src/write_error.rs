@@ -0,0 +1,138 @@
+//! Handling of the errors that happen when writing the colors to the LEDs.
+//!
+//! A failed write shouldn't take the whole device down. Instead, the controller follows a policy
+//! to decide whether the frame is written again, skipped, or whether the driver of the section that
+//! keeps failing is reinitialized.
+
+use crate::animations::Section;
+use crate::telemetry;
+use defmt::{Format, warn};
+
+/// The sections of LEDs that couldn't be written.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub struct WriteError {
+    failed: [bool; 2],
+}
+
+impl WriteError {
+    pub(crate) fn new(failed_section_1: bool, failed_section_2: bool) -> Self {
+        Self {
+            failed: [failed_section_1, failed_section_2],
+        }
+    }
+
+    /// Whether the given section couldn't be written.
+    #[must_use]
+    pub fn failed(&self, section: Section) -> bool {
+        self.failed[section as usize]
+    }
+}
+
+/// What the controller does when the colors can't be written to the LEDs.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum WriteErrorPolicy {
+    /// Writes the frame again, up to the given number of times, then skips it.
+    Retry(u8),
+
+    /// Skips the frame, the next one is written as usual.
+    SkipFrame,
+
+    /// Skips the frames, and reinitializes the driver of a section once the given number of
+    /// consecutive frames failed to be written to it.
+    Reinitialize(u8),
+}
+
+/// What the controller must do after rendering a frame.
+#[derive(Clone, Copy, Debug, Eq, Format, PartialEq)]
+pub enum WriteErrorAction {
+    /// Carry on with the next frame.
+    NextFrame,
+
+    /// Render the same frame again.
+    Retry,
+
+    /// Reinitialize the drivers of the sections that failed, then carry on with the next frame.
+    Reinitialize(WriteError),
+}
+
+/// Applies a [`WriteErrorPolicy`] to the result of rendering each frame.
+#[derive(Clone, Debug)]
+pub struct WriteErrorHandler {
+    /// Number of consecutive frames that couldn't be written, for each section.
+    consecutive_failures: [u8; 2],
+
+    policy: WriteErrorPolicy,
+
+    /// Number of times the current frame was written again.
+    retries: u8,
+}
+
+impl WriteErrorHandler {
+    #[must_use]
+    pub const fn new(policy: WriteErrorPolicy) -> Self {
+        Self {
+            consecutive_failures: [0; 2],
+            policy,
+            retries: 0,
+        }
+    }
+
+    /// Decides what to do next, given the result of rendering a frame.
+    pub fn handle(&mut self, result: Result<(), WriteError>) -> WriteErrorAction {
+        let Err(error) = result else {
+            self.consecutive_failures = [0; 2];
+            self.retries = 0;
+            return WriteErrorAction::NextFrame;
+        };
+
+        match self.policy {
+            WriteErrorPolicy::Retry(attempts) if self.retries < attempts => {
+                self.retries += 1;
+                WriteErrorAction::Retry
+            }
+            WriteErrorPolicy::Retry(attempts) => {
+                warn!("Skipping the frame after {} retries", attempts);
+                self.retries = 0;
+                telemetry::record_skipped_frame();
+                WriteErrorAction::NextFrame
+            }
+            WriteErrorPolicy::SkipFrame => {
+                telemetry::record_skipped_frame();
+                WriteErrorAction::NextFrame
+            }
+            WriteErrorPolicy::Reinitialize(threshold) => {
+                telemetry::record_skipped_frame();
+                self.count_consecutive_failures(error, threshold)
+            }
+        }
+    }
+
+    fn count_consecutive_failures(&mut self, error: WriteError, threshold: u8) -> WriteErrorAction {
+        let mut reinitialize = [false; 2];
+
+        for section in Section::ALL {
+            let failures = &mut self.consecutive_failures[section as usize];
+            if !error.failed(section) {
+                *failures = 0;
+                continue;
+            }
+
+            *failures = failures.saturating_add(1);
+            if *failures >= threshold {
+                warn!(
+                    "Reinitializing the {} section after {} failed frames",
+                    section, *failures
+                );
+                *failures = 0;
+                reinitialize[section as usize] = true;
+                telemetry::record_reinitialization(section);
+            }
+        }
+
+        if reinitialize == [false; 2] {
+            WriteErrorAction::NextFrame
+        } else {
+            WriteErrorAction::Reinitialize(WriteError::new(reinitialize[0], reinitialize[1]))
+        }
+    }
+}
@@ -0,0 +1,333 @@
+//! Drivers of the WS2812 and SK6812 strips over SPI.
+//!
+//! Each bit of the colors takes half of a SPI byte, so a frame takes 12 bytes per RGB LED. The
+//! prerendered driver encodes the whole frame before it's sent at once by the DMA of an async SPI
+//! bus, and its write completes once the transfer does.
+//!
+//! The streaming driver encodes the LEDs into small chunks while they're sent instead, from buffers
+//! whose size doesn't depend on the length of the strip. The bus sends a chunk while the next one
+//! is encoded, with two buffers taking turns. The data line only stays low between the end of a
+//! transfer and the start of the next, while the executor polls the LED task again. The LEDs read
+//! it as a longer low time of the last bit, as long as it's shorter than their reset time, which is
+//! only 50 µs for the older WS2812. No other task of the executor may run that long between two
+//! polls, otherwise the strip latches in the middle of the frame.
+
+#[cfg(feature = "streaming")]
+use core::fmt::Debug;
+use core::marker::PhantomData;
+#[cfg(feature = "streaming")]
+use embassy_futures::join::join;
+use embedded_hal_async::spi::SpiBus as SpiBusAsync;
+use smart_leds_trait::{RGB8, RGBW, SmartLedsWriteAsync};
+
+/// The SPI bytes encoding each byte of the colors, two bits per SPI byte.
+const BYTES_PER_CHANNEL: usize = 4;
+
+/// The high and low times of two bits, the high time first. At 3.8 MHz, the short high time lasts
+/// 263 ns and the long one 790 ns.
+const PATTERNS: [u8; 4] = [0b1000_1000, 0b1000_1110, 0b1110_1000, 0b1110_1110];
+
+/// The zeros latching the colors, which last more than 280 µs at 3.8 MHz.
+const RESET_SIZE: usize = 140;
+
+/// The size of the buffer holding the given number of LEDs, like the chunks of the streaming
+/// driver.
+#[must_use]
+pub const fn buffer_size(leds: usize, channels: usize) -> usize {
+    leds * channels * BYTES_PER_CHANNEL
+}
+
+/// The size of the buffer of the prerendered driver, holding the LEDs and the zeros latching their
+/// colors.
+#[must_use]
+pub const fn frame_size(leds: usize, channels: usize) -> usize {
+    buffer_size(leds, channels) + RESET_SIZE
+}
+
+#[cfg(feature = "streaming")]
+/// A bus sending the chunks from two buffers taking turns.
+pub trait ChunkBus {
+    type Error: Debug;
+
+    /// The buffer in which the first chunk is encoded.
+    fn buffer(&mut self) -> &mut [u8];
+
+    /// Sends the first bytes of the buffer while `encode` fills the other one, and returns the
+    /// length of the chunk it encoded, which is the next one to send. It completes once the chunk
+    /// is sent.
+    fn send(
+        &mut self, length: usize, encode: impl FnOnce(&mut [u8]) -> usize,
+    ) -> impl Future<Output = Result<usize, Self::Error>>;
+}
+
+#[cfg(feature = "streaming")]
+impl<B: ChunkBus> ChunkBus for &mut B {
+    type Error = B::Error;
+
+    fn buffer(&mut self) -> &mut [u8] {
+        B::buffer(self)
+    }
+
+    fn send(
+        &mut self, length: usize, encode: impl FnOnce(&mut [u8]) -> usize,
+    ) -> impl Future<Output = Result<usize, Self::Error>> {
+        B::send(self, length, encode)
+    }
+}
+
+#[cfg(feature = "streaming")]
+/// An async SPI bus sending the chunks from both halves of a buffer, one while the other is
+/// encoded.
+pub struct PingPongChunks<'a, SPI> {
+    spi: SPI,
+    buffers: [&'a mut [u8]; 2],
+    /// The index of the buffer holding the chunk to send.
+    current: usize,
+}
+
+#[cfg(feature = "streaming")]
+impl<'a, SPI: SpiBusAsync<u8>> PingPongChunks<'a, SPI> {
+    /// Each half of the buffer must hold at least one LED, see [`buffer_size`].
+    pub fn new(spi: SPI, buffer: &'a mut [u8]) -> Self {
+        let (first, second) = buffer.split_at_mut(buffer.len() / 2);
+        Self {
+            spi,
+            buffers: [first, second],
+            current: 0,
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<SPI: SpiBusAsync<u8>> ChunkBus for PingPongChunks<'_, SPI> {
+    type Error = SPI::Error;
+
+    fn buffer(&mut self) -> &mut [u8] {
+        self.buffers[self.current]
+    }
+
+    async fn send(
+        &mut self, length: usize, encode: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Result<usize, Self::Error> {
+        let [first, second] = &mut self.buffers;
+        let (sent, next) = if self.current == 0 {
+            (first, second)
+        } else {
+            (second, first)
+        };
+
+        // The transfer starts when the join polls it first, before the next chunk is encoded.
+        let spi = &mut self.spi;
+        let (result, next_length) = join(
+            async {
+                spi.write(&sent[..length]).await?;
+                spi.flush().await
+            },
+            async { encode(next) },
+        )
+        .await;
+        result?;
+
+        self.current = 1 - self.current;
+        Ok(next_length)
+    }
+}
+
+#[derive(Debug)]
+pub enum Error<E> {
+    /// The buffer is too small for the LEDs, see [`frame_size`] and [`buffer_size`].
+    OutOfBounds,
+
+    Bus(E),
+}
+
+/// A strip with colors of type `C`, whose frames are encoded in a buffer before they're sent at
+/// once.
+pub struct Ws2812<'a, SPI, C> {
+    spi: SPI,
+    buffer: &'a mut [u8],
+    color: PhantomData<C>,
+}
+
+impl<'a, SPI: SpiBusAsync<u8>, C> Ws2812<'a, SPI, C> {
+    /// The SPI bus runs between 2 and 3.8 MHz, or between 2.3 and 3.8 MHz for the SK6812. The
+    /// buffer must hold the LEDs, see [`frame_size`].
+    pub fn new(spi: SPI, buffer: &'a mut [u8]) -> Self {
+        Self {
+            spi,
+            buffer,
+            color: PhantomData,
+        }
+    }
+
+    /// Encodes the LEDs and the reset, then sends them in a single transfer.
+    async fn write_channels<const CHANNELS: usize>(
+        &mut self, leds: impl Iterator<Item = [u8; CHANNELS]>,
+    ) -> Result<(), Error<SPI::Error>> {
+        let led_size = CHANNELS * BYTES_PER_CHANNEL;
+        let mut length = 0;
+
+        let mut chunks = self.buffer.chunks_exact_mut(led_size);
+        for channels in leds {
+            let chunk = chunks.next().ok_or(Error::OutOfBounds)?;
+            for (bytes, channel) in chunk.chunks_exact_mut(BYTES_PER_CHANNEL).zip(channels) {
+                encode(channel, bytes);
+            }
+            length += led_size;
+        }
+
+        let reset = self
+            .buffer
+            .get_mut(length..length + RESET_SIZE)
+            .ok_or(Error::OutOfBounds)?;
+        reset.fill(0);
+        length += RESET_SIZE;
+
+        self.spi
+            .write(&self.buffer[..length])
+            .await
+            .map_err(Error::Bus)?;
+        self.spi.flush().await.map_err(Error::Bus)
+    }
+}
+
+impl<SPI: SpiBusAsync<u8>> SmartLedsWriteAsync for Ws2812<'_, SPI, RGB8> {
+    type Color = RGB8;
+    type Error = Error<SPI::Error>;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b]
+        }))
+        .await
+    }
+}
+
+impl<SPI: SpiBusAsync<u8>> SmartLedsWriteAsync for Ws2812<'_, SPI, RGBW<u8>> {
+    type Color = RGBW<u8>;
+    type Error = Error<SPI::Error>;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b, color.a.0]
+        }))
+        .await
+    }
+}
+
+#[cfg(feature = "streaming")]
+/// A strip with colors of type `C`, written over the chunks of the bus.
+pub struct StreamingWs2812<B, C> {
+    bus: B,
+    color: PhantomData<C>,
+}
+
+#[cfg(feature = "streaming")]
+impl<B: ChunkBus, C> StreamingWs2812<B, C> {
+    /// The SPI bus runs between 2 and 3.8 MHz, or between 2.3 and 3.8 MHz for the SK6812.
+    pub fn new(bus: B) -> Self {
+        Self {
+            bus,
+            color: PhantomData,
+        }
+    }
+
+    /// Encodes as many LEDs as each chunk holds, then latches the colors. Each chunk is encoded
+    /// while the previous one is sent.
+    async fn write_channels<const CHANNELS: usize>(
+        &mut self, leds: impl Iterator<Item = [u8; CHANNELS]>,
+    ) -> Result<(), Error<B::Error>> {
+        let led_size = CHANNELS * BYTES_PER_CHANNEL;
+        let mut leds = leds.peekable();
+        let mut reset = RESET_SIZE;
+
+        let mut encode_chunk = |buffer: &mut [u8]| {
+            if leds.peek().is_none() {
+                let length = reset.min(buffer.len());
+                buffer[..length].fill(0);
+                reset -= length;
+                return length;
+            }
+
+            let mut length = 0;
+            // The chunks of the buffer come first, so that no LED is taken once it's full.
+            for (chunk, channels) in buffer.chunks_exact_mut(led_size).zip(&mut leds) {
+                for (bytes, channel) in chunk.chunks_exact_mut(BYTES_PER_CHANNEL).zip(channels) {
+                    encode(channel, bytes);
+                }
+                length += led_size;
+            }
+            length
+        };
+
+        let mut length = encode_chunk(self.bus.buffer());
+        while length > 0 {
+            length = self
+                .bus
+                .send(length, &mut encode_chunk)
+                .await
+                .map_err(Error::Bus)?;
+        }
+
+        // A chunk too small for a LED stops the frame before its reset.
+        if reset > 0 {
+            return Err(Error::OutOfBounds);
+        }
+        Ok(())
+    }
+}
+
+/// Encodes a byte of the colors, most significant bits first.
+fn encode(channel: u8, bytes: &mut [u8]) {
+    for (index, byte) in bytes.iter_mut().enumerate() {
+        *byte = PATTERNS[usize::from((channel >> (6 - 2 * index)) & 0b11)];
+    }
+}
+
+// The write only completes once the last chunk is sent, so the next frame can't be computed in the
+// meantime.
+#[cfg(feature = "streaming")]
+impl<B: ChunkBus> SmartLedsWriteAsync for StreamingWs2812<B, RGB8> {
+    type Color = RGB8;
+    type Error = Error<B::Error>;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b]
+        }))
+        .await
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<B: ChunkBus> SmartLedsWriteAsync for StreamingWs2812<B, RGBW<u8>> {
+    type Color = RGBW<u8>;
+    type Error = Error<B::Error>;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b, color.a.0]
+        }))
+        .await
+    }
+}
@@ -0,0 +1,157 @@
+//! Tests of the Adalight parser, from the received bytes to the frame of the streaming animation.
+
+use cookie_monster_common::adalight::{FRAME_TIMEOUT, Parser};
+use cookie_monster_common::animations::{AnimationKind, LEDS_FIRST_SECTION};
+use cookie_monster_common::console::Console;
+use cookie_monster_common::stream::{STREAM_TIMEOUT, StreamSwitch};
+use embassy_time::{Duration, Instant};
+use smart_leds::{RGB8, RGB16};
+
+mod support;
+
+use support::stream::{LEDS_TOTAL, lock_stream, streamed};
+
+/// Encodes an Adalight frame of the colors.
+fn frame_of(colors: &[RGB8]) -> Vec<u8> {
+    let [high, low] = u16::try_from(colors.len() - 1).unwrap().to_be_bytes();
+    let mut frame = vec![b'A', b'd', b'a', high, low, high ^ low ^ 0x55];
+    frame.extend(colors.iter().flat_map(|color| [color.r, color.g, color.b]));
+    frame
+}
+
+/// Sends the bytes to the parser at the given time, and returns whether each belongs to a frame.
+fn parse(parser: &mut Parser, bytes: &[u8], now: Instant) -> Vec<bool> {
+    bytes
+        .iter()
+        .map(|&byte| parser.receive(byte, now))
+        .collect()
+}
+
+fn widen(color: RGB8) -> RGB16 {
+    RGB16::new(
+        u16::from(color.r) * 257,
+        u16::from(color.g) * 257,
+        u16::from(color.b) * 257,
+    )
+}
+
+/// A color unique to each LED.
+fn color_of(led: usize) -> RGB8 {
+    let [_, r, g, b] = u32::try_from(led)
+        .unwrap()
+        .wrapping_mul(2_654_435_761)
+        .to_be_bytes();
+    RGB8::new(r, g, b)
+}
+
+#[test]
+fn frame_is_shown_across_the_two_sections() {
+    let _stream = lock_stream();
+    let mut parser = Parser::new();
+    let colors: Vec<_> = (0..LEDS_TOTAL).map(color_of).collect();
+
+    let consumed = parse(&mut parser, &frame_of(&colors), Instant::now());
+    let data = streamed();
+
+    // The magic word could still be a line of text until it's complete.
+    assert_eq!(consumed[..3], [false, false, true]);
+    assert!(consumed[3..].iter().all(|&consumed| consumed));
+    assert_eq!(data[0], widen(colors[0]));
+    assert_eq!(
+        data[LEDS_FIRST_SECTION - 1],
+        widen(colors[LEDS_FIRST_SECTION - 1])
+    );
+    assert_eq!(data[LEDS_FIRST_SECTION], widen(colors[LEDS_FIRST_SECTION]));
+    assert_eq!(data[LEDS_TOTAL - 1], widen(colors[LEDS_TOTAL - 1]));
+}
+
+#[test]
+fn header_with_invalid_checksum_is_ignored() {
+    let _stream = lock_stream();
+    let mut parser = Parser::new();
+    let now = Instant::now();
+    parse(&mut parser, &frame_of(&[RGB8::default(); 4]), now);
+
+    let mut frame = frame_of(&[RGB8::new(255, 0, 0); 4]);
+    frame[5] ^= 1;
+    let consumed = parse(&mut parser, &frame, now);
+
+    assert!(consumed[6..].iter().all(|&consumed| !consumed));
+    assert_eq!(streamed()[..4], [RGB16::default(); 4]);
+}
+
+#[test]
+fn leds_past_the_last_one_are_ignored() {
+    let _stream = lock_stream();
+    let mut parser = Parser::new();
+    let colors = vec![RGB8::new(1, 2, 3); LEDS_TOTAL + 10];
+
+    let consumed = parse(&mut parser, &frame_of(&colors), Instant::now());
+    let after = parse(&mut parser, b"next", Instant::now());
+
+    assert!(consumed[3..].iter().all(|&consumed| consumed));
+    assert_eq!(after, [false; 4]);
+    assert_eq!(streamed()[LEDS_TOTAL - 1], widen(RGB8::new(1, 2, 3)));
+}
+
+#[test]
+fn interrupted_frame_is_discarded_after_the_timeout() {
+    let _stream = lock_stream();
+    let mut parser = Parser::new();
+    let start = Instant::now();
+    parse(&mut parser, &frame_of(&[RGB8::default(); 4]), start);
+
+    let interrupted = frame_of(&[RGB8::new(0, 0, 255); 4]);
+    parse(&mut parser, &interrupted[..interrupted.len() - 4], start);
+    let later = start + FRAME_TIMEOUT + Duration::from_millis(1);
+    let consumed = parse(&mut parser, &frame_of(&[RGB8::new(0, 255, 0); 4]), later);
+
+    assert_eq!(consumed[..3], [false, false, true]);
+    assert_eq!(streamed()[..4], [widen(RGB8::new(0, 255, 0)); 4]);
+}
+
+#[test]
+fn rotation_resumes_once_the_stream_stops() {
+    let _stream = lock_stream();
+    let mut parser = Parser::new();
+    let mut switch = StreamSwitch::new();
+    // Later than the frames of the other tests, whose stream has stopped.
+    let start = Instant::now() + STREAM_TIMEOUT * 2;
+    let stopped = start + STREAM_TIMEOUT;
+
+    let before = switch.update(AnimationKind::Shimmer, start);
+    parse(&mut parser, &frame_of(&[RGB8::default(); 4]), start);
+    let started = switch.update(AnimationKind::Shimmer, start);
+    let streaming = switch.update(AnimationKind::Streaming, start);
+    // Like the button pressed while streaming, which is resumed once the stream stops.
+    let pressed = switch.update(AnimationKind::Carrousel, start);
+    let resumed = switch.update(AnimationKind::Streaming, stopped);
+    let after = switch.update(AnimationKind::Carrousel, stopped);
+
+    assert_eq!(before, None);
+    assert_eq!(started, Some(AnimationKind::Streaming));
+    assert_eq!(streaming, None);
+    assert_eq!(pressed, Some(AnimationKind::Streaming));
+    assert_eq!(resumed, Some(AnimationKind::Carrousel));
+    assert_eq!(after, None);
+}
+
+#[test]
+fn console_streams_frames_between_lines() {
+    let _stream = lock_stream();
+    let mut console = Console::new();
+    // The number of LEDs and the colors contain the end of a line and the start of a binary frame.
+    let colors = vec![RGB8::new(b'\n', 0, b'\r'); 11];
+    let mut bytes = b"help\n".to_vec();
+    bytes.extend(frame_of(&colors));
+    bytes.extend(b"help\n");
+
+    let replies: Vec<_> = bytes
+        .iter()
+        .filter_map(|&byte| console.receive(byte).map(<[u8]>::to_vec))
+        .collect();
+
+    assert_eq!(replies.len(), 2);
+    assert!(replies.iter().all(|reply| reply.starts_with(b"commands: ")));
+    assert_eq!(streamed()[..11], [widen(RGB8::new(b'\n', 0, b'\r')); 11]);
+}
@@ -0,0 +1,245 @@
+//! Robustness tests that run every animation under random seeds and settings for thousands of
+//! frames.
+//!
+//! Any panic, including an out of range index or an arithmetic overflow, fails the test. The colors
+//! written by the animations that use the palette are also checked against it, and the current they
+//! draw against the power budget.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS, COLORS_TOTAL, LEDS_FIRST_SECTION, LEDS_SECOND_SECTION,
+    LedData, Section, Settings, calculate_index, create_data,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::power::{
+    BLUE_MILLIAMPS, GREEN_MILLIAMPS, IDLE_MILLIAMPS, PowerBudget, RED_MILLIAMPS, estimate_milliamps,
+};
+use core::convert::Infallible;
+use embassy_futures::block_on;
+use proptest::prelude::*;
+use rand::rngs::SmallRng;
+use rand::{RngExt, SeedableRng};
+use smart_leds::{RGB8, RGB16};
+use smart_leds_trait::SmartLedsWrite;
+
+mod support;
+
+use support::render::NoDelay;
+
+/// The number of frames each animation is run for.
+const FRAMES: usize = 3_000;
+
+/// The odds, per frame, that one of the settings is changed, like a user turning a knob.
+const SETTINGS_CHANGE_ODDS: u32 = 50;
+
+/// The colors an animation is expected to write, other than black.
+enum Palette {
+    /// Any color, the animation doesn't use the palette.
+    Any,
+    /// Only the color selected in the settings.
+    Active,
+    /// Any of the colors of the palette.
+    All,
+}
+
+impl Palette {
+    fn of(kind: AnimationKind) -> Self {
+        match kind {
+            AnimationKind::UniColorFadeIn
+            | AnimationKind::UniColorFrontToBackWave
+            | AnimationKind::UniColorHeartbeat
+            | AnimationKind::UniColorSolid => Palette::Active,
+            AnimationKind::Carrousel
+            | AnimationKind::DoubleCarrousel
+            | AnimationKind::MultiColorFadeIn
+            | AnimationKind::MultiColorFrontToBackWave
+            | AnimationKind::MultiColorHeartbeat
+            | AnimationKind::MultiColorSolid => Palette::All,
+            // The strands have their own palette, the stream has the host's, and the others
+            // compute their colors.
+            AnimationKind::ForwardWave
+            | AnimationKind::MultiColorSolidRandom
+            | AnimationKind::MultiColorSparkle
+            | AnimationKind::MultiColorStrand
+            | AnimationKind::Shimmer
+            | AnimationKind::Streaming
+            | AnimationKind::UniColorSparkle => Palette::Any,
+        }
+    }
+
+    fn contains(&self, color: RGB16, settings: &Settings) -> bool {
+        color == RGB16::default()
+            || match self {
+                Palette::Any => true,
+                Palette::Active => color == COLORS[settings.color_index()],
+                Palette::All => COLORS.contains(&color),
+            }
+    }
+}
+
+/// A LED section that keeps the colors written to it, and counts them and the current they draw.
+#[derive(Default)]
+struct CountingSection {
+    colors: Vec<RGB8>,
+    milliamps: u32,
+    written: usize,
+}
+
+impl SmartLedsWrite for CountingSection {
+    type Color = RGB8;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let colors: Vec<RGB8> = iterator.into_iter().map(Into::into).collect();
+        self.milliamps = estimate_milliamps(colors.iter().copied());
+        self.written = colors.len();
+        self.colors = colors;
+        Ok(())
+    }
+}
+
+/// Randomly changes one of the settings, within the range of values the inputs can produce.
+fn change_settings(settings: &mut Settings, prng: &mut SmallRng, analog_max_value: u16) {
+    if prng.random_range(0..SETTINGS_CHANGE_ODDS) != 0 {
+        return;
+    }
+
+    match prng.random_range(0..4) {
+        0 => settings.set_brightness(prng.random_range(0..=analog_max_value)),
+        1 => settings.set_delay(prng.random_range(0..=analog_max_value)),
+        2 => settings.increment_color_index(),
+        _ => settings.set_color_index(prng.random_range(0..COLORS_TOTAL)),
+    }
+}
+
+/// A power budget that can always be met, at least by turning all the LEDs off.
+fn power_budget() -> impl Strategy<Value = PowerBudget> {
+    let idle = |leds: usize| leds as u32 * IDLE_MILLIAMPS;
+    (
+        idle(LEDS_FIRST_SECTION + LEDS_SECOND_SECTION)..=50_000,
+        idle(LEDS_FIRST_SECTION)..=30_000,
+        idle(LEDS_SECOND_SECTION)..=30_000,
+    )
+        .prop_map(|(milliamps, section_1, section_2)| {
+            PowerBudget::new(milliamps, section_1, section_2)
+        })
+}
+
+fn assert_palette(kind: AnimationKind, data: &LedData, settings: &Settings, frame: usize) {
+    let palette = Palette::of(kind);
+    for (index, color) in data.iter().enumerate() {
+        assert!(
+            palette.contains(*color, settings),
+            "{kind:?} wrote {color:?} at LED {index} on frame {frame} with {settings:?}"
+        );
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(32))]
+
+    #[test]
+    fn animations_run_for_thousands_of_frames(
+        kind in prop::sample::select(AnimationKind::ALL.to_vec()), seed: u64,
+        analog_max_value in 1..=u16::MAX, brightness: u16, delay: u16,
+        color_index in 0..COLORS_TOTAL, power_budget in power_budget(), dithering_enabled: bool,
+    ) {
+        let mut prng = SmallRng::seed_from_u64(seed);
+        let mut settings = Settings::new(
+            color_index, brightness, delay, analog_max_value, COLORS_TOTAL,
+        );
+        settings.set_power_budget(power_budget);
+        settings.set_dithering(dithering_enabled);
+        let mut animation = Animation::new(kind, &mut prng);
+        let mut data = create_data();
+        let mut dithering = Dithering::new();
+        let mut section_1 = CountingSection::default();
+        let mut section_2 = CountingSection::default();
+
+        for frame in 0..FRAMES {
+            change_settings(&mut settings, &mut prng, analog_max_value);
+
+            animation.update(&mut data, &settings);
+            assert_palette(kind, &data, &settings, frame);
+
+            let result = block_on(animation.render(
+                &data, &mut section_1, &mut section_2, &mut dithering, &mut NoDelay, &settings,
+            ));
+            prop_assert_eq!(result, Ok(()));
+            prop_assert_eq!(section_1.written, LEDS_FIRST_SECTION);
+            prop_assert_eq!(section_2.written, LEDS_SECOND_SECTION);
+            prop_assert!(section_1.milliamps <= power_budget.section_milliamps(Section::First));
+            prop_assert!(section_2.milliamps <= power_budget.section_milliamps(Section::Second));
+            // Estimated from the colors of both sections, since each estimate is rounded up.
+            let milliamps =
+                estimate_milliamps(section_1.colors.iter().chain(&section_2.colors).copied());
+            prop_assert!(milliamps <= power_budget.milliamps());
+        }
+    }
+
+    #[test]
+    fn frames_within_the_budget_are_unchanged(
+        kind in prop::sample::select(AnimationKind::ALL.to_vec()), seed: u64, brightness: u16,
+    ) {
+        let mut prng = SmallRng::seed_from_u64(seed);
+        let mut settings = Settings::new(0, brightness, 0, u16::MAX, COLORS_TOTAL);
+        settings.set_dithering(false);
+        let mut animation = Animation::new(kind, &mut prng);
+        let mut data = create_data();
+        animation.update(&mut data, &settings);
+
+        let mut unlimited = (CountingSection::default(), CountingSection::default());
+        settings.set_power_budget(PowerBudget::new(u32::MAX / 255, u32::MAX / 255, u32::MAX / 255));
+        block_on(animation.render(
+            &data, &mut unlimited.0, &mut unlimited.1, &mut Dithering::new(), &mut NoDelay,
+            &settings,
+        )).unwrap();
+
+        // The budget is checked before the colors are quantized, when each channel can be up to a
+        // step above its written value.
+        let quantization = ((LEDS_FIRST_SECTION + LEDS_SECOND_SECTION) as u32
+            * (RED_MILLIAMPS + GREEN_MILLIAMPS + BLUE_MILLIAMPS))
+            .div_ceil(255);
+        let milliamps = unlimited.0.milliamps + unlimited.1.milliamps + quantization;
+        let mut limited = (CountingSection::default(), CountingSection::default());
+        settings.set_power_budget(PowerBudget::new(milliamps, milliamps, milliamps));
+        block_on(animation.render(
+            &data, &mut limited.0, &mut limited.1, &mut Dithering::new(), &mut NoDelay,
+            &settings,
+        )).unwrap();
+
+        prop_assert_eq!(limited.0.milliamps, unlimited.0.milliamps);
+        prop_assert_eq!(limited.1.milliamps, unlimited.1.milliamps);
+    }
+
+    #[test]
+    fn calculated_index_is_in_range(
+        value: u16, max_value in 1..=u16::MAX, num_values in 1..=COLORS_TOTAL,
+    ) {
+        prop_assert!(calculate_index(value, max_value, num_values) < num_values);
+    }
+}
+
+#[test]
+fn sequence_goes_through_every_animation() {
+    for (index, kind) in AnimationKind::ALL.iter().enumerate() {
+        let expected = AnimationKind::ALL[(index + 1) % AnimationKind::ALL.len()];
+        assert_eq!(kind.next(), expected);
+        assert_eq!(expected.previous(), *kind);
+    }
+}
+
+#[test]
+fn streaming_leaves_to_the_ends_of_the_sequence() {
+    assert_eq!(
+        AnimationKind::Streaming.next(),
+        AnimationKind::MultiColorStrand
+    );
+    assert_eq!(
+        AnimationKind::Streaming.previous(),
+        AnimationKind::UniColorSolid
+    );
+}
@@ -0,0 +1,117 @@
+//! Tests of the driver of the APA102 and SK9822 strips.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS, COLORS_TOTAL, LEDS_FIRST_SECTION, Settings, create_data,
+    widen,
+};
+use cookie_monster_common::apa102::{Apa102, Error, buffer_size, encode};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::power::PowerBudget;
+use embassy_futures::block_on;
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::RGB16;
+use smart_leds::colors::WHITE;
+use smart_leds_trait::SmartLedsWrite;
+
+mod support;
+
+use support::render::{DiscardingSection, NoDelay};
+use support::spi::RecordingSpi;
+
+/// The 16-bit value shown by a channel of an encoded LED.
+fn shown(brightness: u8, channel: u8) -> f64 {
+    f64::from(channel) * f64::from(brightness & 0x1F) / 31.0 * 257.0
+}
+
+#[test]
+fn frames_start_and_end_with_zeros() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0xAA; buffer_size(2)];
+    let mut apa102 = Apa102::new(&mut spi, &mut buffer);
+
+    apa102
+        .write([RGB16::new(u16::MAX, 0, 0), RGB16::new(0, 0, u16::MAX)])
+        .unwrap();
+
+    assert_eq!(
+        spi.bytes,
+        [0, 0, 0, 0, 0xFF, 0, 0, 255, 0xFF, 255, 0, 0, 0, 0, 0, 0, 0]
+    );
+}
+
+#[test]
+fn too_many_leds_for_the_buffer_are_an_error() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; buffer_size(1)];
+    let mut apa102 = Apa102::new(&mut spi, &mut buffer);
+
+    let result = apa102.write([RGB16::default(); 2]);
+
+    assert!(matches!(result, Err(Error::OutOfBounds)));
+    assert!(spi.bytes.is_empty());
+}
+
+#[test]
+fn dark_colors_keep_their_resolution() {
+    // Quantized to 8 bits, the red would be 3 out of 255, and the green 1.
+    let [brightness, blue, green, red] = encode(RGB16::new(1_000, 500, 0));
+
+    assert_eq!(brightness, 0xE0 | 1);
+    assert_eq!([red, green, blue], [120, 60, 0]);
+}
+
+#[test]
+fn rendered_frames_are_written_to_the_strip() {
+    let mut settings = Settings::new(
+        COLORS
+            .iter()
+            .position(|color| *color == widen(WHITE))
+            .unwrap(),
+        1,
+        0,
+        1,
+        COLORS_TOTAL,
+    );
+    settings.set_power_budget(PowerBudget::new(100_000, 100_000, 100_000));
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    let mut data = create_data();
+    animation.update(&mut data, &settings);
+
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; buffer_size(LEDS_FIRST_SECTION)];
+    block_on(animation.render(
+        &data,
+        &mut Apa102::new(&mut spi, &mut buffer),
+        &mut DiscardingSection,
+        &mut Dithering::new(),
+        &mut NoDelay,
+        &settings,
+    ))
+    .unwrap();
+
+    assert_eq!(spi.bytes.len(), buffer_size(LEDS_FIRST_SECTION));
+    assert!(
+        spi.bytes[4..4 + 4 * LEDS_FIRST_SECTION]
+            .chunks_exact(4)
+            .all(|led| led == [0xFF, 255, 255, 255])
+    );
+}
+
+proptest! {
+    #[test]
+    fn encoded_colors_are_within_a_step(r: u16, g: u16, b: u16) {
+        let [brightness, blue, green, red] = encode(RGB16::new(r, g, b));
+
+        prop_assert_eq!(brightness & 0xE0, 0xE0);
+        for (channel, value) in [(red, r), (green, g), (blue, b)] {
+            let shown = shown(brightness, channel);
+            prop_assert!(shown <= f64::from(value) + 0.5);
+            prop_assert!(shown > f64::from(value) - 257.0);
+        }
+    }
+}
@@ -0,0 +1,158 @@
+//! Tests of the Art-Net node, talking to a controller on a local UDP socket.
+
+use cookie_monster_common::artnet::{
+    Mapping, Node, POLL_REPLY_SIZE, Packet, PacketError, parse, port_address,
+};
+use embassy_time::Instant;
+use smart_leds::RGB16;
+
+mod support;
+
+use support::loopback::Loopback;
+use support::stream::{LEDS_TOTAL, gray, lock_stream, streamed};
+
+const MAC: [u8; 6] = [0x24, 0x0A, 0xC4, 0x12, 0x34, 0x56];
+const ADDRESS: [u8; 4] = [192, 168, 1, 50];
+
+/// An ArtDMX packet, as the consoles send it.
+fn dmx(port_address: u16, channels: &[u8]) -> Vec<u8> {
+    let [subnet_and_universe, net] = port_address.to_le_bytes();
+    let mut packet = b"Art-Net\0".to_vec();
+    packet.extend([0x00, 0x50, 0x00, 14, 0x01, 0x00, subnet_and_universe, net]);
+    packet.extend(u16::try_from(channels.len()).unwrap().to_be_bytes());
+    packet.extend(channels);
+    packet
+}
+
+/// An ArtPoll packet, as the consoles send it.
+fn poll() -> Vec<u8> {
+    let mut packet = b"Art-Net\0".to_vec();
+    packet.extend([0x00, 0x20, 0x00, 14, 0x00, 0x00]);
+    packet
+}
+
+/// A controller and a node of Art-Net on the loopback interface.
+struct Network {
+    loopback: Loopback,
+    node: Node,
+}
+
+impl Network {
+    fn new(mapping: Mapping) -> Self {
+        Self {
+            loopback: Loopback::new(),
+            node: Node::new(mapping, MAC),
+        }
+    }
+
+    /// Sends the packet from the controller, and returns the replies of the node to it.
+    fn send(&mut self, packet: &[u8]) -> Result<Vec<Vec<u8>>, PacketError> {
+        let received = self.loopback.send(packet);
+        let mut replies = Vec::new();
+        if self.node.receive(&received, Instant::now())? == Packet::Poll {
+            for reply in self.node.poll_replies(ADDRESS) {
+                replies.push(self.loopback.answer(&reply));
+            }
+        }
+        Ok(replies)
+    }
+}
+
+#[test]
+fn port_addresses_are_mapped_across_subnets() {
+    let _stream = lock_stream();
+    let first = port_address(1, 0, 14);
+    let mut network = Network::new(Mapping::new(first, 1));
+
+    let ignored = network.send(&dmx(first - 1, &[255; 512]));
+    for (level, port_address) in (10..).step_by(10).zip(first..first + 6) {
+        network.send(&dmx(port_address, &[level; 512])).unwrap();
+    }
+    let data = streamed();
+
+    assert_eq!(ignored, Ok(Vec::new()));
+    assert_eq!(network.node.mapping().port_addresses(), 0x010E..=0x0113);
+    assert_eq!(data[0], gray(10));
+    assert_eq!(data[170], gray(20));
+    assert_eq!(data[340], gray(30), "the first universe of the next subnet");
+    assert_eq!(data[LEDS_TOTAL - 1], gray(60));
+}
+
+#[test]
+fn poll_is_answered_with_the_port_addresses() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(port_address(1, 0, 14), 1));
+
+    let replies = network.send(&poll()).unwrap();
+
+    assert_eq!(replies.len(), 2, "the ports of a reply share their subnet");
+    for (index, reply) in replies.iter().enumerate() {
+        assert_eq!(reply.len(), POLL_REPLY_SIZE);
+        assert_eq!(reply[..10], *b"Art-Net\0\x00\x21");
+        assert_eq!(reply[10..14], ADDRESS);
+        assert_eq!(reply[14..16], [0x36, 0x19]);
+        assert_eq!(reply[26..40], *b"Cookie Monster");
+        assert_eq!(reply[201..207], MAC);
+        assert_eq!(usize::from(reply[211]), index + 1);
+    }
+    // The net and the subnet, the number of ports, their types and their universes.
+    assert_eq!(replies[0][18..20], [1, 0]);
+    assert_eq!(replies[0][172..174], [0, 2]);
+    assert_eq!(replies[0][174..178], [0x80, 0x80, 0, 0]);
+    assert_eq!(replies[0][190..194], [14, 15, 0, 0]);
+    assert_eq!(replies[1][18..20], [1, 1]);
+    assert_eq!(replies[1][172..174], [0, 4]);
+    assert_eq!(replies[1][190..194], [0, 1, 2, 3]);
+}
+
+#[test]
+fn channels_past_the_start_channel_are_written() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(0, 7));
+    let mut channels = vec![0; 512];
+    channels[6..9].copy_from_slice(&[1, 2, 3]);
+
+    network.send(&dmx(0, &channels)).unwrap();
+    // The universe repeated shows the frame.
+    network.send(&dmx(0, &channels)).unwrap();
+
+    assert_eq!(streamed()[0], RGB16::new(257, 2 * 257, 3 * 257));
+}
+
+#[test]
+fn dmx_packet_is_parsed() {
+    let packet = dmx(port_address(2, 3, 4), &[1, 2, 3, 4]);
+
+    let Ok(Packet::Dmx(dmx)) = parse(&packet) else {
+        panic!("not an ArtDMX packet");
+    };
+
+    assert_eq!(dmx.port_address, 0x0234);
+    assert_eq!(dmx.sequence, 1);
+    assert_eq!(dmx.channels, [1, 2, 3, 4]);
+}
+
+#[test]
+fn invalid_packets_are_rejected() {
+    let valid = dmx(0, &[1, 2, 3, 4]);
+    let with = |index: usize, byte: u8| {
+        let mut packet = valid.clone();
+        packet[index] = byte;
+        packet
+    };
+
+    let packets = [
+        (valid[..11].to_vec(), PacketError::TooShort),
+        (valid[..21].to_vec(), PacketError::TooShort),
+        (with(0, b'a'), PacketError::InvalidId),
+        // An ArtSync packet.
+        (with(9, 0x52), PacketError::UnsupportedOpCode),
+        (with(11, 13), PacketError::UnsupportedVersion),
+        (with(17, 3), PacketError::InvalidLength),
+        (with(17, 0), PacketError::InvalidLength),
+    ];
+
+    for (packet, error) in packets {
+        assert_eq!(parse(&packet), Err(error), "{packet:02x?}");
+    }
+}
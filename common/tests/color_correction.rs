@@ -0,0 +1,77 @@
+//! Tests of the color correction of each section.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS, COLORS_TOTAL, Section, Settings, create_data, widen,
+};
+use cookie_monster_common::color_correction::{ColorCorrection, correction, temperature};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::power::PowerBudget;
+use embassy_futures::block_on;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::RGB8;
+use smart_leds::colors::WHITE;
+
+mod support;
+
+use support::render::{FirstLedSection, NoDelay};
+
+/// Renders white at full brightness, and returns the color of the first LED of each section.
+fn render_white(settings: &mut Settings) -> (RGB8, RGB8) {
+    settings.set_color_index(
+        COLORS
+            .iter()
+            .position(|color| *color == widen(WHITE))
+            .unwrap(),
+    );
+    settings.set_dithering(false);
+    settings.set_power_budget(PowerBudget::new(100_000, 100_000, 100_000));
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    let mut data = create_data();
+    animation.update(&mut data, settings);
+
+    let mut section_1 = FirstLedSection::default();
+    let mut section_2 = FirstLedSection::default();
+    block_on(animation.render(
+        &data,
+        &mut section_1,
+        &mut section_2,
+        &mut Dithering::new(),
+        &mut NoDelay,
+        settings,
+    ))
+    .unwrap();
+
+    (section_1.color, section_2.color)
+}
+
+#[test]
+fn each_section_has_its_own_correction() {
+    let mut settings = Settings::new(0, 1, 0, 1, COLORS_TOTAL);
+    settings.set_color_correction(
+        Section::Second,
+        ColorCorrection::new(correction::TYPICAL_LED_STRIP, temperature::UNCORRECTED),
+    );
+
+    let (section_1, section_2) = render_white(&mut settings);
+
+    assert_eq!(section_1, WHITE);
+    assert_eq!(section_2, correction::TYPICAL_LED_STRIP);
+}
+
+#[test]
+fn correction_and_temperature_are_combined() {
+    let mut settings = Settings::new(0, 1, 0, 1, COLORS_TOTAL);
+    settings.set_color_correction(
+        Section::First,
+        ColorCorrection::new(RGB8::new(255, 128, 255), temperature::CANDLE),
+    );
+
+    let (section_1, _) = render_white(&mut settings);
+
+    // 147 * 128 / 255 and 41 * 255 / 255, rounded down.
+    assert_eq!(section_1, RGB8::new(255, 73, 41));
+}
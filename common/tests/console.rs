@@ -0,0 +1,150 @@
+//! Tests of the text console, from the bytes received to the commands queued and the replies.
+
+use cookie_monster_common::animations::{AnimationKind, COLORS_TOTAL, Section, Settings};
+use cookie_monster_common::color_correction::ColorCorrection;
+use cookie_monster_common::command;
+use cookie_monster_common::command::{Color, Command};
+use cookie_monster_common::console::{Console, LINE_SIZE, Line, ParseError, parse};
+use cookie_monster_common::signal::COMMAND_CHANNEL;
+use smart_leds::RGB8;
+
+mod support;
+
+/// Sends the bytes to the console, and returns the replies.
+fn type_bytes(console: &mut Console, bytes: &[u8]) -> Vec<String> {
+    let mut replies = Vec::new();
+    for &byte in bytes {
+        if let Some(reply) = console.receive(byte) {
+            replies.push(String::from_utf8(reply.to_vec()).unwrap());
+        }
+    }
+    replies
+}
+
+#[test]
+fn commands_are_parsed_with_their_arguments() {
+    let lines = [
+        ("anim shimmer", Command::Animation(AnimationKind::Shimmer)),
+        (
+            "ANIM Uni_Color_Solid",
+            Command::Animation(AnimationKind::UniColorSolid),
+        ),
+        ("next", Command::NextAnimation),
+        ("  prev  ", Command::PreviousAnimation),
+        ("color 4", Command::Color(Color::Index(4))),
+        (
+            "color #FF8000",
+            Command::Color(Color::Rgb(RGB8::new(255, 128, 0))),
+        ),
+        ("bright 0", Command::Brightness(0)),
+        ("bright 255", Command::Brightness(255)),
+        ("speed 1000", Command::Speed(1000)),
+        ("status", Command::Status),
+        ("stats", Command::Stats),
+        ("preset save", Command::SavePreset),
+        ("preset LOAD", Command::LoadPreset),
+        (
+            "correct 2 #ffb0f0 #ff9329",
+            Command::ColorCorrection(
+                Some(Section::Second),
+                ColorCorrection::new(RGB8::new(255, 176, 240), RGB8::new(255, 147, 41)),
+            ),
+        ),
+        (
+            "CORRECT All #ffffff #ffffff",
+            Command::ColorCorrection(None, ColorCorrection::UNCORRECTED),
+        ),
+    ];
+
+    for (line, command) in lines {
+        assert_eq!(parse(line), Ok(Line::Command(command)), "{line}");
+    }
+    assert_eq!(parse("help"), Ok(Line::Help));
+}
+
+#[test]
+fn invalid_lines_are_rejected() {
+    let lines = [
+        ("", ParseError::UnknownCommand),
+        ("dance", ParseError::UnknownCommand),
+        ("anim", ParseError::MissingArgument),
+        ("anim moonwalk", ParseError::InvalidArgument),
+        ("next 2", ParseError::UnexpectedArgument),
+        ("color #12345", ParseError::InvalidArgument),
+        ("color #12345g", ParseError::InvalidArgument),
+        ("color red", ParseError::InvalidArgument),
+        ("bright 256", ParseError::InvalidArgument),
+        ("speed 0", ParseError::InvalidArgument),
+        ("speed 1001", ParseError::InvalidArgument),
+        ("preset delete", ParseError::InvalidArgument),
+        ("preset save now", ParseError::UnexpectedArgument),
+        ("correct 1 #ffffff", ParseError::MissingArgument),
+        ("correct 3 #ffffff #ffffff", ParseError::InvalidArgument),
+        ("correct all #ffffff 255", ParseError::InvalidArgument),
+        (
+            "correct all #ffffff #ffffff #ffffff",
+            ParseError::UnexpectedArgument,
+        ),
+    ];
+
+    for (line, error) in lines {
+        assert_eq!(parse(line), Err(error), "{line}");
+    }
+}
+
+#[test]
+fn every_animation_is_parsed_from_its_name() {
+    for kind in AnimationKind::ALL {
+        assert_eq!(AnimationKind::from_name(kind.name()), Some(kind));
+        assert_eq!(kind.next().previous(), kind);
+    }
+}
+
+#[test]
+fn lines_are_answered_once_complete() {
+    let mut console = Console::new();
+
+    let replies = type_bytes(&mut console, b"next\r\n\r\nbright 12\nprev\rhelp\n");
+    let commands: Vec<_> = std::iter::from_fn(|| COMMAND_CHANNEL.try_receive().ok()).collect();
+
+    assert_eq!(replies.len(), 4);
+    assert!(replies[..3].iter().all(|reply| reply == "ok\r\n"));
+    assert!(replies[3].starts_with("commands: "));
+    assert_eq!(
+        commands,
+        [
+            Command::NextAnimation,
+            Command::Brightness(12),
+            Command::PreviousAnimation
+        ]
+    );
+}
+
+#[test]
+fn too_long_line_is_discarded() {
+    let mut console = Console::new();
+    let mut bytes = vec![b'x'; LINE_SIZE + 1];
+    bytes.extend(b"\nstatus\n");
+
+    let replies = type_bytes(&mut console, &bytes);
+
+    assert_eq!(replies[0], "error: line too long\r\n");
+    assert_eq!(replies.len(), 2);
+}
+
+#[test]
+fn queries_are_answered_from_the_published_status() {
+    let mut settings = Settings::new(3, 0, 100, 1_000, COLORS_TOTAL);
+    settings.set_brightness_level(42);
+    settings.set_custom_color(RGB8::new(1, 2, 255));
+    command::publish_status(AnimationKind::ForwardWave, &settings);
+    let mut console = Console::new();
+
+    let replies = type_bytes(&mut console, b"status\nstats\n");
+
+    assert_eq!(
+        replies[0],
+        "animation forward_wave, color #0102ff, brightness 42, delay 100 ms, dithering on\r\n"
+    );
+    assert!(replies[1].contains(" frames in ") && replies[1].ends_with("\r\n"));
+}
@@ -0,0 +1,146 @@
+//! Tests of the DDP receiver, fed by a sender on a local UDP socket.
+
+use cookie_monster_common::ddp::{DataType, PacketError, parse, receive};
+use embassy_time::Instant;
+use smart_leds::RGB16;
+
+mod support;
+
+use support::loopback::Loopback;
+use support::stream::{LEDS_TOTAL, lock_stream, streamed};
+
+/// The data of the packets sent by xLights, 480 RGB LEDs.
+const DATA_PER_PACKET: usize = 1440;
+
+const PUSH: u8 = 0x41;
+const NO_PUSH: u8 = 0x40;
+const RGB: u8 = 0x0B;
+const RGBW: u8 = 0x1B;
+
+/// A packet of data for the LEDs, as xLights and WLED send it.
+fn packet(flags: u8, data_type: u8, offset: u32, data: &[u8]) -> Vec<u8> {
+    let mut packet = vec![flags, 0x01, data_type, 0x01];
+    packet.extend(offset.to_be_bytes());
+    packet.extend(u16::try_from(data.len()).unwrap().to_be_bytes());
+    packet.extend(data);
+    packet
+}
+
+/// A sender of DDP packets on the loopback interface, and the socket receiving them.
+struct Network {
+    loopback: Loopback,
+}
+
+impl Network {
+    fn new() -> Self {
+        Self {
+            loopback: Loopback::new(),
+        }
+    }
+
+    /// Sends the packet, and returns how it was received.
+    fn send(&self, packet: &[u8]) -> Result<(), PacketError> {
+        receive(&self.loopback.send(packet), Instant::now())
+    }
+}
+
+/// Shows a black frame, so that the test starts from a known frame.
+fn show_black(network: &Network) {
+    network
+        .send(&packet(PUSH, RGB, 0, &[0; LEDS_TOTAL * 3]))
+        .unwrap();
+}
+
+#[test]
+fn frame_is_shown_when_pushed() {
+    let _stream = lock_stream();
+    let network = Network::new();
+    show_black(&network);
+    let channels: Vec<u8> = (0..LEDS_TOTAL * 3).map(|i| (i % 251) as u8).collect();
+    let (first, second) = channels.split_at(DATA_PER_PACKET);
+
+    network.send(&packet(NO_PUSH, RGB, 0, first)).unwrap();
+    let before = streamed();
+    network
+        .send(&packet(PUSH, RGB, DATA_PER_PACKET as u32, second))
+        .unwrap();
+    let data = streamed();
+
+    assert_eq!(before[0], RGB16::default(), "shown before its push");
+    assert_eq!(data[0], RGB16::new(0, 257, 2 * 257));
+    // The first LED of the second packet.
+    let second = DATA_PER_PACKET / 3;
+    assert_eq!(data[second], RGB16::new(185 * 257, 186 * 257, 187 * 257));
+    assert_eq!(
+        data[LEDS_TOTAL - 1],
+        RGB16::new(116 * 257, 117 * 257, 118 * 257)
+    );
+}
+
+#[test]
+fn offset_in_the_middle_of_an_led_writes_its_channels() {
+    let _stream = lock_stream();
+    let network = Network::new();
+    show_black(&network);
+
+    network.send(&packet(PUSH, RGB, 31, &[1, 2, 3])).unwrap();
+    let data = streamed();
+
+    assert_eq!(data[10], RGB16::new(0, 257, 2 * 257));
+    assert_eq!(data[11], RGB16::new(3 * 257, 0, 0));
+}
+
+#[test]
+fn white_of_rgbw_data_is_added_to_the_colors() {
+    let _stream = lock_stream();
+    let network = Network::new();
+    show_black(&network);
+
+    network
+        .send(&packet(PUSH, RGBW, 8, &[10, 20, 250, 10, 0, 0, 0, 0]))
+        .unwrap();
+    let misaligned = network.send(&packet(PUSH, RGBW, 6, &[1, 1, 1, 1]));
+    let data = streamed();
+
+    assert_eq!(data[2], RGB16::new(20 * 257, 30 * 257, 0xFFFF));
+    assert_eq!(misaligned, Err(PacketError::InvalidOffset));
+}
+
+#[test]
+fn time_code_is_skipped() {
+    let mut timed = packet(0x51, RGB, 0, &[1, 2, 3]);
+    timed.splice(10..10, [0x12, 0x34, 0x56, 0x78]);
+
+    let packet = parse(&timed).unwrap().unwrap();
+
+    assert!(packet.push);
+    assert_eq!(packet.data_type, DataType::Rgb);
+    assert_eq!(packet.data, [1, 2, 3]);
+}
+
+#[test]
+fn queries_are_ignored_and_invalid_packets_rejected() {
+    let valid = packet(PUSH, RGB, 0, &[1, 2, 3]);
+    let with = |index: usize, byte: u8| {
+        let mut packet = valid.clone();
+        packet[index] = byte;
+        packet
+    };
+    // A query of the configuration, as xLights sends it.
+    let query = [0x42, 0x00, 0x00, 0xFB, 0, 0, 0, 0, 0, 0];
+
+    let packets = [
+        (valid[..9].to_vec(), PacketError::TooShort),
+        (valid[..12].to_vec(), PacketError::TooShort),
+        (with(0, 0x51), PacketError::TooShort),
+        (with(0, 0x81), PacketError::UnsupportedVersion),
+        // HSL data.
+        (with(2, 0x13), PacketError::UnsupportedDataType),
+        (with(3, 0xFA), PacketError::UnsupportedDestination),
+    ];
+
+    assert_eq!(parse(&query), Ok(None));
+    for (packet, error) in packets {
+        assert_eq!(parse(&packet), Err(error), "{packet:02x?}");
+    }
+}
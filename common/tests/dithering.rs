@@ -0,0 +1,90 @@
+//! Tests of the temporal dithering of the colors written to the LEDs.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, BrightnessCurve, COLORS, COLORS_TOTAL, Settings, create_data, widen,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::power::PowerBudget;
+use embassy_futures::block_on;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::colors::DARK_RED;
+use std::collections::BTreeSet;
+
+mod support;
+
+use support::render::{FirstLedSection, NoDelay};
+
+/// The number of frames the output of each brightness is averaged over.
+const FRAMES: u32 = 257;
+
+/// Renders dark red at the given brightness for a number of frames, and returns the sum of the red
+/// channel of the first LED.
+fn render_dark_red(brightness: u16, dithering_enabled: bool) -> u32 {
+    let color_index = COLORS
+        .iter()
+        .position(|color| *color == widen(DARK_RED))
+        .unwrap();
+    let mut settings = Settings::new(color_index, brightness, 0, u16::from(u8::MAX), COLORS_TOTAL);
+    // Every reading of the potentiometer is then a distinct brightness.
+    settings.set_brightness_curve(BrightnessCurve::Linear);
+    settings.set_dithering(dithering_enabled);
+    settings.set_power_budget(PowerBudget::new(100_000, 100_000, 100_000));
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    let mut data = create_data();
+    animation.update(&mut data, &settings);
+
+    let mut dithering = Dithering::new();
+    let mut section_1 = FirstLedSection::default();
+    let mut section_2 = FirstLedSection::default();
+    let mut sum = 0;
+    for _ in 0..FRAMES {
+        block_on(animation.render(
+            &data,
+            &mut section_1,
+            &mut section_2,
+            &mut dithering,
+            &mut NoDelay,
+            &settings,
+        ))
+        .unwrap();
+        sum += u32::from(section_1.color.r);
+    }
+    sum
+}
+
+#[test]
+fn dithering_averages_between_the_quantized_values() {
+    for brightness in 0..=u16::from(u8::MAX) {
+        let quantized = render_dark_red(brightness, false);
+        let dithered = render_dark_red(brightness, true);
+
+        // Without dithering, every frame is rounded down. With it, the frames average to a value
+        // that is less than a step above.
+        assert!(
+            (quantized..quantized + FRAMES).contains(&dithered),
+            "brightness {brightness}: quantized {quantized}, dithered {dithered}"
+        );
+    }
+}
+
+#[test]
+fn dithering_shows_more_levels_at_low_brightness() {
+    let levels = |dithering_enabled: bool| {
+        (0..32)
+            .map(|brightness| render_dark_red(brightness, dithering_enabled))
+            .collect::<BTreeSet<_>>()
+            .len()
+    };
+
+    let quantized = levels(false);
+    let dithered = levels(true);
+
+    assert!(
+        dithered > quantized * 4,
+        "quantized {quantized} levels, dithered {dithered} levels"
+    );
+}
@@ -0,0 +1,313 @@
+//! Tests of the E1.31 receiver, fed by a sender on a local UDP socket.
+
+use cookie_monster_common::animations::LEDS_FIRST_SECTION;
+use cookie_monster_common::e131::{
+    DATA_LOSS_TIMEOUT, Mapping, PacketError, Receiver, multicast_address, parse,
+};
+use embassy_time::{Duration, Instant};
+use smart_leds::RGB16;
+
+mod support;
+
+use support::loopback::Loopback;
+use support::stream::{LEDS_TOTAL, gray, lock_stream, streamed};
+
+const SENDER: [u8; 16] = *b"cookie-monster-1";
+const OTHER_SENDER: [u8; 16] = *b"cookie-monster-2";
+
+/// A data packet of E1.31, as its sources send it.
+struct Packet {
+    cid: [u8; 16],
+    universe: u16,
+    priority: u8,
+    sequence: u8,
+    options: u8,
+    channels: Vec<u8>,
+}
+
+impl Packet {
+    fn new(universe: u16, sequence: u8, channels: Vec<u8>) -> Self {
+        Self {
+            cid: SENDER,
+            universe,
+            priority: 100,
+            sequence,
+            options: 0,
+            channels,
+        }
+    }
+
+    fn encode(&self) -> Vec<u8> {
+        // The flags and the length of each layer, from its start to the end of the packet.
+        let flags_and_length = |offset: usize| {
+            (0x7000 | u16::try_from(126 + self.channels.len() - offset).unwrap()).to_be_bytes()
+        };
+        let mut name = [0; 64];
+        name[..14].copy_from_slice(b"cookie monster");
+
+        let mut packet = Vec::new();
+        packet.extend([0x00, 0x10, 0x00, 0x00]);
+        packet.extend(b"ASC-E1.17\0\0\0");
+        packet.extend(flags_and_length(16));
+        packet.extend(4_u32.to_be_bytes());
+        packet.extend(self.cid);
+        packet.extend(flags_and_length(38));
+        packet.extend(2_u32.to_be_bytes());
+        packet.extend(name);
+        packet.push(self.priority);
+        packet.extend(0_u16.to_be_bytes());
+        packet.push(self.sequence);
+        packet.push(self.options);
+        packet.extend(self.universe.to_be_bytes());
+        packet.extend(flags_and_length(115));
+        packet.extend([0x02, 0xA1, 0x00, 0x00, 0x00, 0x01]);
+        packet.extend(
+            u16::try_from(self.channels.len() + 1)
+                .unwrap()
+                .to_be_bytes(),
+        );
+        packet.push(0x00);
+        packet.extend(&self.channels);
+        packet
+    }
+}
+
+/// A sender and a receiver of E1.31 packets on the loopback interface.
+struct Network {
+    loopback: Loopback,
+    receiver: Receiver,
+}
+
+impl Network {
+    fn new(mapping: Mapping) -> Self {
+        Self {
+            loopback: Loopback::new(),
+            receiver: Receiver::new(mapping),
+        }
+    }
+
+    /// Sends the packet, and returns how the receiver handled it at the given time.
+    fn send(&mut self, packet: &[u8], now: Instant) -> Result<(), PacketError> {
+        let received = self.loopback.send(packet);
+        self.receiver.receive(&received, now)
+    }
+}
+
+/// The channels of a universe, whose LEDs are a gray of its number.
+fn universe_channels(universe: u16) -> Vec<u8> {
+    vec![u8::try_from(universe).unwrap() * 10; 512]
+}
+
+#[test]
+fn universes_are_mapped_across_the_two_sections() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let now = Instant::now();
+
+    for universe in 1..=5 {
+        let packet = Packet::new(universe, 0, universe_channels(universe));
+        network.send(&packet.encode(), now).unwrap();
+    }
+    let partial = streamed();
+    network
+        .send(&Packet::new(6, 0, universe_channels(6)).encode(), now)
+        .unwrap();
+    let data = streamed();
+
+    assert_eq!(network.receiver.mapping().universes(), 1..=6);
+    assert_ne!(partial[0], gray(10), "shown before its last universe");
+    assert_eq!(data[0], gray(10));
+    assert_eq!(data[169], gray(10));
+    assert_eq!(data[170], gray(20));
+    assert_eq!(data[LEDS_FIRST_SECTION - 1], gray(30));
+    assert_eq!(data[LEDS_FIRST_SECTION], gray(30));
+    assert_eq!(data[LEDS_TOTAL - 1], gray(60));
+}
+
+#[test]
+fn leds_start_at_the_start_channel() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(10, 4));
+    let now = Instant::now();
+    let mut channels = vec![0; 512];
+    channels[3..6].copy_from_slice(&[1, 2, 3]);
+
+    let ignored = network.send(&Packet::new(9, 0, vec![255; 512]).encode(), now);
+    for universe in 10..=15 {
+        let channels = if universe == 10 {
+            channels.clone()
+        } else {
+            universe_channels(universe)
+        };
+        network
+            .send(&Packet::new(universe, 0, channels).encode(), now)
+            .unwrap();
+    }
+    let data = streamed();
+
+    assert_eq!(ignored, Ok(()));
+    assert_eq!(network.receiver.mapping().universes(), 10..=15);
+    assert_eq!(data[0], RGB16::new(257, 2 * 257, 3 * 257));
+    // The first universe holds one LED less, since it starts at its fourth channel.
+    assert_eq!(data[169], gray(110));
+}
+
+#[test]
+fn universe_repeated_before_the_others_shows_the_frame() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let now = Instant::now();
+
+    network
+        .send(&Packet::new(1, 0, vec![7; 512]).encode(), now)
+        .unwrap();
+    network
+        .send(&Packet::new(1, 1, vec![8; 512]).encode(), now)
+        .unwrap();
+
+    assert_eq!(streamed()[0], gray(7));
+}
+
+#[test]
+fn higher_priority_holds_the_universe_until_its_data_is_lost() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let start = Instant::now();
+    let packet = |cid, priority, level| Packet {
+        cid,
+        priority,
+        ..Packet::new(1, 0, vec![level; 512])
+    };
+
+    let low = network.send(&packet(OTHER_SENDER, 100, 1).encode(), start);
+    let high = network.send(&packet(SENDER, 150, 2).encode(), start);
+    let lower = network.send(&packet(OTHER_SENDER, 100, 3).encode(), start);
+    let equal = network.send(&packet(OTHER_SENDER, 150, 3).encode(), start);
+    let lost = start + DATA_LOSS_TIMEOUT;
+    let after_loss = network.send(&packet(OTHER_SENDER, 100, 4).encode(), lost);
+
+    assert_eq!(low, Ok(()));
+    assert_eq!(high, Ok(()));
+    assert_eq!(lower, Err(PacketError::LowerPriority));
+    assert_eq!(equal, Err(PacketError::LowerPriority));
+    assert_eq!(after_loss, Ok(()));
+}
+
+#[test]
+fn packets_out_of_sequence_are_dropped() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let now = Instant::now();
+    let mut send = |sequence| {
+        network.send(
+            &Packet::new(1, sequence, vec![0; 512]).encode(),
+            now + Duration::from_millis(1),
+        )
+    };
+
+    let results = [
+        send(250),
+        send(251),
+        send(251),
+        send(240),
+        // The sequence wraps around.
+        send(3),
+        // A source that restarted is far behind its last packet.
+        send(200),
+    ];
+
+    assert_eq!(
+        results,
+        [
+            Ok(()),
+            Ok(()),
+            Err(PacketError::OutOfSequence),
+            Err(PacketError::OutOfSequence),
+            Ok(()),
+            Ok(()),
+        ]
+    );
+}
+
+#[test]
+fn terminated_source_releases_its_universe() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let now = Instant::now();
+    let terminated = Packet {
+        options: 0x40,
+        ..Packet::new(1, 1, vec![0; 512])
+    };
+    let other = Packet {
+        cid: OTHER_SENDER,
+        priority: 10,
+        ..Packet::new(1, 0, vec![0; 512])
+    };
+
+    network
+        .send(&Packet::new(1, 0, vec![0; 512]).encode(), now)
+        .unwrap();
+    let held = network.send(&other.encode(), now);
+    network.send(&terminated.encode(), now).unwrap();
+    let released = network.send(&other.encode(), now);
+
+    assert_eq!(held, Err(PacketError::LowerPriority));
+    assert_eq!(released, Ok(()));
+}
+
+#[test]
+fn preview_data_is_ignored() {
+    let _stream = lock_stream();
+    let mut network = Network::new(Mapping::new(1, 1));
+    let now = Instant::now();
+    network
+        .send(&Packet::new(1, 0, vec![1; 512]).encode(), now)
+        .unwrap();
+    network
+        .send(&Packet::new(1, 1, vec![1; 512]).encode(), now)
+        .unwrap();
+
+    let preview = Packet {
+        options: 0x80,
+        ..Packet::new(1, 2, vec![9; 512])
+    };
+    network.send(&preview.encode(), now).unwrap();
+    network
+        .send(&Packet::new(1, 3, vec![1; 512]).encode(), now)
+        .unwrap();
+
+    assert_eq!(streamed()[0], gray(1));
+}
+
+#[test]
+fn invalid_packets_are_rejected() {
+    let valid = Packet::new(1, 0, vec![1, 2, 3]).encode();
+    let with = |index: usize, byte: u8| {
+        let mut packet = valid.clone();
+        packet[index] = byte;
+        packet
+    };
+
+    let packets = [
+        (valid[..125].to_vec(), PacketError::TooShort),
+        (valid[..128].to_vec(), PacketError::TooShort),
+        (with(4, b'B'), PacketError::InvalidIdentifier),
+        // The synchronization packets of the root layer.
+        (with(21, 0x08), PacketError::UnsupportedVector),
+        (with(43, 0x01), PacketError::UnsupportedVector),
+        (with(118, 0xA0), PacketError::InvalidLayout),
+        (with(124, 0x00), PacketError::InvalidLayout),
+        (with(114, 0x00), PacketError::InvalidUniverse),
+    ];
+
+    assert_eq!(parse(&valid).unwrap().channels, [1, 2, 3]);
+    for (packet, error) in packets {
+        assert_eq!(parse(&packet), Err(error), "{packet:02x?}");
+    }
+}
+
+#[test]
+fn universes_have_their_multicast_group() {
+    assert_eq!(multicast_address(1), [239, 255, 0, 1]);
+    assert_eq!(multicast_address(63_999), [239, 255, 249, 255]);
+}
@@ -0,0 +1,143 @@
+//! Tests of the exchange of the frames between the core computing them and the one writing them.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, Settings, create_data,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::exchange::FrameExchange;
+use embassy_futures::{block_on, poll_once};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use std::thread;
+
+mod support;
+
+use support::render::{NoDelay, RecordingSection};
+
+const FRAMES: usize = 20;
+
+fn settings() -> Settings {
+    Settings::new(3, 800, 1, 1_000, COLORS_TOTAL)
+}
+
+/// Renders the frames one after the other, updating each of them before it's written.
+fn render_sequentially(
+    kind: AnimationKind, settings: &Settings,
+) -> (RecordingSection, RecordingSection) {
+    let mut animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+    let mut data = create_data();
+    let mut dithering = Dithering::new();
+    let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+    for _ in 0..FRAMES {
+        animation.update(&mut data, settings);
+        block_on(animation.render(
+            &data,
+            &mut sections.0,
+            &mut sections.1,
+            &mut dithering,
+            &mut NoDelay,
+            settings,
+        ))
+        .unwrap();
+    }
+
+    sections
+}
+
+#[test]
+fn exchanged_frames_match_the_sequential_ones() {
+    let settings = settings();
+
+    for kind in AnimationKind::ALL {
+        let (expected_1, expected_2) = render_sequentially(kind, &settings);
+        let exchange = FrameExchange::new();
+        let (mut sender, mut receiver) = exchange.split().unwrap();
+        let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+        thread::scope(|scope| {
+            scope.spawn(|| {
+                let mut animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+                for _ in 0..FRAMES {
+                    block_on(sender.send(&mut animation, &settings));
+                }
+            });
+
+            let mut dithering = Dithering::new();
+            for _ in 0..FRAMES {
+                block_on(receiver.render(
+                    &mut sections.0,
+                    &mut sections.1,
+                    &mut dithering,
+                    &mut NoDelay,
+                ))
+                .unwrap();
+                receiver.next_frame();
+            }
+        });
+
+        assert_eq!(sections.0.frames, expected_1.frames, "{kind:?}");
+        assert_eq!(sections.1.frames, expected_2.frames, "{kind:?}");
+    }
+}
+
+#[test]
+fn sender_waits_for_a_free_buffer() {
+    let settings = settings();
+    let exchange = FrameExchange::new();
+    let (mut sender, mut receiver) = exchange.split().unwrap();
+    let mut animation = Animation::new(AnimationKind::ForwardWave, &mut SmallRng::seed_from_u64(0));
+    let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+    block_on(sender.send(&mut animation, &settings));
+    block_on(sender.send(&mut animation, &settings));
+    assert!(poll_once(sender.send(&mut animation, &settings)).is_pending());
+
+    block_on(receiver.render(
+        &mut sections.0,
+        &mut sections.1,
+        &mut Dithering::new(),
+        &mut NoDelay,
+    ))
+    .unwrap();
+    assert!(poll_once(sender.send(&mut animation, &settings)).is_pending());
+
+    receiver.next_frame();
+    assert!(poll_once(sender.send(&mut animation, &settings)).is_ready());
+}
+
+#[test]
+fn new_animation_replaces_the_frame_already_sent() {
+    let settings = settings();
+    let exchange = FrameExchange::new();
+    let (mut sender, mut receiver) = exchange.split().unwrap();
+    let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    block_on(sender.send(&mut animation, &settings));
+    sender.reset();
+    let mut animation = Animation::new(AnimationKind::ForwardWave, &mut SmallRng::seed_from_u64(0));
+    block_on(sender.send(&mut animation, &settings));
+
+    block_on(receiver.render(
+        &mut sections.0,
+        &mut sections.1,
+        &mut Dithering::new(),
+        &mut NoDelay,
+    ))
+    .unwrap();
+
+    let (expected, _) = render_sequentially(AnimationKind::ForwardWave, &settings);
+    assert_eq!(sections.0.frames, expected.frames[..1]);
+}
+
+#[test]
+fn exchange_is_split_only_once() {
+    let exchange = FrameExchange::new();
+
+    assert!(exchange.split().is_some());
+    assert!(exchange.split().is_none());
+}
@@ -0,0 +1,88 @@
+//! Tests of the gamma curves of the colors and of the brightness curves.
+
+use cookie_monster_common::animations::{BrightnessCurve, COLORS_TOTAL, Settings};
+use cookie_monster_common::gamma::Gamma;
+use proptest::prelude::*;
+use smart_leds::RGB16;
+
+mod support;
+
+const ANALOG_MAX_VALUE: u16 = 4095;
+
+fn gray(value: u16) -> RGB16 {
+    RGB16::new(value, value, value)
+}
+
+fn brightness(reading: u16, curve: BrightnessCurve) -> u8 {
+    let mut settings = Settings::new(0, reading, 0, ANALOG_MAX_VALUE, COLORS_TOTAL);
+    settings.set_brightness_curve(curve);
+    settings.brightness()
+}
+
+#[test]
+fn exponent_of_one_leaves_the_colors_unchanged() {
+    let gamma = Gamma::new(1.0);
+    for value in (0..=u16::MAX).step_by(97) {
+        let corrected = gamma.correct(gray(value)).r;
+        assert!(corrected.abs_diff(value) <= 1, "{value} became {corrected}");
+    }
+}
+
+#[test]
+fn exponents_are_clamped() {
+    assert_eq!(Gamma::new(0.5).exponents(), [Gamma::MIN_EXPONENT; 3]);
+    assert_eq!(Gamma::new(f32::NAN).exponents(), [Gamma::MIN_EXPONENT; 3]);
+    assert_eq!(Gamma::new(10.0).exponents(), [Gamma::MAX_EXPONENT; 3]);
+}
+
+#[test]
+fn each_channel_follows_its_own_curve() {
+    let gamma = Gamma::per_channel(1.8, 2.2, 2.8);
+    for value in (0..=u16::MAX).step_by(97) {
+        let color = gamma.correct(gray(value));
+        assert_eq!(color.r, Gamma::new(1.8).correct(gray(value)).r);
+        assert_eq!(color.g, Gamma::new(2.2).correct(gray(value)).g);
+        assert_eq!(color.b, Gamma::new(2.8).correct(gray(value)).b);
+    }
+}
+
+#[test]
+fn cie_1931_midpoint_is_perceived_as_half_brightness() {
+    let midpoint = ANALOG_MAX_VALUE / 2;
+
+    // A lightness of 50 is a luminance of 18.4%.
+    assert_eq!(brightness(midpoint, BrightnessCurve::Cie1931), 46);
+    assert_eq!(brightness(midpoint, BrightnessCurve::Linear), 127);
+}
+
+#[test]
+fn brightness_curve_change_applies_to_the_current_reading() {
+    let mut settings = Settings::new(0, ANALOG_MAX_VALUE / 2, 0, ANALOG_MAX_VALUE, COLORS_TOTAL);
+    assert_eq!(settings.brightness_curve(), BrightnessCurve::Cie1931);
+
+    settings.set_brightness_curve(BrightnessCurve::Linear);
+    assert_eq!(settings.brightness(), 127);
+}
+
+proptest! {
+    #[test]
+    fn gamma_curves_are_monotonic_and_keep_the_extremes(
+        exponent in Gamma::MIN_EXPONENT..=Gamma::MAX_EXPONENT, value in 0..u16::MAX,
+    ) {
+        let gamma = Gamma::new(exponent);
+        prop_assert_eq!(gamma.correct(gray(0)), gray(0));
+        prop_assert_eq!(gamma.correct(gray(u16::MAX)), gray(u16::MAX));
+        prop_assert!(gamma.correct(gray(value)).r <= gamma.correct(gray(value + 1)).r);
+        prop_assert!(gamma.correct(gray(value)).r <= value);
+    }
+
+    #[test]
+    fn brightness_curves_are_monotonic_and_keep_the_extremes(
+        reading in 0..ANALOG_MAX_VALUE,
+        curve in prop::sample::select(vec![BrightnessCurve::Linear, BrightnessCurve::Cie1931]),
+    ) {
+        prop_assert_eq!(brightness(0, curve), 0);
+        prop_assert_eq!(brightness(ANALOG_MAX_VALUE, curve), u8::MAX);
+        prop_assert!(brightness(reading, curve) <= brightness(reading + 1, curve));
+    }
+}
@@ -0,0 +1,146 @@
+//! Tests of the Open Pixel Control server, fed by a client on a local TCP connection.
+
+use cookie_monster_common::animations::{LEDS_FIRST_SECTION, Section};
+use cookie_monster_common::color_correction::ColorCorrection;
+use cookie_monster_common::opc::{Parser, SYSEX_COLOR_CORRECTION, SYSTEM_ID};
+use cookie_monster_common::signal::COLOR_CORRECTION_CHANGED_SIGNAL;
+use embassy_time::Instant;
+use smart_leds::{RGB8, RGB16};
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+
+mod support;
+
+use support::stream::{LEDS_TOTAL, gray, lock_stream, streamed};
+
+/// A message, as the OPC clients send it.
+fn message(channel: u8, command: u8, data: &[u8]) -> Vec<u8> {
+    let mut message = vec![channel, command];
+    message.extend(u16::try_from(data.len()).unwrap().to_be_bytes());
+    message.extend(data);
+    message
+}
+
+/// A message setting the colors of the LEDs of the channel.
+fn pixels(channel: u8, colors: &[u8]) -> Vec<u8> {
+    message(channel, 0, colors)
+}
+
+/// A system-exclusive message setting the color correction of the sections of the channel.
+fn color_correction(channel: u8, correction: [u8; 3], temperature: [u8; 3]) -> Vec<u8> {
+    let mut data = SYSTEM_ID.to_be_bytes().to_vec();
+    data.push(SYSEX_COLOR_CORRECTION);
+    data.extend(correction);
+    data.extend(temperature);
+    message(channel, 255, &data)
+}
+
+/// A client connected to the server on the loopback interface.
+struct Connection {
+    client: TcpStream,
+    server: TcpStream,
+    parser: Parser,
+}
+
+impl Connection {
+    fn new() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let client = TcpStream::connect(listener.local_addr().unwrap()).unwrap();
+        let (server, _) = listener.accept().unwrap();
+        server
+            .set_read_timeout(Some(std::time::Duration::from_secs(1)))
+            .unwrap();
+
+        Self {
+            client,
+            server,
+            parser: Parser::new(),
+        }
+    }
+
+    /// Sends the messages from the client, and parses them as the server receives them, in small
+    /// reads that split the messages.
+    fn send(&mut self, messages: &[Vec<u8>]) {
+        let bytes = messages.concat();
+        self.client.write_all(&bytes).unwrap();
+        let mut buffer = [0; 7];
+        let mut received = 0;
+        while received < bytes.len() {
+            let length = self.server.read(&mut buffer).unwrap();
+            for &byte in &buffer[..length] {
+                self.parser.receive(byte, Instant::now());
+            }
+            received += length;
+        }
+    }
+}
+
+#[test]
+fn broadcast_sets_all_the_leds() {
+    let _stream = lock_stream();
+    let mut connection = Connection::new();
+    let colors: Vec<u8> = (0..LEDS_TOTAL * 3).map(|i| (i % 251) as u8).collect();
+
+    connection.send(&[pixels(0, &colors)]);
+    let data = streamed();
+
+    assert_eq!(data[0], RGB16::new(0, 257, 2 * 257));
+    assert_eq!(
+        data[LEDS_TOTAL - 1],
+        RGB16::new(116 * 257, 117 * 257, 118 * 257)
+    );
+}
+
+#[test]
+fn section_channels_stop_at_their_last_led() {
+    let _stream = lock_stream();
+    let mut connection = Connection::new();
+    connection.send(&[pixels(0, &[0; LEDS_TOTAL * 3])]);
+
+    connection.send(&[
+        pixels(1, &[10; (LEDS_FIRST_SECTION + 1) * 3]),
+        pixels(2, &[20; 6]),
+    ]);
+    let data = streamed();
+
+    assert_eq!(data[LEDS_FIRST_SECTION - 1], gray(10));
+    assert_eq!(data[LEDS_FIRST_SECTION], gray(20));
+    assert_eq!(data[LEDS_FIRST_SECTION + 1], gray(20));
+    assert_eq!(data[LEDS_FIRST_SECTION + 2], gray(0));
+}
+
+#[test]
+fn unknown_messages_are_skipped() {
+    let _stream = lock_stream();
+    let mut connection = Connection::new();
+    connection.send(&[pixels(0, &[0; 3])]);
+
+    connection.send(&[
+        // A channel that isn't on the board, an unknown command, and a message of another device.
+        pixels(3, &[30; 3]),
+        message(0, 2, &[40; 5]),
+        message(0, 255, &[0x00, 0x01, 0x01, 2, 3, 4, 5, 6, 7]),
+        pixels(0, &[50; 3]),
+    ]);
+
+    assert_eq!(streamed()[0], gray(50));
+    assert_eq!(COLOR_CORRECTION_CHANGED_SIGNAL.try_take(), None);
+}
+
+#[test]
+fn system_exclusive_sets_the_color_correction() {
+    let _stream = lock_stream();
+    let mut connection = Connection::new();
+    let expected = |section| {
+        let correction = ColorCorrection::new(RGB8::new(255, 176, 240), RGB8::new(255, 147, 41));
+        Some((section, correction))
+    };
+
+    connection.send(&[color_correction(2, [255, 176, 240], [255, 147, 41])]);
+    let second = COLOR_CORRECTION_CHANGED_SIGNAL.try_take();
+    connection.send(&[color_correction(0, [255, 176, 240], [255, 147, 41])]);
+    let both = COLOR_CORRECTION_CHANGED_SIGNAL.try_take();
+
+    assert_eq!(second, expected(Some(Section::Second)));
+    assert_eq!(both, expected(None));
+}
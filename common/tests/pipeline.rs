@@ -0,0 +1,135 @@
+//! Tests of the double-buffered rendering, which computes the next frame while one is written.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, Settings, create_data,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::pipeline::Pipeline;
+use embassy_futures::block_on;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+mod support;
+
+use support::render::{NoDelay, RecordingSection};
+
+const FRAMES: usize = 20;
+
+fn settings() -> Settings {
+    Settings::new(3, 800, 1, 1_000, COLORS_TOTAL)
+}
+
+/// Renders the frames one after the other, updating each of them before it's written.
+fn render_sequentially(
+    kind: AnimationKind, settings: &Settings,
+) -> (RecordingSection, RecordingSection) {
+    let mut animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+    let mut data = create_data();
+    let mut dithering = Dithering::new();
+    let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+    for _ in 0..FRAMES {
+        animation.update(&mut data, settings);
+        block_on(animation.render(
+            &data,
+            &mut sections.0,
+            &mut sections.1,
+            &mut dithering,
+            &mut NoDelay,
+            settings,
+        ))
+        .unwrap();
+    }
+
+    sections
+}
+
+/// Renders the frames with the pipeline, retrying the frames whose write failed.
+fn render_pipelined(
+    kind: AnimationKind, mut sections: (RecordingSection, RecordingSection), settings: &Settings,
+) -> (RecordingSection, RecordingSection) {
+    let mut animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+    let mut pipeline = Pipeline::new();
+    let mut dithering = Dithering::new();
+
+    for _ in 0..FRAMES {
+        while block_on(pipeline.render(
+            &mut animation,
+            &mut sections.0,
+            &mut sections.1,
+            &mut dithering,
+            &mut NoDelay,
+            settings,
+        ))
+        .is_err()
+        {}
+        pipeline.next_frame();
+    }
+
+    sections
+}
+
+#[test]
+fn pipelined_frames_match_the_sequential_ones() {
+    let settings = settings();
+
+    for kind in AnimationKind::ALL {
+        let (expected_1, expected_2) = render_sequentially(kind, &settings);
+        let (section_1, section_2) = render_pipelined(kind, Default::default(), &settings);
+
+        assert_eq!(section_1.frames, expected_1.frames, "{kind:?}");
+        assert_eq!(section_2.frames, expected_2.frames, "{kind:?}");
+    }
+}
+
+#[test]
+fn failed_frame_is_written_again_before_the_next_one() {
+    let kind = AnimationKind::UniColorFadeIn;
+    // The residuals of the dithering change with each write, even when the frame doesn't.
+    let mut settings = settings();
+    settings.set_dithering(false);
+    let (expected, _) = render_sequentially(kind, &settings);
+    let sections = (
+        RecordingSection {
+            failures: 2,
+            ..Default::default()
+        },
+        RecordingSection::default(),
+    );
+
+    let (section, _) = render_pipelined(kind, sections, &settings);
+
+    assert!(
+        section.frames[..3]
+            .iter()
+            .all(|frame| *frame == expected.frames[0])
+    );
+    assert_eq!(section.frames[3..], expected.frames[1..]);
+}
+
+#[test]
+fn reset_starts_the_animation_over() {
+    let kind = AnimationKind::ForwardWave;
+    let settings = settings();
+    let mut animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+    let mut pipeline = Pipeline::new();
+    let mut sections = (RecordingSection::default(), RecordingSection::default());
+
+    for _ in 0..2 {
+        block_on(pipeline.render(
+            &mut animation,
+            &mut sections.0,
+            &mut sections.1,
+            &mut Dithering::new(),
+            &mut NoDelay,
+            &settings,
+        ))
+        .unwrap();
+        pipeline.next_frame();
+
+        pipeline.reset();
+        animation = Animation::new(kind, &mut SmallRng::seed_from_u64(0));
+    }
+
+    assert_eq!(sections.0.frames[0], sections.0.frames[1]);
+}
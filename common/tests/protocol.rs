@@ -0,0 +1,300 @@
+//! Tests of the binary control protocol, from the messages to their frames and back.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, LEDS_FIRST_SECTION, LEDS_SECOND_SECTION, Section,
+    Settings,
+};
+use cookie_monster_common::color_correction::ColorCorrection;
+use cookie_monster_common::command;
+use cookie_monster_common::command::{Color, Command, Status};
+use cookie_monster_common::console::Console;
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::pipeline::Pipeline;
+use cookie_monster_common::protocol::{
+    CHUNK_SIZE, Chunk, DELIMITER, FRAME_SIZE, FrameError, Request, Response, ResponseError,
+    Statistics, Timing, VERSION, decode, encode,
+};
+use cookie_monster_common::signal::COMMAND_CHANNEL;
+use embassy_futures::block_on;
+use proptest::prelude::*;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::RGB8;
+
+mod support;
+
+use support::render::{NoDelay, RecordingSection};
+
+fn any_color() -> impl Strategy<Value = Color> {
+    prop_oneof![
+        any::<u8>().prop_map(Color::Index),
+        any::<[u8; 3]>().prop_map(|[r, g, b]| Color::Rgb(RGB8::new(r, g, b))),
+    ]
+}
+
+fn any_command() -> impl Strategy<Value = Command> {
+    prop_oneof![
+        prop::sample::select(AnimationKind::ALL.to_vec()).prop_map(Command::Animation),
+        Just(Command::NextAnimation),
+        Just(Command::PreviousAnimation),
+        any_color().prop_map(Command::Color),
+        any::<u8>().prop_map(Command::Brightness),
+        any::<u16>().prop_map(Command::Speed),
+        Just(Command::Status),
+        Just(Command::Stats),
+        Just(Command::SavePreset),
+        Just(Command::LoadPreset),
+        (
+            prop::sample::select(vec![None, Some(Section::First), Some(Section::Second)]),
+            any::<[u8; 6]>(),
+        )
+            .prop_map(|(section, [r, g, b, tr, tg, tb])| {
+                let correction = RGB8::new(r, g, b);
+                let temperature = RGB8::new(tr, tg, tb);
+                Command::ColorCorrection(section, ColorCorrection::new(correction, temperature))
+            }),
+    ]
+}
+
+fn any_request() -> impl Strategy<Value = Request> {
+    prop_oneof![
+        any_command().prop_map(Request::Command),
+        any::<u16>().prop_map(|offset| Request::Frame { offset }),
+    ]
+}
+
+fn any_timing() -> impl Strategy<Value = Timing> {
+    any::<[u32; 3]>().prop_map(|[min, average, max]| Timing { min, average, max })
+}
+
+fn any_response() -> impl Strategy<Value = Response> {
+    let status = (
+        prop::sample::select(AnimationKind::ALL.to_vec()),
+        any::<u8>(),
+        any_color(),
+        any::<u32>(),
+        any::<bool>(),
+    )
+        .prop_map(|(animation, brightness, color, delay, dithering)| Status {
+            animation,
+            brightness,
+            color,
+            delay,
+            dithering,
+        });
+    let statistics = (
+        any::<[u32; 4]>(),
+        any::<u8>(),
+        [any_timing(), any_timing(), any_timing(), any_timing()],
+        any::<[u32; 4]>(),
+    )
+        .prop_map(
+            |([frames, elapsed, frame_rate, skipped_frames], busy_percentage, timings, errors)| {
+                Statistics {
+                    frames,
+                    elapsed,
+                    frame_rate,
+                    busy_percentage,
+                    update: timings[0],
+                    write: [timings[1], timings[2]],
+                    idle: timings[3],
+                    write_errors: [errors[0], errors[1]],
+                    skipped_frames,
+                    reinitializations: [errors[2], errors[3]],
+                }
+            },
+        );
+    prop_oneof![
+        Just(Response::Queued),
+        status.prop_map(Response::Status),
+        statistics.prop_map(Response::Statistics),
+        Just(Response::Error(ResponseError::Busy)),
+        Just(Response::Error(ResponseError::InvalidFrame(
+            FrameError::UnsupportedVersion(u8::MAX)
+        ))),
+    ]
+}
+
+/// Encodes the message, and returns the frame without its delimiters.
+fn frame_of<T: serde::Serialize>(message: &T) -> Vec<u8> {
+    let mut frame = [0; FRAME_SIZE];
+    let length = encode(message, &mut frame).unwrap();
+    assert_eq!(frame[0], DELIMITER);
+    assert_eq!(frame[length - 1], DELIMITER);
+    frame[1..length - 1].to_vec()
+}
+
+proptest! {
+    #[test]
+    fn every_request_is_decoded_as_encoded(request in any_request()) {
+        let mut frame = frame_of(&request);
+
+        prop_assert!(!frame.contains(&DELIMITER));
+        prop_assert_eq!(decode::<Request>(&mut frame), Ok(request));
+    }
+
+    #[test]
+    fn every_response_is_decoded_as_encoded(response in any_response()) {
+        let mut frame = frame_of(&response);
+
+        prop_assert!(!frame.contains(&DELIMITER));
+        prop_assert_eq!(decode::<Response>(&mut frame), Ok(response));
+    }
+
+    #[test]
+    fn corrupted_frame_is_rejected(
+        command in any_command(), index in any::<prop::sample::Index>(), flip in 1..=u8::MAX
+    ) {
+        let mut frame = frame_of(&Request::Command(command));
+        let index = index.index(frame.len());
+        frame[index] ^= flip;
+
+        prop_assert!(decode::<Request>(&mut frame).is_err());
+    }
+}
+
+/// Renders a frame of the animation, like the task running it.
+fn render(animation: &mut Animation, settings: &Settings) {
+    block_on(Pipeline::new().render(
+        animation,
+        &mut RecordingSection::default(),
+        &mut RecordingSection::default(),
+        &mut Dithering::new(),
+        &mut NoDelay,
+        settings,
+    ))
+    .unwrap();
+}
+
+#[test]
+fn rendered_frame_is_answered_in_chunks() {
+    let settings = Settings::new(3, 800, 1, 1_000, COLORS_TOTAL);
+    let mut animation = Animation::new(
+        AnimationKind::MultiColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    render(&mut animation, &settings);
+    let unpublished = Chunk::of_frame(0);
+    render(&mut animation, &settings);
+
+    let mut colors = Vec::new();
+    loop {
+        let offset = u16::try_from(colors.len()).unwrap();
+        let chunk = Chunk::of_frame(offset).unwrap();
+        let mut frame = frame_of(&Response::Frame(chunk));
+        let Ok(Response::Frame(chunk)) = decode(&mut frame) else {
+            panic!("the chunk from {offset} isn't decoded");
+        };
+        assert_eq!(chunk.offset, offset);
+        assert_eq!(
+            usize::from(chunk.total),
+            LEDS_FIRST_SECTION + LEDS_SECOND_SECTION
+        );
+        if chunk.colors().is_empty() {
+            break;
+        }
+        assert!(chunk.colors().len() <= CHUNK_SIZE);
+        colors.extend_from_slice(chunk.colors());
+    }
+
+    assert_eq!(unpublished, None);
+    assert_eq!(colors.len(), LEDS_FIRST_SECTION + LEDS_SECOND_SECTION);
+    assert!(colors.iter().any(|color| *color != RGB8::default()));
+    assert_eq!(Chunk::of_frame(0), None);
+}
+
+#[test]
+fn frame_of_another_version_is_rejected_with_its_version() {
+    let mut raw = frame_of(&Request::Command(Command::Status));
+    let length = cobs::decode_in_place(&mut raw).unwrap();
+    assert_eq!(raw[0], VERSION);
+    raw[0] = VERSION + 1;
+    let checksum = crc16(&raw[..length - 2]).to_le_bytes();
+    raw[length - 2..length].copy_from_slice(&checksum);
+    let mut frame = vec![0; cobs::max_encoding_length(length)];
+    let encoded = cobs::encode(&raw[..length], &mut frame);
+
+    assert_eq!(
+        decode::<Request>(&mut frame[..encoded]),
+        Err(FrameError::UnsupportedVersion(VERSION + 1))
+    );
+}
+
+#[test]
+fn truncated_frames_are_rejected() {
+    let frame = frame_of(&Request::Command(Command::Brightness(200)));
+
+    for length in 0..frame.len() {
+        assert!(decode::<Request>(&mut frame[..length].to_vec()).is_err());
+    }
+}
+
+#[test]
+fn console_answers_frames_between_lines() {
+    let mut settings = Settings::new(3, 0, 100, 1_000, COLORS_TOTAL);
+    settings.set_brightness_level(42);
+    command::publish_status(AnimationKind::Shimmer, &settings);
+    let mut console = Console::new();
+    let mut bytes = b"next\n".to_vec();
+    let mut frame = [0; FRAME_SIZE];
+    for request in [
+        Request::Command(Command::Speed(20)),
+        Request::Command(Command::Status),
+    ] {
+        let length = encode(&request, &mut frame).unwrap();
+        bytes.extend(&frame[..length]);
+    }
+    bytes.extend(b"\0\x05garbage\0prev\n");
+
+    let mut replies = Vec::new();
+    for &byte in &bytes {
+        if let Some(reply) = console.receive(byte) {
+            replies.push(reply.to_vec());
+        }
+    }
+    let commands: Vec<_> = std::iter::from_fn(|| COMMAND_CHANNEL.try_receive().ok()).collect();
+    let responses: Vec<Response> = replies[1..4]
+        .iter()
+        .map(|reply| {
+            assert_eq!(reply.first(), Some(&DELIMITER));
+            assert_eq!(reply.last(), Some(&DELIMITER));
+            decode(&mut reply[1..reply.len() - 1].to_vec()).unwrap()
+        })
+        .collect();
+
+    assert_eq!(replies.len(), 5);
+    assert_eq!(replies[0], b"ok\r\n");
+    assert_eq!(replies[4], b"ok\r\n");
+    assert_eq!(
+        responses[..2],
+        [
+            Response::Queued,
+            Response::Status(Status::new(AnimationKind::Shimmer, &settings)),
+        ]
+    );
+    assert!(matches!(
+        responses[2],
+        Response::Error(ResponseError::InvalidFrame(_))
+    ));
+    assert_eq!(
+        commands,
+        [
+            Command::NextAnimation,
+            Command::Speed(20),
+            Command::PreviousAnimation
+        ]
+    );
+}
+
+/// CRC-16/CCITT-FALSE of the bytes, like the protocol.
+fn crc16(bytes: &[u8]) -> u16 {
+    bytes.iter().fold(0xFFFF, |crc, &byte| {
+        (0..8).fold(crc ^ (u16::from(byte) << 8), |crc, _| {
+            if crc & 0x8000 == 0 {
+                crc << 1
+            } else {
+                (crc << 1) ^ 0x1021
+            }
+        })
+    })
+}
@@ -0,0 +1,243 @@
+//! Tests of the persistence of the settings, on a flash memory emulated in RAM.
+
+use cookie_monster_common::animations::{AnimationKind, COLORS_TOTAL, Section, Settings};
+use cookie_monster_common::color_correction::{ColorCorrection, correction, temperature};
+use cookie_monster_common::command;
+use cookie_monster_common::command::{Change, Color, Command, Status};
+use cookie_monster_common::storage::{DeferredSave, PresetError, SAVE_DELAY, SettingsStorage};
+use embassy_time::{Duration, Instant};
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash,
+};
+use smart_leds::RGB8;
+
+mod support;
+
+const SECTOR_SIZE: usize = 4096;
+
+/// A flash memory of two sectors, whose writes can only clear bits like a real one.
+struct RamFlash {
+    bytes: Vec<u8>,
+    erases: usize,
+}
+
+impl RamFlash {
+    fn new() -> Self {
+        Self {
+            bytes: vec![u8::MAX; 2 * SECTOR_SIZE],
+            erases: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct RamFlashError(NorFlashErrorKind);
+
+impl NorFlashError for RamFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        self.0
+    }
+}
+
+impl ErrorType for RamFlash {
+    type Error = RamFlashError;
+}
+
+impl ReadNorFlash for RamFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        let offset = offset as usize;
+        bytes.copy_from_slice(&self.bytes[offset..offset + bytes.len()]);
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        self.bytes.len()
+    }
+}
+
+impl NorFlash for RamFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        if !(from as usize).is_multiple_of(SECTOR_SIZE)
+            || !(to as usize).is_multiple_of(SECTOR_SIZE)
+        {
+            return Err(RamFlashError(NorFlashErrorKind::NotAligned));
+        }
+        self.bytes[from as usize..to as usize].fill(u8::MAX);
+        self.erases += 1;
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        if !(offset as usize).is_multiple_of(4) || !bytes.len().is_multiple_of(4) {
+            return Err(RamFlashError(NorFlashErrorKind::NotAligned));
+        }
+        for (stored, byte) in self.bytes[offset as usize..].iter_mut().zip(bytes) {
+            *stored &= byte;
+        }
+        Ok(())
+    }
+}
+
+fn settings() -> Settings {
+    Settings::new(0, 0, 0, 1, COLORS_TOTAL)
+}
+
+fn corrected_settings() -> Settings {
+    let mut settings = settings();
+    settings.set_color_correction(
+        Section::First,
+        ColorCorrection::new(correction::TYPICAL_LED_STRIP, temperature::UNCORRECTED),
+    );
+    settings.set_color_correction(
+        Section::Second,
+        ColorCorrection::new(correction::TYPICAL_PIXEL_STRING, temperature::TUNGSTEN_100W),
+    );
+    settings
+}
+
+#[test]
+fn saved_settings_are_restored() {
+    let mut flash = RamFlash::new();
+    let saved = corrected_settings();
+    SettingsStorage::new(&mut flash, SECTOR_SIZE as u32)
+        .save(&saved)
+        .unwrap();
+
+    let mut restored = settings();
+    assert!(SettingsStorage::new(&mut flash, SECTOR_SIZE as u32).load(&mut restored));
+    for section in Section::ALL {
+        assert_eq!(
+            restored.color_correction(section),
+            saved.color_correction(section)
+        );
+    }
+}
+
+#[test]
+fn erased_flash_keeps_the_defaults() {
+    let mut flash = RamFlash::new();
+    let mut settings = settings();
+
+    assert!(!SettingsStorage::new(&mut flash, 0).load(&mut settings));
+    assert_eq!(
+        *settings.color_correction(Section::First),
+        ColorCorrection::UNCORRECTED
+    );
+}
+
+#[test]
+fn corrupted_record_is_ignored() {
+    let mut flash = RamFlash::new();
+    SettingsStorage::new(&mut flash, 0)
+        .save(&corrected_settings())
+        .unwrap();
+    flash.bytes[6] ^= 0x10;
+
+    let mut settings = settings();
+    assert!(!SettingsStorage::new(&mut flash, 0).load(&mut settings));
+    assert_eq!(
+        *settings.color_correction(Section::Second),
+        ColorCorrection::UNCORRECTED
+    );
+}
+
+#[test]
+fn unchanged_settings_are_not_written_again() {
+    let mut flash = RamFlash::new();
+    let settings = corrected_settings();
+    let mut storage = SettingsStorage::new(&mut flash, 0);
+
+    storage.save(&settings).unwrap();
+    storage.save(&settings).unwrap();
+
+    assert_eq!(flash.erases, 1);
+}
+
+#[test]
+fn saved_preset_is_restored_along_with_the_settings() {
+    let mut flash = RamFlash::new();
+    let mut storage = SettingsStorage::new(&mut flash, 0);
+    let preset = Status {
+        animation: AnimationKind::UniColorHeartbeat,
+        brightness: 77,
+        color: Color::Rgb(RGB8::new(10, 20, 30)),
+        delay: 250,
+        dithering: false,
+    };
+
+    assert_eq!(storage.load_preset(), None);
+    storage.save(&corrected_settings()).unwrap();
+    storage.save_preset(&preset).unwrap();
+
+    let mut settings = settings();
+    assert_eq!(storage.load_preset(), Some(preset));
+    assert!(storage.load(&mut settings));
+    assert_eq!(
+        *settings.color_correction(Section::First),
+        *corrected_settings().color_correction(Section::First)
+    );
+
+    assert_eq!(
+        preset.apply(&mut settings),
+        AnimationKind::UniColorHeartbeat
+    );
+    assert_eq!(Status::new(preset.animation, &settings), preset);
+}
+
+#[test]
+fn streaming_animation_is_not_saved_as_the_preset() {
+    let mut flash = RamFlash::new();
+    let mut storage = SettingsStorage::new(&mut flash, 0);
+    let status = Status::new(AnimationKind::Streaming, &settings());
+
+    assert_eq!(storage.save_preset(&status), Err(PresetError::Streaming));
+    assert_eq!(storage.load_preset(), None);
+    assert_eq!(flash.erases, 0);
+}
+
+#[test]
+fn color_correction_command_is_applied_and_left_to_save() {
+    let mut flash = RamFlash::new();
+    let mut storage = SettingsStorage::new(&mut flash, 0);
+    let mut settings = settings();
+    let color_correction = ColorCorrection::new(correction::TYPICAL_SMD5050, temperature::CANDLE);
+
+    let change = command::execute(
+        Command::ColorCorrection(Some(Section::Second), color_correction),
+        AnimationKind::Shimmer,
+        &mut settings,
+        Some(&mut storage),
+    );
+    command::execute(
+        Command::ColorCorrection(None, color_correction),
+        AnimationKind::Shimmer,
+        &mut settings,
+        Option::<&mut SettingsStorage<RamFlash>>::None,
+    );
+
+    let mut restored = self::settings();
+    assert_eq!(change, Some(Change::Settings));
+    assert_eq!(*settings.color_correction(Section::First), color_correction);
+    assert!(!storage.load(&mut restored));
+    assert_eq!(flash.erases, 0);
+}
+
+#[test]
+fn deferred_save_waits_until_the_settings_stop_changing() {
+    let mut deferred_save = DeferredSave::new();
+    let start = Instant::now();
+    let second = Duration::from_secs(1);
+
+    assert!(!deferred_save.is_due(start + SAVE_DELAY));
+    deferred_save.changed(start);
+    deferred_save.changed(start + second);
+
+    assert!(!deferred_save.is_due(start + SAVE_DELAY));
+    assert!(deferred_save.is_due(start + second + SAVE_DELAY));
+    assert!(!deferred_save.is_due(start + second * 10 + SAVE_DELAY));
+}
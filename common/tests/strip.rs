@@ -0,0 +1,134 @@
+//! Tests of the color order and of the white channel of the LED strips.
+
+use cookie_monster_common::strip::{ColorOrder, Strip, StripKind, WhiteMode};
+use core::convert::Infallible;
+use smart_leds::RGB8;
+use smart_leds_trait::{RGBW, SmartLedsWrite, White};
+
+mod support;
+
+/// A driver that records the channels it would send, in the GRB order of the WS2812 followed by the
+/// white channel.
+#[derive(Default)]
+struct RecordingDriver {
+    bytes: Vec<u8>,
+}
+
+impl SmartLedsWrite for RecordingDriver {
+    type Color = RGB8;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        for color in iterator {
+            let color = color.into();
+            self.bytes.extend([color.g, color.r, color.b]);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+struct RecordingRgbwDriver {
+    bytes: Vec<u8>,
+}
+
+impl SmartLedsWrite for RecordingRgbwDriver {
+    type Color = RGBW<u8>;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        for color in iterator {
+            let color = color.into();
+            self.bytes.extend([color.g, color.r, color.b, color.a.0]);
+        }
+        Ok(())
+    }
+}
+
+type RecordingStrip = Strip<RecordingDriver, RecordingRgbwDriver>;
+
+fn rgb_strip(order: ColorOrder) -> RecordingStrip {
+    Strip::Rgb {
+        driver: RecordingDriver::default(),
+        order,
+    }
+}
+
+fn rgbw_strip(order: ColorOrder, white_mode: WhiteMode) -> RecordingStrip {
+    Strip::Rgbw {
+        driver: RecordingRgbwDriver::default(),
+        order,
+        white_mode,
+    }
+}
+
+fn bytes(strip: &RecordingStrip) -> &[u8] {
+    match strip {
+        Strip::Rgb { driver, .. } => &driver.bytes,
+        Strip::Rgbw { driver, .. } => &driver.bytes,
+    }
+}
+
+#[test]
+fn channels_are_sent_in_the_color_order() {
+    let color = RGB8::new(1, 2, 3);
+    let orders = [
+        (ColorOrder::Rgb, [1, 2, 3]),
+        (ColorOrder::Rbg, [1, 3, 2]),
+        (ColorOrder::Grb, [2, 1, 3]),
+        (ColorOrder::Gbr, [2, 3, 1]),
+        (ColorOrder::Brg, [3, 1, 2]),
+        (ColorOrder::Bgr, [3, 2, 1]),
+    ];
+
+    for (order, expected) in orders {
+        let mut strip = rgb_strip(order);
+        strip.write([color]).unwrap();
+        assert_eq!(bytes(&strip), expected, "{order:?}");
+
+        let mut strip = rgbw_strip(order, WhiteMode::None);
+        strip.write([color]).unwrap();
+        assert_eq!(bytes(&strip)[..3], expected, "{order:?}");
+    }
+}
+
+#[test]
+fn white_is_extracted_in_each_mode() {
+    let color = RGB8::new(200, 120, 50);
+    let white = |r, g, b, w| RGBW {
+        r,
+        g,
+        b,
+        a: White(w),
+    };
+
+    assert_eq!(WhiteMode::None.extract(color), white(200, 120, 50, 0));
+    assert_eq!(WhiteMode::Accurate.extract(color), white(150, 70, 0, 50));
+    assert_eq!(WhiteMode::Brighter.extract(color), white(200, 120, 50, 50));
+    assert_eq!(WhiteMode::Max.extract(color), white(0, 0, 0, 200));
+}
+
+#[test]
+fn white_is_sent_after_the_ordered_channels() {
+    let mut strip = rgbw_strip(ColorOrder::Rgb, WhiteMode::Accurate);
+
+    strip
+        .write([RGB8::new(255, 255, 255), RGB8::new(10, 20, 30)])
+        .unwrap();
+
+    assert_eq!(bytes(&strip), [0, 0, 0, 255, 0, 10, 20, 10]);
+}
+
+#[test]
+fn kinds_have_the_channels_of_their_leds() {
+    assert_eq!(StripKind::WS2812.channels(), 3);
+    assert_eq!(StripKind::SK6812_RGBW.channels(), 4);
+}
@@ -0,0 +1,43 @@
+//! A sender and a receiving socket on the loopback interface, for the tests of the UDP protocols.
+
+use std::net::UdpSocket;
+use std::time::Duration;
+
+/// Large enough for the packets of every protocol.
+const PACKET_SIZE: usize = 4096;
+
+pub struct Loopback {
+    sender: UdpSocket,
+    socket: UdpSocket,
+}
+
+impl Loopback {
+    pub fn new() -> Self {
+        let timeout = Some(Duration::from_secs(1));
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        socket.set_read_timeout(timeout).unwrap();
+        let sender = UdpSocket::bind("127.0.0.1:0").unwrap();
+        sender.set_read_timeout(timeout).unwrap();
+        sender.connect(socket.local_addr().unwrap()).unwrap();
+
+        Self { sender, socket }
+    }
+
+    /// Sends the packet, and returns it as the socket received it.
+    pub fn send(&self, packet: &[u8]) -> Vec<u8> {
+        self.sender.send(packet).unwrap();
+        let mut buffer = [0; PACKET_SIZE];
+        let length = self.socket.recv(&mut buffer).unwrap();
+        buffer[..length].to_vec()
+    }
+
+    /// Answers the sender from the socket, and returns the answer as the sender received it.
+    pub fn answer(&self, packet: &[u8]) -> Vec<u8> {
+        self.socket
+            .send_to(packet, self.sender.local_addr().unwrap())
+            .unwrap();
+        let mut buffer = [0; PACKET_SIZE];
+        let length = self.sender.recv(&mut buffer).unwrap();
+        buffer[..length].to_vec()
+    }
+}
@@ -0,0 +1,35 @@
+//! Support code shared by the integration tests.
+//!
+//! The library logs with `defmt`, which expects the firmware to provide a global logger and a panic
+//! handler. On the host, the logs are discarded and the panics are forwarded to the test harness.
+
+// Provides the timestamp of the logs, even when nothing else uses the time.
+use embassy_time as _;
+
+#[defmt::global_logger]
+struct DiscardingLogger;
+
+unsafe impl defmt::Logger for DiscardingLogger {
+    fn acquire() {}
+
+    unsafe fn flush() {}
+
+    unsafe fn release() {}
+
+    unsafe fn write(_bytes: &[u8]) {}
+}
+
+#[defmt::panic_handler]
+fn panic() -> ! {
+    panic!("defmt panic")
+}
+
+// Each test only uses some of the helpers.
+#[allow(dead_code)]
+pub mod loopback;
+#[allow(dead_code)]
+pub mod render;
+#[allow(dead_code)]
+pub mod spi;
+#[allow(dead_code)]
+pub mod stream;
@@ -0,0 +1,109 @@
+//! The LED sections and the delay that the tests render the frames with, and the lock of the
+//! telemetry that the frames are recorded in.
+
+use core::convert::Infallible;
+#[cfg(not(feature = "benchmark"))]
+use embedded_hal_async::delay::DelayNs;
+use smart_leds::RGB8;
+use smart_leds_trait::{SmartLedsWrite, SmartLedsWriteAsync};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+#[cfg(feature = "benchmark")]
+#[allow(unused_imports)]
+pub use cookie_monster_common::benchmark::{DiscardingSection, NoDelay};
+
+/// The telemetry is global, so only one test records frames at a time.
+static TELEMETRY: Mutex<()> = Mutex::new(());
+
+pub fn lock_telemetry() -> MutexGuard<'static, ()> {
+    TELEMETRY.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A LED section whose colors are dropped, like the one of the benchmark.
+#[cfg(not(feature = "benchmark"))]
+pub struct DiscardingSection;
+
+#[cfg(not(feature = "benchmark"))]
+impl SmartLedsWrite for DiscardingSection {
+    type Color = RGB8;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        iterator.into_iter().for_each(drop);
+        Ok(())
+    }
+}
+
+/// A delay that returns immediately, like the one of the benchmark.
+#[cfg(not(feature = "benchmark"))]
+pub struct NoDelay;
+
+#[cfg(not(feature = "benchmark"))]
+impl DelayNs for NoDelay {
+    async fn delay_ns(&mut self, _ns: u32) {}
+}
+
+/// A LED section that keeps every frame written to it, and fails its first writes.
+#[derive(Default)]
+pub struct RecordingSection {
+    pub frames: Vec<Vec<RGB8>>,
+    pub failures: usize,
+}
+
+impl SmartLedsWrite for RecordingSection {
+    type Color = RGB8;
+    type Error = ();
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.frames
+            .push(iterator.into_iter().map(Into::into).collect());
+        if self.failures > 0 {
+            self.failures -= 1;
+            return Err(());
+        }
+        Ok(())
+    }
+}
+
+impl SmartLedsWriteAsync for RecordingSection {
+    type Color = RGB8;
+    type Error = ();
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        SmartLedsWrite::write(self, iterator)
+    }
+}
+
+/// A LED section that only keeps the color of its first LED.
+#[derive(Default)]
+pub struct FirstLedSection {
+    pub color: RGB8,
+}
+
+impl SmartLedsWrite for FirstLedSection {
+    type Color = RGB8;
+    type Error = Infallible;
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        let mut colors = iterator.into_iter().map(Into::into);
+        self.color = colors.next().unwrap_or_default();
+        colors.for_each(drop);
+        Ok(())
+    }
+}
@@ -0,0 +1,75 @@
+//! A SPI bus recording the bytes written to it, for the tests of the drivers.
+
+use cookie_monster_common::ws2812::buffer_size;
+use core::convert::Infallible;
+use embedded_hal::spi::{ErrorType, SpiBus};
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWrite;
+use ws2812_spi::prerendered::Ws2812;
+
+/// A SPI bus that records the bytes written to it.
+#[derive(Default)]
+pub struct RecordingSpi {
+    pub bytes: Vec<u8>,
+}
+
+impl ErrorType for RecordingSpi {
+    type Error = Infallible;
+}
+
+impl SpiBus<u8> for RecordingSpi {
+    fn read(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        self.bytes.extend_from_slice(words);
+        Ok(())
+    }
+
+    fn transfer(&mut self, _read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        self.write(write)
+    }
+
+    fn transfer_in_place(&mut self, _words: &mut [u8]) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    fn flush(&mut self) -> Result<(), Self::Error> {
+        Ok(())
+    }
+}
+
+// The async bus isn't imported, so that both traits don't provide the same methods.
+impl embedded_hal_async::spi::SpiBus<u8> for RecordingSpi {
+    async fn read(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        SpiBus::read(self, words)
+    }
+
+    async fn write(&mut self, words: &[u8]) -> Result<(), Self::Error> {
+        SpiBus::write(self, words)
+    }
+
+    async fn transfer(&mut self, read: &mut [u8], write: &[u8]) -> Result<(), Self::Error> {
+        SpiBus::transfer(self, read, write)
+    }
+
+    async fn transfer_in_place(&mut self, words: &mut [u8]) -> Result<(), Self::Error> {
+        SpiBus::transfer_in_place(self, words)
+    }
+
+    async fn flush(&mut self) -> Result<(), Self::Error> {
+        SpiBus::flush(self)
+    }
+}
+
+/// The bytes of the colors encoded by the prerendered driver of the ws2812-spi crate, with its
+/// reset.
+pub fn prerendered_rgb(colors: &[RGB8]) -> Vec<u8> {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = vec![0; buffer_size(colors.len(), 3)];
+    Ws2812::new(&mut spi, &mut buffer)
+        .write(colors.iter().copied())
+        .unwrap();
+    spi.bytes
+}
@@ -0,0 +1,35 @@
+//! The stream shown by the streaming animation, shared by the tests of the streaming protocols.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, LEDS_FIRST_SECTION, LEDS_SECOND_SECTION, LedData,
+    Settings,
+};
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::RGB16;
+use std::sync::{Mutex, MutexGuard, PoisonError};
+
+pub const LEDS_TOTAL: usize = LEDS_FIRST_SECTION + LEDS_SECOND_SECTION;
+
+/// The tests share the stream and its signals, so only one runs at a time.
+static STREAM: Mutex<()> = Mutex::new(());
+
+pub fn lock_stream() -> MutexGuard<'static, ()> {
+    STREAM.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// The color of the LEDs whose channels are all at the given level.
+pub fn gray(level: u8) -> RGB16 {
+    let level = u16::from(level) * 257;
+    RGB16::new(level, level, level)
+}
+
+/// The colors shown by the streaming animation, before their corrections.
+pub fn streamed() -> LedData {
+    let mut prng = SmallRng::seed_from_u64(0);
+    let settings = Settings::new(0, 0, 0, 4095, COLORS_TOTAL);
+    let mut animation = Animation::new(AnimationKind::Streaming, &mut prng);
+    let mut data = [RGB16::default(); LEDS_TOTAL];
+    animation.update(&mut data, &settings);
+    data
+}
@@ -0,0 +1,106 @@
+//! Tests of the frame timing statistics.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, Settings, create_data,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::telemetry;
+use cookie_monster_common::telemetry::Statistic;
+use embassy_futures::block_on;
+use embassy_time::{Duration, Instant};
+use embedded_hal_async::delay::DelayNs;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use std::thread;
+
+mod support;
+
+use support::render::{DiscardingSection, lock_telemetry};
+
+/// A delay that really waits, so that the wait is recorded as idle time.
+struct SleepingDelay;
+
+impl DelayNs for SleepingDelay {
+    async fn delay_ns(&mut self, ns: u32) {
+        thread::sleep(std::time::Duration::from_nanos(u64::from(ns)));
+    }
+}
+
+/// Updates and renders a frame of a solid animation, which waits 1 ms for the next frame.
+fn run_frame() {
+    let settings = Settings::new(0, 0, 0, 4095, COLORS_TOTAL);
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    let mut data = create_data();
+    animation.update(&mut data, &settings);
+
+    block_on(animation.render(
+        &data,
+        &mut DiscardingSection,
+        &mut DiscardingSection,
+        &mut Dithering::new(),
+        &mut SleepingDelay,
+        &settings,
+    ))
+    .unwrap();
+}
+
+#[test]
+fn statistic_accumulates_min_max_and_mean() {
+    let mut statistic = Statistic::new();
+    let empty = statistic;
+
+    for micros in [300, 100, 200, 600] {
+        statistic.record(Duration::from_micros(micros));
+    }
+
+    assert_eq!(empty.count(), 0);
+    assert_eq!(empty.min(), Duration::MIN);
+    assert_eq!(empty.average(), Duration::MIN);
+    assert_eq!(statistic.count(), 4);
+    assert_eq!(statistic.min(), Duration::from_micros(100));
+    assert_eq!(statistic.max(), Duration::from_micros(600));
+    assert_eq!(statistic.average(), Duration::from_micros(300));
+}
+
+#[test]
+fn statistics_are_reset_at_the_end_of_the_period() {
+    let _telemetry = lock_telemetry();
+    let start = Instant::now();
+    telemetry::reset(start);
+
+    run_frame();
+    run_frame();
+    telemetry::report_if_due(AnimationKind::UniColorSolid, start + Duration::from_secs(9));
+    let during = telemetry::snapshot(start + Duration::from_secs(9));
+    let end = start + Duration::from_secs(10);
+    telemetry::report_if_due(AnimationKind::UniColorSolid, end);
+    let after = telemetry::snapshot(end);
+
+    assert_eq!(during.frames(), 2);
+    assert_eq!(during.idle().count(), 2);
+    assert_eq!(during.elapsed(), Duration::from_secs(9));
+    assert_eq!(after.frames(), 0);
+    assert_eq!(after.idle().count(), 0);
+    assert_eq!(after.elapsed(), Duration::MIN);
+}
+
+#[test]
+fn busy_percentage_compares_the_idle_time_with_the_period() {
+    let _telemetry = lock_telemetry();
+    let start = Instant::now();
+    telemetry::reset(start);
+
+    let empty = telemetry::snapshot(start);
+    let busy = telemetry::snapshot(start + Duration::from_secs(1));
+    run_frame();
+    // The frame waited longer than the period, so all of it was idle.
+    let idle = telemetry::snapshot(start + Duration::from_micros(500));
+
+    assert_eq!(empty.busy_percentage(), 0, "no time elapsed");
+    assert_eq!(empty.frame_rate(), 0);
+    assert_eq!(busy.busy_percentage(), 100);
+    assert_eq!(idle.busy_percentage(), 0);
+}
@@ -0,0 +1,190 @@
+//! Tests of the TPM2 parser and the TPM2.net assembler, from captured packets to the frame of the
+//! streaming animation.
+
+use cookie_monster_common::console::Console;
+use cookie_monster_common::tpm2::{NetAssembler, PACKET_TIMEOUT, PacketError, Parser};
+use embassy_time::{Duration, Instant};
+use smart_leds::RGB16;
+
+mod support;
+
+use support::stream::{lock_stream, streamed};
+
+/// A data packet of 4 LEDs, red, green, blue and a dim gray, as sent by Jinx! over a serial port.
+const SERIAL_PACKET: [u8; 17] = [
+    0xC9, 0xDA, 0x00, 0x0C, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x10, 0x20, 0x30,
+    0x36,
+];
+
+/// The same frame split across two TPM2.net packets, in the middle of the green LED.
+const NET_PACKETS: [&[u8]; 2] = [
+    &[
+        0x9C, 0xDA, 0x00, 0x07, 0x01, 0x02, 0xFF, 0x00, 0x00, 0x00, 0xFF, 0x00, 0x00, 0x36,
+    ],
+    &[
+        0x9C, 0xDA, 0x00, 0x05, 0x02, 0x02, 0x00, 0xFF, 0x10, 0x20, 0x30, 0x36,
+    ],
+];
+
+/// The colors of the LEDs of the captured frames.
+const CAPTURED_COLORS: [RGB16; 4] = [
+    RGB16::new(0xFFFF, 0, 0),
+    RGB16::new(0, 0xFFFF, 0),
+    RGB16::new(0, 0, 0xFFFF),
+    RGB16::new(0x1010, 0x2020, 0x3030),
+];
+
+/// Sends the bytes to the parser at the given time, and returns whether each belongs to a packet.
+fn parse(parser: &mut Parser, bytes: &[u8], now: Instant) -> Vec<bool> {
+    bytes
+        .iter()
+        .map(|&byte| parser.receive(byte, now))
+        .collect()
+}
+
+/// Shows a black frame of 4 LEDs, so that the test starts from a known frame.
+fn show_black() {
+    let mut packet = SERIAL_PACKET;
+    packet[4..16].fill(0);
+    parse(&mut Parser::new(), &packet, Instant::now());
+}
+
+#[test]
+fn serial_packet_is_shown() {
+    let _stream = lock_stream();
+    show_black();
+
+    let consumed = parse(&mut Parser::new(), &SERIAL_PACKET, Instant::now());
+
+    assert!(consumed.iter().all(|&consumed| consumed));
+    assert_eq!(streamed()[..4], CAPTURED_COLORS);
+}
+
+#[test]
+fn serial_packet_without_its_end_byte_is_not_shown() {
+    let _stream = lock_stream();
+    show_black();
+    let mut packet = SERIAL_PACKET;
+    packet[16] = 0x00;
+
+    let mut parser = Parser::new();
+    parse(&mut parser, &packet, Instant::now());
+
+    assert_eq!(streamed()[..4], [RGB16::default(); 4]);
+    assert!(!parser.is_receiving());
+}
+
+#[test]
+fn serial_command_packet_is_skipped() {
+    let _stream = lock_stream();
+    show_black();
+    let mut parser = Parser::new();
+    let now = Instant::now();
+
+    let command = parse(&mut parser, &[0xC9, 0xC0, 0x00, 0x01, 0xAA, 0x36], now);
+    let after = parse(&mut parser, b"next", now);
+
+    assert!(command.iter().all(|&consumed| consumed));
+    assert_eq!(after, [false; 4]);
+    assert_eq!(streamed()[..4], [RGB16::default(); 4]);
+}
+
+#[test]
+fn interrupted_serial_packet_is_discarded_after_the_timeout() {
+    let _stream = lock_stream();
+    show_black();
+    let mut parser = Parser::new();
+    let start = Instant::now();
+
+    parse(&mut parser, &SERIAL_PACKET[..8], start);
+    let later = start + PACKET_TIMEOUT + Duration::from_millis(1);
+    let consumed = parse(&mut parser, &SERIAL_PACKET, later);
+
+    assert!(consumed.iter().all(|&consumed| consumed));
+    assert_eq!(streamed()[..4], CAPTURED_COLORS);
+}
+
+#[test]
+fn console_streams_packets_between_lines() {
+    let _stream = lock_stream();
+    show_black();
+    let mut console = Console::new();
+    // The payload contains the end of a line and the start of a binary frame.
+    let mut packet = SERIAL_PACKET.to_vec();
+    packet[4..7].copy_from_slice(b"\n\0\r");
+    let mut bytes = b"help\n".to_vec();
+    bytes.extend(&packet);
+    bytes.extend(b"help\n");
+
+    let replies: Vec<_> = bytes
+        .iter()
+        .filter_map(|&byte| console.receive(byte).map(<[u8]>::to_vec))
+        .collect();
+
+    assert_eq!(replies.len(), 2);
+    assert!(replies.iter().all(|reply| reply.starts_with(b"commands: ")));
+    assert_eq!(streamed()[0], RGB16::new(0x0A0A, 0, 0x0D0D));
+}
+
+#[test]
+fn net_packets_are_assembled_into_a_frame() {
+    let _stream = lock_stream();
+    show_black();
+    let mut assembler = NetAssembler::new();
+    let now = Instant::now();
+
+    assert_eq!(assembler.receive(NET_PACKETS[0], now), Ok(()));
+    let partial = streamed();
+    assert_eq!(assembler.receive(NET_PACKETS[1], now), Ok(()));
+
+    assert_eq!(partial[..4], [RGB16::default(); 4]);
+    assert_eq!(streamed()[..4], CAPTURED_COLORS);
+}
+
+#[test]
+fn net_frame_missing_a_packet_is_dropped() {
+    let _stream = lock_stream();
+    show_black();
+    let mut assembler = NetAssembler::new();
+    let now = Instant::now();
+
+    let missing_first = assembler.receive(NET_PACKETS[1], now);
+    let repeated_first = [
+        assembler.receive(NET_PACKETS[0], now),
+        assembler.receive(NET_PACKETS[0], now),
+    ];
+
+    assert_eq!(missing_first, Err(PacketError::MissingPacket));
+    assert_eq!(repeated_first, [Ok(()), Ok(())]);
+    assert_eq!(
+        assembler.receive(NET_PACKETS[1], now),
+        Ok(()),
+        "the frame restarts with its first packet"
+    );
+    assert_eq!(streamed()[..4], CAPTURED_COLORS);
+}
+
+#[test]
+fn invalid_net_packets_are_rejected() {
+    let mut assembler = NetAssembler::new();
+    let now = Instant::now();
+    let with = |index: usize, byte: u8| {
+        let mut packet = NET_PACKETS[0].to_vec();
+        packet[index] = byte;
+        packet
+    };
+
+    let packets = [
+        (NET_PACKETS[0][..5].to_vec(), PacketError::TooShort),
+        (with(0, 0xC9), PacketError::InvalidStart),
+        (with(3, 0x08), PacketError::InvalidSize),
+        (NET_PACKETS[0][..13].to_vec(), PacketError::InvalidSize),
+        (with(13, 0x00), PacketError::InvalidEnd),
+        (with(4, 0x00), PacketError::InvalidPacketNumber),
+        (with(4, 0x03), PacketError::InvalidPacketNumber),
+    ];
+
+    for (packet, error) in packets {
+        assert_eq!(assembler.receive(&packet, now), Err(error), "{packet:02x?}");
+    }
+}
@@ -0,0 +1,139 @@
+//! Tests of the policies applied when the colors can't be written to the LEDs.
+
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_TOTAL, Section, Settings, create_data,
+};
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::telemetry;
+use cookie_monster_common::write_error::{
+    WriteError, WriteErrorAction, WriteErrorHandler, WriteErrorPolicy,
+};
+use embassy_futures::block_on;
+use embassy_time::Instant;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+use smart_leds::RGB8;
+use smart_leds_trait::SmartLedsWrite;
+
+mod support;
+
+use support::render::{NoDelay, lock_telemetry};
+
+/// A LED section that fails every write when it's broken.
+#[derive(Default)]
+struct FlakySection {
+    broken: bool,
+}
+
+impl SmartLedsWrite for FlakySection {
+    type Color = RGB8;
+    type Error = ();
+
+    fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        iterator.into_iter().for_each(drop);
+        if self.broken { Err(()) } else { Ok(()) }
+    }
+}
+
+/// Renders a frame of a solid animation to the two sections.
+fn render(section_1: &mut FlakySection, section_2: &mut FlakySection) -> Result<(), WriteError> {
+    let settings = Settings::new(0, 1, 1, 1, COLORS_TOTAL);
+    let mut animation = Animation::new(
+        AnimationKind::UniColorSolid,
+        &mut SmallRng::seed_from_u64(0),
+    );
+    let mut data = create_data();
+    animation.update(&mut data, &settings);
+
+    block_on(animation.render(
+        &data,
+        section_1,
+        section_2,
+        &mut Dithering::new(),
+        &mut NoDelay,
+        &settings,
+    ))
+}
+
+#[test]
+fn failing_section_is_reported_and_counted() {
+    let _telemetry = lock_telemetry();
+    let mut section_1 = FlakySection::default();
+    let mut section_2 = FlakySection { broken: true };
+
+    telemetry::reset(Instant::now());
+    let error = render(&mut section_1, &mut section_2).unwrap_err();
+
+    assert!(!error.failed(Section::First));
+    assert!(error.failed(Section::Second));
+    let statistics = telemetry::snapshot(Instant::now());
+    assert_eq!(statistics.write_errors(Section::First), 0);
+    assert_eq!(statistics.write_errors(Section::Second), 1);
+    assert_eq!(statistics.write(Section::First).count(), 1);
+}
+
+#[test]
+fn retry_policy_retries_then_skips_the_frame() {
+    let _telemetry = lock_telemetry();
+    let mut handler = WriteErrorHandler::new(WriteErrorPolicy::Retry(2));
+    let mut section = FlakySection { broken: true };
+
+    for _ in 0..2 {
+        let result = render(&mut FlakySection::default(), &mut section);
+        assert_eq!(handler.handle(result), WriteErrorAction::Retry);
+    }
+    let result = render(&mut FlakySection::default(), &mut section);
+    assert_eq!(handler.handle(result), WriteErrorAction::NextFrame);
+
+    // The retries start over with the next frame.
+    let result = render(&mut FlakySection::default(), &mut section);
+    assert_eq!(handler.handle(result), WriteErrorAction::Retry);
+}
+
+#[test]
+fn skip_frame_policy_carries_on() {
+    let _telemetry = lock_telemetry();
+    let mut handler = WriteErrorHandler::new(WriteErrorPolicy::SkipFrame);
+
+    for _ in 0..10 {
+        let result = render(
+            &mut FlakySection { broken: true },
+            &mut FlakySection::default(),
+        );
+        assert_eq!(handler.handle(result), WriteErrorAction::NextFrame);
+    }
+}
+
+#[test]
+fn reinitialize_policy_reinitializes_after_consecutive_failures() {
+    let _telemetry = lock_telemetry();
+    let mut handler = WriteErrorHandler::new(WriteErrorPolicy::Reinitialize(3));
+    let mut section_1 = FlakySection { broken: true };
+    let mut section_2 = FlakySection::default();
+
+    for _ in 0..2 {
+        let result = render(&mut section_1, &mut section_2);
+        assert_eq!(handler.handle(result), WriteErrorAction::NextFrame);
+    }
+
+    // A successful frame resets the count of consecutive failures.
+    section_1.broken = false;
+    let result = render(&mut section_1, &mut section_2);
+    assert_eq!(handler.handle(result), WriteErrorAction::NextFrame);
+    section_1.broken = true;
+
+    for _ in 0..2 {
+        let result = render(&mut section_1, &mut section_2);
+        assert_eq!(handler.handle(result), WriteErrorAction::NextFrame);
+    }
+    let result = render(&mut section_1, &mut section_2);
+    let WriteErrorAction::Reinitialize(error) = handler.handle(result) else {
+        panic!("the first section should be reinitialized");
+    };
+    assert!(error.failed(Section::First));
+    assert!(!error.failed(Section::Second));
+}
@@ -0,0 +1,61 @@
+//! Tests of the prerendered driver of the WS2812 and SK6812 strips, which both boards use by
+//! default.
+
+use cookie_monster_common::ws2812::{Error, Ws2812, frame_size};
+use embassy_futures::block_on;
+use proptest::prelude::*;
+use smart_leds::RGB8;
+use smart_leds_trait::{RGBW, SmartLedsWriteAsync, White};
+
+mod support;
+
+use support::spi::{RecordingSpi, prerendered_rgb};
+
+#[test]
+fn prerendered_buffer_must_hold_the_reset() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; frame_size(2, 3) - 1];
+    let mut ws2812 = Ws2812::<_, RGB8>::new(&mut spi, &mut buffer);
+
+    let result = block_on(ws2812.write([RGB8::default(); 2]));
+
+    assert!(matches!(result, Err(Error::OutOfBounds)));
+    assert!(spi.bytes.is_empty());
+}
+
+#[test]
+fn prerendered_rgbw_frame_is_sent_at_once() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; frame_size(1, 4)];
+    let white = RGBW {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: White(0xFF),
+    };
+
+    let mut ws2812 = Ws2812::<_, RGBW<u8>>::new(&mut spi, &mut buffer);
+
+    block_on(ws2812.write([white])).unwrap();
+
+    assert_eq!(spi.bytes.len(), frame_size(1, 4));
+    assert_eq!(spi.bytes[12..16], [0b1110_1110; 4]);
+    assert!(spi.bytes[16..].iter().all(|byte| *byte == 0));
+}
+
+proptest! {
+    #[test]
+    fn prerendered_frames_match_the_ones_of_ws2812_spi(
+        colors in prop::collection::vec(any::<(u8, u8, u8)>(), 0..40),
+    ) {
+        let colors: Vec<_> = colors.into_iter().map(|(r, g, b)| RGB8::new(r, g, b)).collect();
+        let mut spi = RecordingSpi::default();
+        let mut buffer = vec![0; frame_size(colors.len(), 3)];
+
+        let mut ws2812 = Ws2812::<_, RGB8>::new(&mut spi, &mut buffer);
+
+        block_on(ws2812.write(colors.iter().copied())).unwrap();
+
+        prop_assert_eq!(spi.bytes, prerendered_rgb(&colors));
+    }
+}
@@ -0,0 +1,112 @@
+//! Tests of the streaming driver of the WS2812 and SK6812 strips.
+
+use cookie_monster_common::ws2812::{
+    ChunkBus, Error, PingPongChunks, StreamingWs2812, buffer_size,
+};
+use core::convert::Infallible;
+use embassy_futures::block_on;
+use proptest::prelude::*;
+use smart_leds::RGB8;
+use smart_leds_trait::{RGBW, SmartLedsWriteAsync, White};
+
+mod support;
+
+use support::spi::{RecordingSpi, prerendered_rgb};
+
+/// A bus with two buffers taking turns, which records the chunks it sends.
+struct PingPongBus {
+    buffers: [Vec<u8>; 2],
+    next: usize,
+    chunks: Vec<(usize, Vec<u8>)>,
+}
+
+impl PingPongBus {
+    fn new(size: usize) -> Self {
+        Self {
+            buffers: [vec![0; size], vec![0; size]],
+            next: 0,
+            chunks: Vec::new(),
+        }
+    }
+}
+
+impl ChunkBus for PingPongBus {
+    type Error = Infallible;
+
+    fn buffer(&mut self) -> &mut [u8] {
+        &mut self.buffers[self.next]
+    }
+
+    async fn send(
+        &mut self, length: usize, encode: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Result<usize, Self::Error> {
+        self.chunks
+            .push((self.next, self.buffers[self.next][..length].to_vec()));
+        self.next = 1 - self.next;
+        Ok(encode(&mut self.buffers[self.next]))
+    }
+}
+
+#[test]
+fn chunks_hold_whole_leds_in_turns() {
+    let mut bus = PingPongBus::new(buffer_size(2, 3) + 1);
+
+    block_on(StreamingWs2812::<_, RGB8>::new(&mut bus).write([RGB8::new(255, 0, 0); 5])).unwrap();
+
+    let lengths: Vec<_> = bus.chunks.iter().map(|(_, chunk)| chunk.len()).collect();
+    assert_eq!(lengths[..3], [24, 24, 12]);
+    assert!(
+        bus.chunks
+            .windows(2)
+            .all(|chunks| chunks[0].0 != chunks[1].0)
+    );
+}
+
+#[test]
+fn rgbw_leds_take_four_channels() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; 2 * buffer_size(1, 4)];
+    let white = RGBW {
+        r: 0,
+        g: 0,
+        b: 0,
+        a: White(0xFF),
+    };
+
+    let mut ws2812 =
+        StreamingWs2812::<_, RGBW<u8>>::new(PingPongChunks::new(&mut spi, &mut buffer));
+
+    block_on(ws2812.write([white])).unwrap();
+
+    assert_eq!(spi.bytes[..12], [0b1000_1000; 12]);
+    assert_eq!(spi.bytes[12..16], [0b1110_1110; 4]);
+}
+
+#[test]
+fn buffer_smaller_than_a_led_is_an_error() {
+    let mut spi = RecordingSpi::default();
+    let mut buffer = [0; 2 * buffer_size(1, 3) - 1];
+    let mut ws2812 = StreamingWs2812::<_, RGB8>::new(PingPongChunks::new(&mut spi, &mut buffer));
+
+    let result = block_on(ws2812.write([RGB8::default()]));
+
+    assert!(matches!(result, Err(Error::OutOfBounds)));
+    assert!(spi.bytes.is_empty());
+}
+
+proptest! {
+    #[test]
+    fn streamed_frames_match_the_prerendered_ones(
+        colors in prop::collection::vec(any::<(u8, u8, u8)>(), 0..40),
+        chunk_leds in 1_usize..8,
+    ) {
+        let colors: Vec<_> = colors.into_iter().map(|(r, g, b)| RGB8::new(r, g, b)).collect();
+        let mut spi = RecordingSpi::default();
+        let mut buffer = vec![0; 2 * buffer_size(chunk_leds, 3)];
+        let chunks = PingPongChunks::new(&mut spi, &mut buffer);
+
+        block_on(StreamingWs2812::<_, RGB8>::new(chunks).write(colors.iter().copied())).unwrap();
+
+        prop_assert_eq!(spi.bytes, prerendered_rgb(&colors));
+    }
+}
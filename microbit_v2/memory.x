@@ -1,6 +1,7 @@
 MEMORY
 {
   /* NOTE 1 K = 1 KiBi = 1024 bytes */
-  FLASH : ORIGIN = 0x00000000, LENGTH = 512K
+  /* The last 4K page of the flash is reserved for the settings */
+  FLASH : ORIGIN = 0x00000000, LENGTH = 508K
   RAM : ORIGIN = 0x20000000, LENGTH = 128K
 }
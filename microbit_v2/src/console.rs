@@ -0,0 +1,78 @@
+use cookie_monster_common::console::Console;
+use defmt::{error, info};
+use embassy_nrf::buffered_uarte::{BufferedUarte, Error, InterruptHandler};
+use embassy_nrf::gpio::AnyPin;
+use embassy_nrf::peripherals::{PPI_CH0, PPI_CH1, PPI_GROUP0, TIMER1, UARTE0};
+use embassy_nrf::uarte::Config;
+use embassy_nrf::{Peri, bind_interrupts};
+
+bind_interrupts!(struct Irqs {
+    UARTE0 => InterruptHandler<UARTE0>;
+});
+
+/// The peripherals of the UART of the interface chip, which is the serial port of the USB.
+pub(crate) struct SerialConfig<'a> {
+    pub uarte: Peri<'a, UARTE0>,
+    pub timer: Peri<'a, TIMER1>,
+    pub ppi_channel_1: Peri<'a, PPI_CH0>,
+    pub ppi_channel_2: Peri<'a, PPI_CH1>,
+    pub ppi_group: Peri<'a, PPI_GROUP0>,
+    pub rx_pin: Peri<'a, AnyPin>,
+    pub tx_pin: Peri<'a, AnyPin>,
+}
+
+/// Task that answers the lines of the text console, received over the serial port of the USB at
+/// 115200 bauds.
+///
+/// The bytes are buffered while the other tasks run, so that a line sent at once isn't lost.
+#[embassy_executor::task]
+pub async fn console_task(serial_config: SerialConfig<'static>) {
+    info!("Starting console task...");
+
+    let mut rx_buffer = [0; 64];
+    let mut tx_buffer = [0; 64];
+    let mut uart = BufferedUarte::new(
+        serial_config.uarte,
+        serial_config.timer,
+        serial_config.ppi_channel_1,
+        serial_config.ppi_channel_2,
+        serial_config.ppi_group,
+        serial_config.rx_pin,
+        serial_config.tx_pin,
+        Irqs,
+        Config::default(),
+        &mut rx_buffer,
+        &mut tx_buffer,
+    );
+
+    let mut console = Console::new();
+    let mut buffer = [0; 16];
+
+    loop {
+        let length = match uart.read(&mut buffer).await {
+            Ok(length) => length,
+            Err(e) => {
+                error!("Cannot read from the console: {}", e);
+                continue;
+            }
+        };
+
+        for &byte in &buffer[..length] {
+            if let Some(reply) = console.receive(byte)
+                && let Err(e) = write_all(&mut uart, reply).await
+            {
+                error!("Cannot write to the console: {}", e);
+            }
+        }
+    }
+}
+
+/// Writes all the bytes, waiting for room in the buffer.
+async fn write_all(uart: &mut BufferedUarte<'_>, mut bytes: &[u8]) -> Result<(), Error> {
+    while !bytes.is_empty() {
+        let written = uart.write(bytes).await?;
+        bytes = &bytes[written..];
+    }
+
+    Ok(())
+}
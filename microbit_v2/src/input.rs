@@ -1,11 +1,13 @@
 use cookie_monster_common::input::process_analog_sensors;
-use cookie_monster_common::signal::{ANIMATION_CHANGED_SIGNAL, COLOR_CHANGED_SIGNAL};
+use cookie_monster_common::signal::{
+    ANIMATION_CHANGED_SIGNAL, COLOR_CHANGED_SIGNAL, DITHERING_TOGGLED_SIGNAL,
+};
 use defmt::{debug, error, info};
 use embassy_nrf::gpio::{AnyPin, Input, Pull};
 use embassy_nrf::peripherals::SAADC;
 use embassy_nrf::saadc::{AnyInput, ChannelConfig, Config, Saadc};
 use embassy_nrf::{Peri, bind_interrupts, saadc};
-use embassy_time::Delay;
+use embassy_time::{Delay, Duration, with_timeout};
 use embedded_hal_async::delay::DelayNs;
 
 bind_interrupts!(struct Irqs {
@@ -19,6 +21,7 @@ pub(crate) const ANALOG_MAXIMUM_VALUE: u16 = 2u16.pow(ADC_RESOLUTION) - 1;
 const ADC_RESOLUTION: u32 = 12;
 const ANALOG_READ_FREQUENCY_MILLISECONDS: u32 = 500;
 const DEBOUNCE_PERIOD_MILLISECONDS: u32 = 50;
+const HOLD_PERIOD_MILLISECONDS: u64 = 1000;
 
 /// Task that reads analog sensors (potentiometers) to signal the brightness and delay values.
 ///
@@ -75,7 +78,8 @@ pub async fn animation_button_task(button: Peri<'static, AnyPin>) {
     }
 }
 
-/// Task that waits for a button to be pressed to signal a color change.
+/// Task that waits for a button to be pressed to signal a color change, or held to toggle the
+/// dithering.
 #[embassy_executor::task]
 pub async fn color_button_task(button: Peri<'static, AnyPin>) {
     info!("Starting color button task...");
@@ -83,10 +87,17 @@ pub async fn color_button_task(button: Peri<'static, AnyPin>) {
     let mut button = Input::new(button, Pull::Up);
 
     loop {
-        perform_when_button_pressed(&mut button, || async {
-            COLOR_CHANGED_SIGNAL.signal(());
-            info!("Color change signaled");
-        })
+        perform_when_button_pressed_or_held(
+            &mut button,
+            || async {
+                COLOR_CHANGED_SIGNAL.signal(());
+                info!("Color change signaled");
+            },
+            || async {
+                DITHERING_TOGGLED_SIGNAL.signal(());
+                info!("Dithering toggle signaled");
+            },
+        )
         .await;
     }
 }
@@ -153,3 +164,41 @@ where
     // If button_pin.is_high() here, it means it was a very short bounce that didn't settle, so we
     // simply loop and wait for the next falling edge.
 }
+
+/// Executes the first action when the button is pressed and released, or the second one when it's
+/// held for [`HOLD_PERIOD_MILLISECONDS`].
+///
+/// It handles debouncing the same way as [`perform_when_button_pressed`], but the press is only
+/// known once the button is released or held long enough.
+async fn perform_when_button_pressed_or_held<F, Fut, G, Gut>(
+    button: &mut Input<'_>, pressed_action: F, held_action: G,
+) where
+    F: FnOnce() -> Fut,
+    Fut: Future<Output = ()>,
+    G: FnOnce() -> Gut,
+    Gut: Future<Output = ()>,
+{
+    debug!("Waiting for button press...");
+
+    let mut delay = Delay;
+
+    button.wait_for_falling_edge().await;
+    delay.delay_ms(DEBOUNCE_PERIOD_MILLISECONDS).await;
+
+    if button.is_low() {
+        let hold_period = Duration::from_millis(HOLD_PERIOD_MILLISECONDS);
+        if with_timeout(hold_period, button.wait_for_rising_edge())
+            .await
+            .is_ok()
+        {
+            debug!("Button pressed!");
+            pressed_action().await;
+        } else {
+            debug!("Button held!");
+            held_action().await;
+            button.wait_for_rising_edge().await;
+        }
+
+        delay.delay_ms(DEBOUNCE_PERIOD_MILLISECONDS).await;
+    }
+}
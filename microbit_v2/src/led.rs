@@ -1,80 +1,301 @@
+#[cfg(feature = "pwm")]
+use crate::pwm;
+#[cfg(feature = "pwm")]
+use crate::pwm::PwmWs2812;
+#[cfg(any(not(feature = "streaming"), feature = "apa102-first-section"))]
+use cookie_monster_common::animations::LEDS_FIRST_SECTION;
+#[cfg(any(not(feature = "streaming"), feature = "apa102-second-section"))]
+use cookie_monster_common::animations::LEDS_SECOND_SECTION;
 use cookie_monster_common::animations::{
-    Animation, AnimationKind, COLORS_INDEX_DEFAULT, COLORS_TOTAL, LEDS_FIRST_SECTION,
-    LEDS_SECOND_SECTION, Settings, create_data, reset_data,
+    Animation, AnimationKind, COLORS_INDEX_DEFAULT, COLORS_TOTAL, Section, Settings,
 };
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+use cookie_monster_common::apa102;
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+use cookie_monster_common::apa102::Apa102;
+use cookie_monster_common::command;
+use cookie_monster_common::command::Change;
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::gamma::Gamma;
+use cookie_monster_common::pipeline::Pipeline;
 use cookie_monster_common::signal::{
-    ANIMATION_CHANGED_SIGNAL, BRIGHTNESS_READ_SIGNAL, COLOR_CHANGED_SIGNAL, DELAY_READ_SIGNAL,
+    ANIMATION_CHANGED_SIGNAL, BRIGHTNESS_READ_SIGNAL, COLOR_CHANGED_SIGNAL, COMMAND_CHANNEL,
+    DELAY_READ_SIGNAL, DITHERING_TOGGLED_SIGNAL,
 };
-use defmt::{debug, info};
+use cookie_monster_common::storage::{DeferredSave, SettingsStorage};
+use cookie_monster_common::stream::StreamSwitch;
+use cookie_monster_common::strip::{Strip, StripKind};
+use cookie_monster_common::telemetry;
+use cookie_monster_common::write_error::{WriteErrorAction, WriteErrorHandler, WriteErrorPolicy};
+#[cfg(not(any(
+    feature = "pwm",
+    all(feature = "apa102-first-section", feature = "apa102-second-section")
+)))]
+use cookie_monster_common::ws2812;
+#[cfg(not(any(feature = "pwm", feature = "streaming")))]
+use cookie_monster_common::ws2812::Ws2812;
+#[cfg(feature = "streaming")]
+use cookie_monster_common::ws2812::{PingPongChunks, StreamingWs2812};
+#[cfg(feature = "pwm")]
+use defmt::unwrap;
+use defmt::{debug, error, info};
 use embassy_nrf::gpio::AnyPin;
-use embassy_nrf::peripherals::{RNG, SPI2, SPI3};
+#[cfg(not(feature = "pwm"))]
+use embassy_nrf::interrupt::typelevel::Binding;
+use embassy_nrf::nvmc::Nvmc;
+use embassy_nrf::peripherals::{NVMC, RNG};
+#[cfg(feature = "pwm")]
+use embassy_nrf::peripherals::{PWM0, PWM1};
+#[cfg(not(feature = "pwm"))]
+use embassy_nrf::peripherals::{SPI2, SPI3};
+#[cfg(feature = "pwm")]
+use embassy_nrf::pwm::SequencePwm;
 use embassy_nrf::rng::Rng;
-use embassy_nrf::spim::{Config, Frequency, Instance, Spim};
-use embassy_nrf::{Peri, bind_interrupts, rng, spim};
-use embassy_time::Delay;
+#[cfg(not(feature = "pwm"))]
+use embassy_nrf::spim;
+#[cfg(not(feature = "pwm"))]
+use embassy_nrf::spim::{Config, Frequency, Spim};
+use embassy_nrf::{Peri, bind_interrupts, rng};
+use embassy_time::{Delay, Instant};
 use rand::SeedableRng;
 use rand::rngs::SmallRng;
-use ws2812_spi::prerendered::Ws2812;
+use smart_leds_trait::{RGB8, RGBW};
 
 bind_interrupts!(struct Irqs {
     RNG => rng::InterruptHandler<RNG>;
+    #[cfg(not(feature = "pwm"))]
     SPI2 => spim::InterruptHandler<SPI2>;
+    #[cfg(not(feature = "pwm"))]
     SPIM3 => spim::InterruptHandler<SPI3>;
 });
 
-// 12 is calculated by knowing that we're using 8 bits per color (3 bytes total), and that
-// ws2812_spi converts each color byte to 4 SPI bytes.
-const FIRST_SECTION_BUFFERS_SIZE: usize = LEDS_FIRST_SECTION * 12;
+#[cfg(all(
+    feature = "pwm",
+    any(
+        feature = "apa102-first-section",
+        feature = "apa102-second-section",
+        feature = "streaming"
+    )
+))]
+compile_error!(
+    "The `pwm` feature only drives the WS2812 and SK6812 strips, without the SPI instances"
+);
 
-// 12 is calculated by knowing that we're using 8 bits per color (3 bytes total), and that
-// ws2812_spi converts each color byte to 4 SPI bytes.
-const SECOND_SECTION_BUFFERS_SIZE: usize = LEDS_SECOND_SECTION * 12;
+#[cfg(all(feature = "write-error-retry", feature = "write-error-skip-frame"))]
+compile_error!(
+    "Only one of the `write-error-retry` and `write-error-skip-frame` features can be enabled"
+);
 
-pub(crate) struct SpiConfig<'a, T: Instance> {
+// The strip of each section. SK6812 RGBW strips use `StripKind::Rgbw`, and strips that don't take
+// their channels in the GRB order of the WS2812 set their own `ColorOrder`.
+const FIRST_SECTION_STRIP: StripKind = StripKind::WS2812;
+const SECOND_SECTION_STRIP: StripKind = StripKind::WS2812;
+
+// The LEDs encoded at once by the streaming driver, whose buffers only hold two chunks of them: one
+// is sent while the other is encoded.
+#[cfg(all(
+    feature = "streaming",
+    not(all(feature = "apa102-first-section", feature = "apa102-second-section"))
+))]
+const CHUNK_LEDS: usize = 16;
+
+#[cfg(not(any(
+    feature = "apa102-first-section",
+    feature = "pwm",
+    feature = "streaming"
+)))]
+const FIRST_SECTION_BUFFERS_SIZE: usize =
+    ws2812::frame_size(LEDS_FIRST_SECTION, FIRST_SECTION_STRIP.channels());
+#[cfg(feature = "pwm")]
+const FIRST_SECTION_BUFFERS_SIZE: usize =
+    pwm::buffer_size(LEDS_FIRST_SECTION, FIRST_SECTION_STRIP.channels());
+#[cfg(feature = "apa102-first-section")]
+const FIRST_SECTION_BUFFERS_SIZE: usize = apa102::buffer_size(LEDS_FIRST_SECTION);
+#[cfg(all(feature = "streaming", not(feature = "apa102-first-section")))]
+const FIRST_SECTION_BUFFERS_SIZE: usize =
+    2 * ws2812::buffer_size(CHUNK_LEDS, FIRST_SECTION_STRIP.channels());
+
+#[cfg(not(any(
+    feature = "apa102-second-section",
+    feature = "pwm",
+    feature = "streaming"
+)))]
+const SECOND_SECTION_BUFFERS_SIZE: usize =
+    ws2812::frame_size(LEDS_SECOND_SECTION, SECOND_SECTION_STRIP.channels());
+#[cfg(feature = "pwm")]
+const SECOND_SECTION_BUFFERS_SIZE: usize =
+    pwm::buffer_size(LEDS_SECOND_SECTION, SECOND_SECTION_STRIP.channels());
+#[cfg(feature = "apa102-second-section")]
+const SECOND_SECTION_BUFFERS_SIZE: usize = apa102::buffer_size(LEDS_SECOND_SECTION);
+#[cfg(all(feature = "streaming", not(feature = "apa102-second-section")))]
+const SECOND_SECTION_BUFFERS_SIZE: usize =
+    2 * ws2812::buffer_size(CHUNK_LEDS, SECOND_SECTION_STRIP.channels());
+
+// A write only fails when the SPI or PWM transfer does, which usually won't recover by itself. By
+// default, the driver is recreated after a few consecutive failures instead of retrying the same
+// frame. The `write-error-retry` and `write-error-skip-frame` features select the other policies.
+#[cfg(not(any(feature = "write-error-retry", feature = "write-error-skip-frame")))]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::Reinitialize(10);
+#[cfg(feature = "write-error-retry")]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::Retry(3);
+#[cfg(feature = "write-error-skip-frame")]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::SkipFrame;
+
+// The last page of the flash, which is left out of the program in `memory.x`.
+const SETTINGS_OFFSET: u32 = 0x7F000;
+
+// The gamma curves are generated at build time and kept in the flash. Strips whose channels don't
+// respond the same way can use `Gamma::per_channel` instead.
+static GAMMA: Gamma = Gamma::new(2.8);
+
+/// The bus driving the strip of a section: a SPI instance, or a PWM playing the bits as a sequence.
+#[cfg(not(feature = "pwm"))]
+type Bus<'a> = Spim<'a>;
+#[cfg(feature = "pwm")]
+type Bus<'a> = SequencePwm<'a>;
+
+/// The words of the buffers of the drivers: SPI bytes, or PWM duty cycles.
+#[cfg(not(feature = "pwm"))]
+type Word = u8;
+#[cfg(feature = "pwm")]
+type Word = u16;
+
+/// The driver of a section, for RGB or RGBW strips.
+#[cfg(not(any(feature = "pwm", feature = "streaming")))]
+type Driver<'a> = Strip<Ws2812<'a, Spim<'a>, RGB8>, Ws2812<'a, Spim<'a>, RGBW<u8>>>;
+#[cfg(feature = "pwm")]
+type Driver<'a> = Strip<PwmWs2812<'a, RGB8>, PwmWs2812<'a, RGBW<u8>>>;
+#[cfg(feature = "streaming")]
+type Driver<'a> = Strip<
+    StreamingWs2812<PingPongChunks<'a, Spim<'a>>, RGB8>,
+    StreamingWs2812<PingPongChunks<'a, Spim<'a>>, RGBW<u8>>,
+>;
+
+// The APA102 and SK9822 strips take their data with the clock of the SPI instance, and replace the
+// strip of their section when its feature is enabled.
+#[cfg(not(feature = "apa102-first-section"))]
+type FirstSectionDriver<'a> = Driver<'a>;
+#[cfg(feature = "apa102-first-section")]
+type FirstSectionDriver<'a> = Apa102<'a, Spim<'a>>;
+
+#[cfg(not(feature = "apa102-second-section"))]
+type SecondSectionDriver<'a> = Driver<'a>;
+#[cfg(feature = "apa102-second-section")]
+type SecondSectionDriver<'a> = Apa102<'a, Spim<'a>>;
+
+/// A driver created on the bus of a section.
+trait SectionDriver<'a> {
+    fn new(bus: Bus<'a>, buffer: &'a mut [Word], kind: StripKind) -> Self;
+}
+
+#[cfg(not(any(feature = "pwm", feature = "streaming")))]
+impl<'a> SectionDriver<'a> for Driver<'a> {
+    fn new(spi: Bus<'a>, buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: Ws2812::new(spi, buffer),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: Ws2812::new(spi, buffer),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "pwm")]
+impl<'a> SectionDriver<'a> for Driver<'a> {
+    fn new(pwm: Bus<'a>, buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: PwmWs2812::new(pwm, buffer),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: PwmWs2812::new(pwm, buffer),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<'a> SectionDriver<'a> for Driver<'a> {
+    fn new(spi: Bus<'a>, buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: StreamingWs2812::new(PingPongChunks::new(spi, buffer)),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: StreamingWs2812::new(PingPongChunks::new(spi, buffer)),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+impl<'a> SectionDriver<'a> for Apa102<'a, Spim<'a>> {
+    fn new(spi: Bus<'a>, buffer: &'a mut [Word], _kind: StripKind) -> Self {
+        Apa102::new(spi, buffer)
+    }
+}
+
+/// The configuration of each section, from which its bus is created.
+#[cfg(not(feature = "pwm"))]
+pub(crate) type FirstSectionConfig<'a> = SpiConfig<'a, SPI2>;
+#[cfg(feature = "pwm")]
+pub(crate) type FirstSectionConfig<'a> = PwmConfig<'a, PWM0>;
+
+#[cfg(not(feature = "pwm"))]
+pub(crate) type SecondSectionConfig<'a> = SpiConfig<'a, SPI3>;
+#[cfg(feature = "pwm")]
+pub(crate) type SecondSectionConfig<'a> = PwmConfig<'a, PWM1>;
+
+#[cfg(not(feature = "pwm"))]
+pub(crate) struct SpiConfig<'a, T: spim::Instance> {
     pub spim: Peri<'a, T>,
     pub sck: Peri<'a, AnyPin>,
     pub led_pin: Peri<'a, AnyPin>,
 }
 
+#[cfg(feature = "pwm")]
+pub(crate) struct PwmConfig<'a, T: embassy_nrf::pwm::Instance> {
+    pub pwm: Peri<'a, T>,
+    pub led_pin: Peri<'a, AnyPin>,
+}
+
 #[embassy_executor::task]
 pub async fn led_task(
-    rng: Peri<'static, RNG>, spi_config_1: SpiConfig<'static, SPI2>,
-    spi_config_2: SpiConfig<'static, SPI3>, analog_default_value: u16, analog_maximum_value: u16,
+    nvmc: Peri<'static, NVMC>, rng: Peri<'static, RNG>, config_1: FirstSectionConfig<'static>,
+    config_2: SecondSectionConfig<'static>, analog_default_value: u16, analog_maximum_value: u16,
 ) {
     info!("Starting LED task...");
 
-    let mut config = Config::default();
-    config.frequency = Frequency::M4;
-
-    let spi_1 = Spim::new_txonly(
-        spi_config_1.spim,
-        Irqs,
-        spi_config_1.sck,
-        spi_config_1.led_pin,
-        config.clone(),
-    );
-
-    let mut buffer_1 = [0; FIRST_SECTION_BUFFERS_SIZE];
-    let mut ws2812_1 = Ws2812::new(spi_1, &mut buffer_1);
-
-    let spi_2 = Spim::new_txonly(
-        spi_config_2.spim,
-        Irqs,
-        spi_config_2.sck,
-        spi_config_2.led_pin,
-        config,
-    );
+    let mut config_1 = config_1;
+    let mut buffer_1 = [Word::default(); FIRST_SECTION_BUFFERS_SIZE];
+    let mut leds_1: FirstSectionDriver =
+        create_driver(&mut config_1, &mut buffer_1, FIRST_SECTION_STRIP);
 
-    let mut buffer_2 = [0; SECOND_SECTION_BUFFERS_SIZE];
-    let mut ws2812_2 = Ws2812::new(spi_2, &mut buffer_2);
+    let mut config_2 = config_2;
+    let mut buffer_2 = [Word::default(); SECOND_SECTION_BUFFERS_SIZE];
+    let mut leds_2: SecondSectionDriver =
+        create_driver(&mut config_2, &mut buffer_2, SECOND_SECTION_STRIP);
 
     // Setup Pseudo Random Number Generator
     let mut prng = setup_prng(rng).await;
 
-    let mut data = create_data();
+    let mut pipeline = Pipeline::new();
+    let mut dithering = Dithering::new();
 
     let mut active_kind = AnimationKind::MultiColorStrand;
     let mut active_animation = Animation::new(active_kind, &mut prng);
+    let mut stream_switch = StreamSwitch::new();
 
     info!("Creating default animation settings");
     let mut settings = Settings::new(
@@ -84,15 +305,28 @@ pub async fn led_task(
         analog_maximum_value,
         COLORS_TOTAL,
     );
+    settings.set_gamma(&GAMMA);
+
+    let mut storage = SettingsStorage::new(Nvmc::new(nvmc), SETTINGS_OFFSET);
+    if storage.load(&mut settings) {
+        info!("Settings restored from the flash");
+    }
+    let mut deferred_save = DeferredSave::new();
+
+    #[cfg(feature = "benchmark")]
+    cookie_monster_common::benchmark::run(&mut prng, &settings).await;
 
     let mut delay = Delay;
+    let mut write_error_handler = WriteErrorHandler::new(WRITE_ERROR_POLICY);
 
     loop {
         if let Some(()) = ANIMATION_CHANGED_SIGNAL.try_take() {
             info!("Animation changed signal received");
             active_kind = active_kind.next();
             active_animation = Animation::new(active_kind, &mut prng);
-            reset_data(&mut data);
+            pipeline.reset();
+            dithering.reset();
+            telemetry::reset(Instant::now());
         }
 
         if let Some(brightness) = BRIGHTNESS_READ_SIGNAL.try_take() {
@@ -108,16 +342,117 @@ pub async fn led_task(
             settings.set_delay(delay);
         }
 
-        debug!("Updating animation data");
-        active_animation.update(&mut data, &settings);
+        if let Some(()) = DITHERING_TOGGLED_SIGNAL.try_take() {
+            settings.set_dithering(!settings.dithering());
+            info!("Dithering toggled: {}", settings.dithering());
+        }
 
+        while let Ok(command) = COMMAND_CHANNEL.try_receive() {
+            match command::execute(command, active_kind, &mut settings, Some(&mut storage)) {
+                Some(Change::Animation(kind)) => {
+                    active_kind = kind;
+                    active_animation = Animation::new(active_kind, &mut prng);
+                    pipeline.reset();
+                    dithering.reset();
+                    telemetry::reset(Instant::now());
+                }
+                Some(Change::Settings) => deferred_save.changed(Instant::now()),
+                None => {}
+            }
+        }
+
+        // The clients can send the color corrections at frame rate, so they're only saved once
+        // they stop changing.
+        if deferred_save.is_due(Instant::now())
+            && let Err(e) = storage.save(&settings)
+        {
+            error!("Cannot save the settings: {}", e);
+        }
+
+        if let Some(kind) = stream_switch.update(active_kind, Instant::now()) {
+            info!("Switching to the {} animation", kind.name());
+            active_kind = kind;
+            active_animation = Animation::new(active_kind, &mut prng);
+            pipeline.reset();
+            dithering.reset();
+            telemetry::reset(Instant::now());
+        }
+        command::publish_status(active_kind, &settings);
+
+        // The next frame is updated while this one is sent.
         debug!("Rendering animation");
-        active_animation
-            .render(&data, &mut ws2812_1, &mut ws2812_2, &mut delay, &settings)
-            .await;
+        loop {
+            let result = pipeline
+                .render(
+                    &mut active_animation,
+                    &mut leds_1,
+                    &mut leds_2,
+                    &mut dithering,
+                    &mut delay,
+                    &settings,
+                )
+                .await;
+
+            match write_error_handler.handle(result) {
+                WriteErrorAction::NextFrame => break,
+                WriteErrorAction::Retry => {}
+                WriteErrorAction::Reinitialize(error) => {
+                    if error.failed(Section::First) {
+                        drop(leds_1);
+                        leds_1 = create_driver(&mut config_1, &mut buffer_1, FIRST_SECTION_STRIP);
+                    }
+                    if error.failed(Section::Second) {
+                        drop(leds_2);
+                        leds_2 = create_driver(&mut config_2, &mut buffer_2, SECOND_SECTION_STRIP);
+                    }
+                    break;
+                }
+            }
+        }
+        pipeline.next_frame();
+
+        telemetry::report_if_due(active_kind, Instant::now());
     }
 }
 
+/// Creates the driver of a section for its kind of strip, on a SPI instance borrowed from its
+/// configuration so that it can be created again.
+#[cfg(not(feature = "pwm"))]
+fn create_driver<'a, T: spim::Instance, D: SectionDriver<'a>>(
+    spi_config: &'a mut SpiConfig<'static, T>, buffer: &'a mut [Word], kind: StripKind,
+) -> D
+where
+    Irqs: Binding<T::Interrupt, spim::InterruptHandler<T>>,
+{
+    let mut config = Config::default();
+    config.frequency = Frequency::M4;
+
+    let spi = Spim::new_txonly(
+        spi_config.spim.reborrow(),
+        Irqs,
+        spi_config.sck.reborrow(),
+        spi_config.led_pin.reborrow(),
+        config,
+    );
+
+    D::new(spi, buffer, kind)
+}
+
+/// Creates the driver of a section for its kind of strip, on a PWM borrowed from its configuration
+/// so that it can be created again.
+#[cfg(feature = "pwm")]
+fn create_driver<'a, T: embassy_nrf::pwm::Instance, D: SectionDriver<'a>>(
+    pwm_config: &'a mut PwmConfig<'static, T>, buffer: &'a mut [Word], kind: StripKind,
+) -> D {
+    let pwm = unwrap!(SequencePwm::new_1ch(
+        pwm_config.pwm.reborrow(),
+        pwm_config.led_pin.reborrow(),
+        pwm::config(),
+    ));
+
+    D::new(pwm, buffer, kind)
+}
+
 async fn setup_prng(rng: Peri<'static, RNG>) -> SmallRng {
     let mut rng = Rng::new(rng, Irqs);
     let mut seed = [0; 8];
@@ -1,17 +1,22 @@
 #![no_std]
 #![no_main]
 
+use crate::console::{SerialConfig, console_task};
 use crate::input::{
     ANALOG_DEFAULT_VALUE, ANALOG_MAXIMUM_VALUE, analog_sensors_task, animation_button_task,
     color_button_task,
 };
+#[cfg(feature = "pwm")]
+use crate::led::PwmConfig;
+#[cfg(not(feature = "pwm"))]
 use crate::led::SpiConfig;
+use crate::led::{FirstSectionConfig, SecondSectionConfig};
 use defmt::{info, unwrap};
 use embassy_executor::Spawner;
 use embassy_nrf::Peri;
 use embassy_nrf::config::Config;
 use embassy_nrf::gpio::AnyPin;
-use embassy_nrf::peripherals::{RNG, SAADC, SPI2, SPI3};
+use embassy_nrf::peripherals::{NVMC, RNG, SAADC};
 use embassy_nrf::saadc::{AnyInput, Input};
 use embassy_time::Delay;
 use embedded_hal_async::delay::DelayNs;
@@ -41,27 +46,63 @@ async fn main(spawner: Spawner) {
         // GPIO port 0 pin 3 is an analog input corresponding to the big "1" connector or pin 1 on
         // the board.
         delay: peripherals.P0_03.degrade_saadc(),
+    };
 
-        // GPIO port 0 pin 13 corresponds to pin 15 on the board.
-        led_1: peripherals.P0_13.into(),
-
-        // GPIO port 0 pin 10 corresponds to pin 8 on the board.
-        led_2: peripherals.P0_10.into(),
-
-        // GPIO port 0 pin 17 corresponds to pin 13 on the board.
-        sck_1: peripherals.P0_17.into(),
+    // The WS2812 and SK6812 strips are driven by the SPI instances, or by the PWM peripherals which
+    // leave them free.
+    #[cfg(not(feature = "pwm"))]
+    let (config_1, config_2) = (
+        SpiConfig {
+            spim: peripherals.SPI2,
+            // GPIO port 0 pin 17 corresponds to pin 13 on the board. It only clocks the first
+            // section when it's an APA102 strip.
+            sck: peripherals.P0_17.into(),
+            // GPIO port 0 pin 13 corresponds to pin 15 on the board.
+            led_pin: peripherals.P0_13.into(),
+        },
+        SpiConfig {
+            spim: peripherals.SPI3,
+            // GPIO port 1 pin 2 corresponds to pin 16 on the board. It only clocks the second
+            // section when it's an APA102 strip.
+            sck: peripherals.P1_02.into(),
+            // GPIO port 0 pin 10 corresponds to pin 8 on the board.
+            led_pin: peripherals.P0_10.into(),
+        },
+    );
+    #[cfg(feature = "pwm")]
+    let (config_1, config_2) = (
+        PwmConfig {
+            pwm: peripherals.PWM0,
+            // GPIO port 0 pin 13 corresponds to pin 15 on the board.
+            led_pin: peripherals.P0_13.into(),
+        },
+        PwmConfig {
+            pwm: peripherals.PWM1,
+            // GPIO port 0 pin 10 corresponds to pin 8 on the board.
+            led_pin: peripherals.P0_10.into(),
+        },
+    );
 
-        // GPIO port 1 pin 2 corresponds to pin 16 on the board.
-        sck_2: peripherals.P1_02.into(),
+    let serial_config = SerialConfig {
+        uarte: peripherals.UARTE0,
+        timer: peripherals.TIMER1,
+        ppi_channel_1: peripherals.PPI_CH0,
+        ppi_channel_2: peripherals.PPI_CH1,
+        ppi_group: peripherals.PPI_GROUP0,
+        // GPIO port 1 pin 8 receives the serial port of the USB from the interface chip.
+        rx_pin: peripherals.P1_08.into(),
+        // GPIO port 0 pin 6 sends the serial port of the USB to the interface chip.
+        tx_pin: peripherals.P0_06.into(),
     };
 
     spawn_all_tasks(
         &spawner,
+        peripherals.NVMC,
         peripherals.SAADC,
         peripherals.RNG,
-        peripherals.SPI2,
-        peripherals.SPI3,
         pins,
+        (config_1, config_2),
+        serial_config,
     );
 
     let mut delay = Delay;
@@ -72,22 +113,21 @@ async fn main(spawner: Spawner) {
     }
 }
 
-/// Represents the pins used for both input and output.
+/// Represents the pins used for the inputs. The pins of the LEDs are in the configuration of their
+/// section.
 struct Pins<'a> {
     animation: Peri<'a, AnyPin>,
     brightness: AnyInput<'a>,
     color: Peri<'a, AnyPin>,
     delay: AnyInput<'a>,
-    led_1: Peri<'a, AnyPin>,
-    led_2: Peri<'a, AnyPin>,
-    sck_1: Peri<'a, AnyPin>,
-    sck_2: Peri<'a, AnyPin>,
 }
 
-/// Spawns all the tasks for the inputs and LEDs.
+/// Spawns all the tasks for the inputs, the console and the LEDs.
 fn spawn_all_tasks(
-    spawner: &Spawner, adc: Peri<'static, SAADC>, rng: Peri<'static, RNG>,
-    spi_1: Peri<'static, SPI2>, spi_2: Peri<'static, SPI3>, pins: Pins<'static>,
+    spawner: &Spawner, nvmc: Peri<'static, NVMC>, adc: Peri<'static, SAADC>,
+    rng: Peri<'static, RNG>, pins: Pins<'static>,
+    (config_1, config_2): (FirstSectionConfig<'static>, SecondSectionConfig<'static>),
+    serial_config: SerialConfig<'static>,
 ) {
     info!("Spawning all tasks...");
 
@@ -104,23 +144,22 @@ fn spawn_all_tasks(
     // Spawn the color button task
     spawner.spawn(unwrap!(color_button_task(pins.color)));
 
+    // Spawn the console task
+    spawner.spawn(unwrap!(console_task(serial_config)));
+
     // Spawn the LED task
     spawner.spawn(unwrap!(led::led_task(
+        nvmc,
         rng,
-        SpiConfig {
-            spim: spi_1,
-            sck: pins.sck_1,
-            led_pin: pins.led_1,
-        },
-        SpiConfig {
-            spim: spi_2,
-            sck: pins.sck_2,
-            led_pin: pins.led_2,
-        },
+        config_1,
+        config_2,
         ANALOG_DEFAULT_VALUE,
         ANALOG_MAXIMUM_VALUE
     )));
 }
 
+mod console;
 mod input;
 mod led;
+#[cfg(feature = "pwm")]
+mod pwm;
@@ -0,0 +1,155 @@
+//! Driver of the WS2812 and SK6812 strips over the PWM peripheral, which frees the SPI instances.
+//!
+//! Each bit of the colors is a PWM period of 1.25 µs, whose duty cycle is its high time. The
+//! EasyDMA of the PWM plays the whole frame as a sequence of 2 bytes per bit, so a frame takes 48
+//! bytes per RGB LED. Any pin can carry the data, since the PWM doesn't need a clock pin.
+
+use core::marker::PhantomData;
+use defmt::Format;
+use embassy_futures::yield_now;
+use embassy_nrf::pwm;
+use embassy_nrf::pwm::{
+    Prescaler, SequenceConfig, SequencePwm, SingleSequenceMode, SingleSequencer,
+};
+use embassy_time::Timer;
+use smart_leds_trait::{RGB8, RGBW, SmartLedsWriteAsync};
+
+/// The PWM periods last 20 ticks of the 16 MHz clock, which is 1.25 µs.
+const PERIOD: u16 = 20;
+
+/// The high times of the bits: 375 ns for a zero, and 688 ns for a one, which both the WS2812 and
+/// the SK6812 accept. The polarity bit keeps the line high until the counter reaches them.
+const ZERO: u16 = 0x8000 | 6;
+const ONE: u16 = 0x8000 | 11;
+
+/// The line stays low for the whole period of the words latching the colors.
+const RESET: u16 = 0x8000;
+
+/// The periods latching the colors, which last 300 µs.
+const RESET_SIZE: usize = 240;
+
+/// The configuration of the PWM driving a strip, whose line stays low when idle.
+pub(crate) fn config() -> pwm::Config {
+    let mut config = pwm::Config::default();
+    config.max_duty = PERIOD;
+    config.prescaler = Prescaler::Div1;
+
+    config
+}
+
+/// The size of the buffer holding the given number of LEDs, and the periods latching their colors.
+pub(crate) const fn buffer_size(leds: usize, channels: usize) -> usize {
+    leds * channels * 8 + RESET_SIZE
+}
+
+#[derive(Debug, Format)]
+pub(crate) enum Error {
+    /// The buffer is too small for the number of LEDs, see [`buffer_size`].
+    OutOfBounds,
+
+    Pwm(pwm::Error),
+}
+
+/// A strip with colors of type `C`, whose data line is the first channel of the PWM.
+pub(crate) struct PwmWs2812<'a, C> {
+    pwm: SequencePwm<'a>,
+    buffer: &'a mut [u16],
+    color: PhantomData<C>,
+}
+
+impl<'a, C> PwmWs2812<'a, C> {
+    /// The buffer must be in RAM for the EasyDMA, and hold the LEDs, see [`buffer_size`].
+    pub(crate) fn new(pwm: SequencePwm<'a>, buffer: &'a mut [u16]) -> Self {
+        Self {
+            pwm,
+            buffer,
+            color: PhantomData,
+        }
+    }
+
+    /// Encodes the channels of the LEDs, then plays them once as a sequence.
+    async fn write_channels<const CHANNELS: usize>(
+        &mut self, leds: impl Iterator<Item = [u8; CHANNELS]>,
+    ) -> Result<(), Error> {
+        let bits = CHANNELS * 8;
+        let mut length = 0;
+        for channels in leds {
+            let words = self
+                .buffer
+                .get_mut(length..length + bits)
+                .ok_or(Error::OutOfBounds)?;
+            encode(&channels, words);
+            length += bits;
+        }
+
+        self.buffer
+            .get_mut(length..length + RESET_SIZE)
+            .ok_or(Error::OutOfBounds)?
+            .fill(RESET);
+        length += RESET_SIZE;
+
+        let mut loops_done = self.pwm.event_loops_done();
+        loops_done.clear();
+
+        // The sequencer stops the PWM when it's dropped, once the latch is played.
+        let sequencer = SingleSequencer::new(
+            &mut self.pwm,
+            &self.buffer[..length],
+            SequenceConfig::default(),
+        );
+        sequencer
+            .start(SingleSequenceMode::Times(1))
+            .map_err(Error::Pwm)?;
+
+        // The sequence is played by the EasyDMA, so that the next frame is computed meanwhile.
+        Timer::after_micros(length as u64 * u64::from(PERIOD) / 16).await;
+        while !loops_done.is_triggered() {
+            yield_now().await;
+        }
+
+        Ok(())
+    }
+}
+
+/// Encodes the channels of a LED, most significant bit first.
+fn encode(channels: &[u8], words: &mut [u16]) {
+    for (byte, words) in channels.iter().zip(words.chunks_exact_mut(8)) {
+        for (bit, word) in words.iter_mut().enumerate() {
+            *word = if byte & (0x80 >> bit) == 0 { ZERO } else { ONE };
+        }
+    }
+}
+
+impl SmartLedsWriteAsync for PwmWs2812<'_, RGB8> {
+    type Color = RGB8;
+    type Error = Error;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b]
+        }))
+        .await
+    }
+}
+
+impl SmartLedsWriteAsync for PwmWs2812<'_, RGBW<u8>> {
+    type Color = RGBW<u8>;
+    type Error = Error;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b, color.a.0]
+        }))
+        .await
+    }
+}
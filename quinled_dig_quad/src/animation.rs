@@ -0,0 +1,142 @@
+use crate::flash;
+use crate::flash::RomFlash;
+use cookie_monster_common::animations::{
+    Animation, AnimationKind, COLORS_INDEX_DEFAULT, COLORS_TOTAL, Section, Settings,
+};
+use cookie_monster_common::command;
+use cookie_monster_common::command::Change;
+use cookie_monster_common::exchange::FrameSender;
+use cookie_monster_common::gamma::Gamma;
+use cookie_monster_common::signal::{
+    ANIMATION_CHANGED_SIGNAL, BRIGHTNESS_READ_SIGNAL, COLOR_CHANGED_SIGNAL,
+    COLOR_CORRECTION_CHANGED_SIGNAL, COMMAND_CHANNEL, DELAY_READ_SIGNAL, DITHERING_TOGGLED_SIGNAL,
+};
+use cookie_monster_common::storage::{DeferredSave, SettingsStorage};
+use cookie_monster_common::stream::StreamSwitch;
+use cookie_monster_common::telemetry;
+use core::slice;
+use defmt::{debug, error, info};
+use embassy_time::Instant;
+use esp_hal::rng::Rng;
+use rand::SeedableRng;
+use rand::rngs::SmallRng;
+
+// The gamma curves are generated at build time and kept in the flash. Strips whose channels don't
+// respond the same way can use `Gamma::per_channel` instead.
+static GAMMA: Gamma = Gamma::new(2.8);
+
+/// Computes the frames of the animation on the APP core, and sends them to the LED task.
+#[embassy_executor::task]
+pub async fn animation_task(
+    mut frames: FrameSender<'static>, analog_default_value: u16, analog_maximum_value: u16,
+) {
+    info!("Starting animation task...");
+
+    // Setup Pseudo Random Number Generator
+    let rng = Rng::new();
+    let mut prng = SmallRng::seed_from_u64(u64::from(rng.random()));
+
+    let mut active_kind = AnimationKind::MultiColorStrand;
+    let mut active_animation = Animation::new(active_kind, &mut prng);
+    let mut stream_switch = StreamSwitch::new();
+
+    info!("Creating default animation settings");
+    let mut settings = Settings::new(
+        COLORS_INDEX_DEFAULT,
+        analog_default_value,
+        analog_default_value,
+        analog_maximum_value,
+        COLORS_TOTAL,
+    );
+    settings.set_gamma(&GAMMA);
+
+    let mut flash = RomFlash;
+    let mut storage =
+        flash::settings_offset(&mut flash).map(|offset| SettingsStorage::new(flash, offset));
+    if let Some(storage) = &mut storage
+        && storage.load(&mut settings)
+    {
+        info!("Settings restored from the flash");
+    }
+    let mut deferred_save = DeferredSave::new();
+
+    #[cfg(feature = "benchmark")]
+    cookie_monster_common::benchmark::run(&mut prng, &settings).await;
+
+    loop {
+        if let Some(()) = ANIMATION_CHANGED_SIGNAL.try_take() {
+            info!("Animation changed signal received");
+            active_kind = active_kind.next();
+            active_animation = Animation::new(active_kind, &mut prng);
+            frames.reset();
+            telemetry::reset(Instant::now());
+        }
+
+        if let Some(brightness) = BRIGHTNESS_READ_SIGNAL.try_take() {
+            settings.set_brightness(brightness);
+        }
+
+        if let Some(()) = COLOR_CHANGED_SIGNAL.try_take() {
+            info!("Color changed signal received");
+            settings.set_color_index((settings.color_index() + 1) % COLORS_TOTAL);
+        }
+
+        if let Some((section, color_correction)) = COLOR_CORRECTION_CHANGED_SIGNAL.try_take() {
+            let sections = section.as_ref().map_or(&Section::ALL[..], slice::from_ref);
+            for &section in sections {
+                settings.set_color_correction(section, color_correction);
+            }
+            info!(
+                "Color correction of the {} sections: {}",
+                sections, color_correction
+            );
+            deferred_save.changed(Instant::now());
+        }
+
+        if let Some(delay) = DELAY_READ_SIGNAL.try_take() {
+            settings.set_delay(delay);
+        }
+
+        if let Some(()) = DITHERING_TOGGLED_SIGNAL.try_take() {
+            settings.set_dithering(!settings.dithering());
+            info!("Dithering toggled: {}", settings.dithering());
+        }
+
+        while let Ok(command) = COMMAND_CHANNEL.try_receive() {
+            match command::execute(command, active_kind, &mut settings, storage.as_mut()) {
+                Some(Change::Animation(kind)) => {
+                    active_kind = kind;
+                    active_animation = Animation::new(active_kind, &mut prng);
+                    frames.reset();
+                    telemetry::reset(Instant::now());
+                }
+                Some(Change::Settings) => deferred_save.changed(Instant::now()),
+                None => {}
+            }
+        }
+
+        // The clients can send the color corrections at frame rate, so they're only saved once
+        // they stop changing.
+        if deferred_save.is_due(Instant::now())
+            && let Some(storage) = &mut storage
+            && let Err(e) = storage.save(&settings)
+        {
+            error!("Cannot save the settings: {}", e);
+        }
+
+        if let Some(kind) = stream_switch.update(active_kind, Instant::now()) {
+            info!("Switching to the {} animation", kind.name());
+            active_kind = kind;
+            active_animation = Animation::new(active_kind, &mut prng);
+            frames.reset();
+            telemetry::reset(Instant::now());
+        }
+        command::publish_status(active_kind, &settings);
+
+        // The LED task writes the previous frames while this one is computed.
+        debug!("Computing animation");
+        frames.send(&mut active_animation, &settings).await;
+
+        telemetry::report_if_due(active_kind, Instant::now());
+    }
+}
@@ -0,0 +1,65 @@
+use cookie_monster_common::console::Console;
+use defmt::{error, info};
+use esp_hal::Async;
+use esp_hal::gpio::AnyPin;
+use esp_hal::peripherals::UART0;
+use esp_hal::uart::{Config, TxError, Uart};
+
+#[cfg(any(
+    feature = "rmt",
+    feature = "apa102-first-section",
+    feature = "apa102-second-section"
+))]
+compile_error!(
+    "The `console` feature moves the second section to LED4, and takes the pins of UART0"
+);
+
+/// Task that answers the lines of the text console, received over UART0 at 115200 bauds. It's
+/// the serial port of the USB, which also outputs the logs. They're written straight into the
+/// FIFO, so a log can land in the middle of a reply unless they're built out with `DEFMT_LOG=off`.
+///
+/// The bytes are kept in the FIFO of the UART while the other tasks run, so that a line sent at
+/// once isn't lost.
+#[embassy_executor::task]
+pub async fn console_task(uart: UART0<'static>, rx_pin: AnyPin<'static>, tx_pin: AnyPin<'static>) {
+    info!("Starting console task...");
+
+    let mut uart = match Uart::new(uart, Config::default()) {
+        Ok(uart) => uart.with_rx(rx_pin).with_tx(tx_pin).into_async(),
+        Err(e) => {
+            error!("Cannot configure the console: {}", e);
+            return;
+        }
+    };
+
+    let mut console = Console::new();
+    let mut buffer = [0; 16];
+
+    loop {
+        let length = match uart.read_async(&mut buffer).await {
+            Ok(length) => length,
+            Err(e) => {
+                error!("Cannot read from the console: {}", e);
+                continue;
+            }
+        };
+
+        for &byte in &buffer[..length] {
+            if let Some(reply) = console.receive(byte)
+                && let Err(e) = write_all(&mut uart, reply).await
+            {
+                error!("Cannot write to the console: {}", e);
+            }
+        }
+    }
+}
+
+/// Writes all the bytes, waiting for room in the FIFO.
+async fn write_all(uart: &mut Uart<'_, Async>, mut bytes: &[u8]) -> Result<(), TxError> {
+    while !bytes.is_empty() {
+        let written = uart.write_async(bytes).await?;
+        bytes = &bytes[written..];
+    }
+
+    Ok(())
+}
@@ -0,0 +1,74 @@
+//! Chunks of the streaming driver sent by the DMA of a SPI bus, from two buffers taking turns.
+//!
+//! A chunk is encoded in one buffer while the other one is sent, and the task waits for the DMA
+//! interrupt at the end of the transfer. The bus only stops until the executor polls the task again
+//! and it starts the next transfer, which must take less than the 50 µs reset time of the strips.
+//! The network tasks would run longer on the same executor, so they aren't built with this driver.
+
+use cookie_monster_common::ws2812::ChunkBus;
+use core::mem;
+use esp_hal::Async;
+use esp_hal::dma::DmaTxBuf;
+use esp_hal::spi::Error;
+use esp_hal::spi::master::SpiDma;
+
+pub(crate) struct DmaChunks<'a> {
+    /// Only empty while a chunk is sent.
+    spi: Option<SpiDma<'a, Async>>,
+
+    /// The buffer holding the next chunk to send, only empty while it's sent.
+    next: Option<DmaTxBuf>,
+
+    /// The buffer in which the chunk after it is encoded.
+    spare: DmaTxBuf,
+}
+
+impl<'a> DmaChunks<'a> {
+    pub(crate) fn new(spi: SpiDma<'a, Async>, buffers: [DmaTxBuf; 2]) -> Self {
+        let [next, spare] = buffers;
+        Self {
+            spi: Some(spi),
+            next: Some(next),
+            spare,
+        }
+    }
+
+    /// Returns the buffers so that the bus can be created again. No chunk is being sent, since each
+    /// send completes once its transfer does.
+    pub(crate) fn split(mut self) -> [DmaTxBuf; 2] {
+        [self.next.take().unwrap(), self.spare]
+    }
+}
+
+impl ChunkBus for DmaChunks<'_> {
+    type Error = Error;
+
+    fn buffer(&mut self) -> &mut [u8] {
+        self.next.as_mut().unwrap().as_mut_slice()
+    }
+
+    async fn send(
+        &mut self, length: usize, encode: impl FnOnce(&mut [u8]) -> usize,
+    ) -> Result<usize, Self::Error> {
+        let mut buffer = self.next.take().unwrap();
+        buffer.set_length(length);
+
+        match self.spi.take().unwrap().write(length, buffer) {
+            Ok(mut transfer) => {
+                let next_length = encode(self.spare.as_mut_slice());
+                transfer.wait_for_done().await;
+
+                // The transfer is done, so this doesn't wait anymore.
+                let (spi, sent) = transfer.wait();
+                self.spi = Some(spi);
+                self.next = Some(mem::replace(&mut self.spare, sent));
+                Ok(next_length)
+            }
+            Err((e, spi, buffer)) => {
+                self.spi = Some(spi);
+                self.next = Some(buffer);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,235 @@
+//! Access to the SPI flash memory through the functions of the ROM, to persist the settings.
+//!
+//! The code can't be fetched from the flash while the ROM functions access it, so the cache of both
+//! cores is disabled around them, the functions doing so are placed in RAM, the interrupts are
+//! masked, and the other core is parked.
+
+use defmt::warn;
+use embedded_storage::nor_flash::{
+    ErrorType, NorFlash, NorFlashError, NorFlashErrorKind, ReadNorFlash, RmwNorFlashStorage,
+};
+use esp_bootloader_esp_idf::partitions::{
+    DataPartitionSubType, PARTITION_TABLE_MAX_LEN, PartitionType, read_partition_table,
+};
+use esp_hal::peripherals::CPU_CTRL;
+use esp_hal::system::{Cpu, CpuControl, is_running};
+use esp_rom_sys::rom::spiflash::{
+    ESP_ROM_SPIFLASH_RESULT_OK, esp_rom_spiflash_erase_sector, esp_rom_spiflash_read,
+    esp_rom_spiflash_write,
+};
+
+/// The size of the flash memory of the ESP32-WROOM-32E on the board.
+const CAPACITY: usize = 4 * 1024 * 1024;
+
+const SECTOR_SIZE: usize = 4096;
+
+/// The number of words copied through RAM at once, since the ROM functions need aligned buffers.
+const CHUNK_WORDS: usize = 16;
+
+unsafe extern "C" {
+    fn Cache_Flush_rom(cpu: u32);
+    fn Cache_Read_Disable_rom(cpu: u32);
+    fn Cache_Read_Enable_rom(cpu: u32);
+}
+
+#[derive(Debug)]
+pub(crate) enum RomFlashError {
+    NotAligned,
+    OutOfBounds,
+
+    /// A ROM function failed, or timed out.
+    Rom,
+}
+
+impl NorFlashError for RomFlashError {
+    fn kind(&self) -> NorFlashErrorKind {
+        match self {
+            RomFlashError::NotAligned => NorFlashErrorKind::NotAligned,
+            RomFlashError::OutOfBounds => NorFlashErrorKind::OutOfBounds,
+            RomFlashError::Rom => NorFlashErrorKind::Other,
+        }
+    }
+}
+
+/// The whole flash memory, with the offsets starting at its beginning.
+pub(crate) struct RomFlash;
+
+impl ErrorType for RomFlash {
+    type Error = RomFlashError;
+}
+
+impl ReadNorFlash for RomFlash {
+    const READ_SIZE: usize = 4;
+
+    fn read(&mut self, offset: u32, bytes: &mut [u8]) -> Result<(), Self::Error> {
+        check(offset, bytes.len(), Self::READ_SIZE)?;
+
+        let mut words = [0_u32; CHUNK_WORDS];
+        for (index, chunk) in bytes.chunks_mut(CHUNK_WORDS * 4).enumerate() {
+            let address = offset + (index * CHUNK_WORDS * 4) as u32;
+            with_other_core_parked(|| read(address, words.as_mut_ptr(), chunk.len() as u32))?;
+            for (bytes, word) in chunk.chunks_mut(4).zip(words) {
+                bytes.copy_from_slice(&word.to_le_bytes());
+            }
+        }
+
+        Ok(())
+    }
+
+    fn capacity(&self) -> usize {
+        CAPACITY
+    }
+}
+
+impl NorFlash for RomFlash {
+    const WRITE_SIZE: usize = 4;
+    const ERASE_SIZE: usize = SECTOR_SIZE;
+
+    fn erase(&mut self, from: u32, to: u32) -> Result<(), Self::Error> {
+        check(from, (to - from) as usize, Self::ERASE_SIZE)?;
+
+        for sector in from / SECTOR_SIZE as u32..to / SECTOR_SIZE as u32 {
+            with_other_core_parked(|| erase(sector))?;
+        }
+
+        Ok(())
+    }
+
+    fn write(&mut self, offset: u32, bytes: &[u8]) -> Result<(), Self::Error> {
+        check(offset, bytes.len(), Self::WRITE_SIZE)?;
+
+        let mut words = [0_u32; CHUNK_WORDS];
+        for (index, chunk) in bytes.chunks(CHUNK_WORDS * 4).enumerate() {
+            let address = offset + (index * CHUNK_WORDS * 4) as u32;
+            for (word, bytes) in words.iter_mut().zip(chunk.chunks(4)) {
+                *word = u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            }
+            with_other_core_parked(|| write(address, words.as_ptr(), chunk.len() as u32))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Finds the offset of the NVS partition of the partition table, which holds the settings since
+/// ESP-IDF doesn't use it.
+pub(crate) fn settings_offset(flash: &mut RomFlash) -> Option<u32> {
+    let mut merge_buffer = [0; SECTOR_SIZE];
+    let mut table_buffer = [0; PARTITION_TABLE_MAX_LEN];
+    let mut storage = RmwNorFlashStorage::new(flash, &mut merge_buffer);
+
+    let table = read_partition_table(&mut storage, &mut table_buffer)
+        .inspect_err(|error| warn!("Cannot read the partition table: {}", error))
+        .ok()?;
+
+    match table.find_partition(PartitionType::Data(DataPartitionSubType::Nvs)) {
+        Ok(Some(partition)) => Some(partition.offset()),
+        Ok(None) => {
+            warn!("There's no NVS partition to persist the settings");
+            None
+        }
+        Err(error) => {
+            warn!("Cannot find the NVS partition: {}", error);
+            None
+        }
+    }
+}
+
+/// Runs the access to the flash with the interrupts masked, and the other core parked since it runs
+/// its code from the flash too.
+///
+/// The settings are saved from the APP core, so the PRO core stops writing the LEDs meanwhile, up
+/// to a few hundred milliseconds for a sector erase. The RMT and streaming drivers aren't refilled
+/// either, so the frame they're sending is cut short.
+fn with_other_core_parked<T>(access: impl FnOnce() -> T) -> T {
+    critical_section::with(|_| {
+        // SAFETY: The CPU control is only used to park and unpark the other core, and the scheduler
+        // only used it to start the APP core when the board started.
+        let mut cpu_control = CpuControl::new(unsafe { CPU_CTRL::steal() });
+        let other_core = Cpu::other().find(|core| is_running(*core));
+
+        if let Some(core) = other_core {
+            // SAFETY: The parked core isn't the current one.
+            unsafe { cpu_control.park_core(core) };
+        }
+        let result = access();
+        if let Some(core) = other_core {
+            cpu_control.unpark_core(core);
+        }
+
+        result
+    })
+}
+
+fn check(offset: u32, length: usize, alignment: usize) -> Result<(), RomFlashError> {
+    if !(offset as usize).is_multiple_of(alignment) || !length.is_multiple_of(alignment) {
+        return Err(RomFlashError::NotAligned);
+    }
+    if offset as usize + length > CAPACITY {
+        return Err(RomFlashError::OutOfBounds);
+    }
+
+    Ok(())
+}
+
+#[esp_hal::ram]
+fn erase(sector: u32) -> Result<(), RomFlashError> {
+    // SAFETY: The cache is disabled while the ROM accesses the flash, and this function runs from
+    // RAM with the interrupts masked and the other core parked by the caller.
+    let result = unsafe {
+        Cache_Read_Disable_rom(0);
+        Cache_Read_Disable_rom(1);
+        let result = esp_rom_spiflash_erase_sector(sector);
+        Cache_Flush_rom(0);
+        Cache_Flush_rom(1);
+        Cache_Read_Enable_rom(0);
+        Cache_Read_Enable_rom(1);
+        result
+    };
+
+    if result == ESP_ROM_SPIFLASH_RESULT_OK {
+        Ok(())
+    } else {
+        Err(RomFlashError::Rom)
+    }
+}
+
+#[esp_hal::ram]
+fn read(address: u32, words: *mut u32, length: u32) -> Result<(), RomFlashError> {
+    // SAFETY: See `erase`, and the words can hold the length, which is a multiple of 4.
+    let result = unsafe {
+        Cache_Read_Disable_rom(0);
+        Cache_Read_Disable_rom(1);
+        let result = esp_rom_spiflash_read(address, words, length);
+        Cache_Read_Enable_rom(0);
+        Cache_Read_Enable_rom(1);
+        result
+    };
+
+    if result == ESP_ROM_SPIFLASH_RESULT_OK {
+        Ok(())
+    } else {
+        Err(RomFlashError::Rom)
+    }
+}
+
+#[esp_hal::ram]
+fn write(address: u32, words: *const u32, length: u32) -> Result<(), RomFlashError> {
+    // SAFETY: See `erase`, and the words hold the length, which is a multiple of 4.
+    let result = unsafe {
+        Cache_Read_Disable_rom(0);
+        Cache_Read_Disable_rom(1);
+        let result = esp_rom_spiflash_write(address, words, length);
+        Cache_Flush_rom(0);
+        Cache_Flush_rom(1);
+        Cache_Read_Enable_rom(0);
+        Cache_Read_Enable_rom(1);
+        result
+    };
+
+    if result == ESP_ROM_SPIFLASH_RESULT_OK {
+        Ok(())
+    } else {
+        Err(RomFlashError::Rom)
+    }
+}
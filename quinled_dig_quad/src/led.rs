@@ -1,117 +1,372 @@
-use cookie_monster_common::animations::{
-    Animation, AnimationKind, COLORS_INDEX_DEFAULT, COLORS_TOTAL, LEDS_FIRST_SECTION,
-    LEDS_SECOND_SECTION, Settings, create_data, reset_data,
-};
-use cookie_monster_common::signal::{
-    ANIMATION_CHANGED_SIGNAL, BRIGHTNESS_READ_SIGNAL, COLOR_CHANGED_SIGNAL, DELAY_READ_SIGNAL,
-};
+#[cfg(feature = "streaming")]
+use crate::dma_chunks::DmaChunks;
+#[cfg(feature = "rmt")]
+use crate::rmt;
+#[cfg(feature = "rmt")]
+use crate::rmt::RmtWs2812;
+use cookie_monster_common::animations::Section;
+#[cfg(not(feature = "streaming"))]
+use cookie_monster_common::animations::{LEDS_FIRST_SECTION, LEDS_SECOND_SECTION};
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+use cookie_monster_common::apa102;
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+use cookie_monster_common::apa102::Apa102;
+use cookie_monster_common::dithering::Dithering;
+use cookie_monster_common::exchange::FrameReceiver;
+use cookie_monster_common::strip::{Strip, StripKind};
+use cookie_monster_common::write_error::{WriteErrorAction, WriteErrorHandler, WriteErrorPolicy};
+#[cfg(not(any(
+    feature = "rmt",
+    all(feature = "apa102-first-section", feature = "apa102-second-section")
+)))]
+use cookie_monster_common::ws2812;
+#[cfg(feature = "streaming")]
+use cookie_monster_common::ws2812::StreamingWs2812;
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+use cookie_monster_common::ws2812::Ws2812;
 use defmt::{debug, info};
 use embassy_time::Delay;
-use esp_hal::dma::{AnySpiDmaChannel, DmaRxBuf, DmaTxBuf};
+#[cfg(not(feature = "streaming"))]
+use esp_hal::Async;
+#[cfg(not(feature = "rmt"))]
+use esp_hal::Blocking;
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+use esp_hal::dma::DmaRxBuf;
+#[cfg(not(feature = "rmt"))]
+use esp_hal::dma::{AnySpiDmaChannel, DmaTxBuf};
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+use esp_hal::dma_buffers;
+#[cfg(feature = "streaming")]
+use esp_hal::dma_tx_buffer;
+#[cfg(not(feature = "rmt"))]
 use esp_hal::gpio::AnyPin;
-use esp_hal::rng::Rng;
-use esp_hal::spi::master::{AnySpi, Config, Spi, SpiDmaBus};
+#[cfg(feature = "rmt")]
+use esp_hal::rmt::{Channel, PulseCode, Tx};
+#[cfg(any(
+    not(any(feature = "rmt", feature = "streaming")),
+    feature = "apa102-first-section",
+    feature = "apa102-second-section"
+))]
+use esp_hal::spi::master::SpiDmaBus;
+#[cfg(not(feature = "rmt"))]
+use esp_hal::spi::master::{AnySpi, Config, Spi, SpiDma};
+#[cfg(not(feature = "rmt"))]
 use esp_hal::time::Rate;
-use esp_hal::{Blocking, dma_buffers};
-use rand::SeedableRng;
-use rand::rngs::SmallRng;
-use ws2812_spi::prerendered::Ws2812;
+use smart_leds_trait::{RGB8, RGBW};
 
-// 12 is calculated by knowing that we're using 8 bits per color (3 bytes total), and that
-// ws2812_spi converts each color byte to 4 SPI bytes.
-const FIRST_SECTION_BUFFERS_SIZE: usize = LEDS_FIRST_SECTION * 12;
+#[cfg(all(
+    feature = "rmt",
+    any(feature = "apa102-first-section", feature = "apa102-second-section")
+))]
+compile_error!("The APA102 strips are clocked by a SPI bus, which the `rmt` feature doesn't use");
 
-// 12 is calculated by knowing that we're using 8 bits per color (3 bytes total), and that
-// ws2812_spi converts each color byte to 4 SPI bytes.
-const SECOND_SECTION_BUFFERS_SIZE: usize = LEDS_SECOND_SECTION * 12;
+#[cfg(all(
+    feature = "streaming",
+    any(
+        feature = "rmt",
+        feature = "apa102-first-section",
+        feature = "apa102-second-section"
+    )
+))]
+compile_error!("The `streaming` feature only drives the WS2812 and SK6812 strips on the SPI buses");
+
+// The network tasks parse whole packets between two polls of the LED task, which is longer than the
+// RMT channels wait for their refill.
+#[cfg(all(feature = "rmt", feature = "network"))]
+compile_error!("The `rmt` feature can't be combined with the network features");
+
+// The network tasks parse whole packets between two polls of the LED task, which is longer than the
+// strips wait before latching the chunks sent so far.
+#[cfg(all(feature = "streaming", feature = "network"))]
+compile_error!("The `streaming` feature can't be combined with the network features");
+
+#[cfg(all(feature = "write-error-retry", feature = "write-error-skip-frame"))]
+compile_error!(
+    "Only one of the `write-error-retry` and `write-error-skip-frame` features can be enabled"
+);
+
+// The strip of each section. SK6812 RGBW strips use `StripKind::Rgbw`, and strips that don't take
+// their channels in the GRB order of the WS2812 set their own `ColorOrder`.
+const FIRST_SECTION_STRIP: StripKind = StripKind::WS2812;
+const SECOND_SECTION_STRIP: StripKind = StripKind::WS2812;
+
+// Each section is split over two of the four LED outputs of the board when it's driven by the RMT.
+#[cfg(feature = "rmt")]
+pub(crate) const OUTPUTS_PER_SECTION: usize = 2;
+
+// Each of the two DMA buffers of a section holds a chunk of 16 LEDs for the streaming driver. They
+// are sized for RGBW strips, and RGB strips leave the end of the buffers unused.
+#[cfg(feature = "streaming")]
+const CHUNK_SIZE: usize = ws2812::buffer_size(16, StripKind::SK6812_RGBW.channels());
+
+#[cfg(not(any(
+    feature = "apa102-first-section",
+    feature = "rmt",
+    feature = "streaming"
+)))]
+const FIRST_SECTION_BUFFERS_SIZE: usize =
+    ws2812::frame_size(LEDS_FIRST_SECTION, FIRST_SECTION_STRIP.channels());
+#[cfg(feature = "apa102-first-section")]
+const FIRST_SECTION_BUFFERS_SIZE: usize = apa102::buffer_size(LEDS_FIRST_SECTION);
+#[cfg(feature = "rmt")]
+const FIRST_SECTION_BUFFERS_SIZE: usize = rmt::buffer_size(
+    LEDS_FIRST_SECTION,
+    FIRST_SECTION_STRIP.channels(),
+    OUTPUTS_PER_SECTION,
+);
 
-// According to the ws2812_spi documentation, the SPI frequency must be between 2 and 3.8 MHz.
+#[cfg(not(any(
+    feature = "apa102-second-section",
+    feature = "rmt",
+    feature = "streaming"
+)))]
+const SECOND_SECTION_BUFFERS_SIZE: usize =
+    ws2812::frame_size(LEDS_SECOND_SECTION, SECOND_SECTION_STRIP.channels());
+#[cfg(feature = "apa102-second-section")]
+const SECOND_SECTION_BUFFERS_SIZE: usize = apa102::buffer_size(LEDS_SECOND_SECTION);
+#[cfg(feature = "rmt")]
+const SECOND_SECTION_BUFFERS_SIZE: usize = rmt::buffer_size(
+    LEDS_SECOND_SECTION,
+    SECOND_SECTION_STRIP.channels(),
+    OUTPUTS_PER_SECTION,
+);
+
+// The streaming driver encodes the chunks straight into the DMA buffers, and has no buffer of its
+// own.
+#[cfg(feature = "streaming")]
+const FIRST_SECTION_BUFFERS_SIZE: usize = 0;
+#[cfg(feature = "streaming")]
+const SECOND_SECTION_BUFFERS_SIZE: usize = 0;
+
+// According to the ws2812_spi documentation, the SPI frequency must be between 2 and 3.8 MHz, or
+// between 2.3 and 3.8 MHz for the SK6812.
 // Though, in practice, it seems that the lower limit is really around 2.2 MHz on this board.
+#[cfg(not(feature = "rmt"))]
 const SPI_FREQUENCY: Rate = Rate::from_khz(3_800);
 
+// A write only fails when the SPI or RMT transfer does, which usually won't recover by itself. By
+// default, the bus is recreated after a few consecutive failures instead of retrying the same
+// frame. The `write-error-retry` and `write-error-skip-frame` features select the other policies.
+#[cfg(not(any(feature = "write-error-retry", feature = "write-error-skip-frame")))]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::Reinitialize(10);
+#[cfg(feature = "write-error-retry")]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::Retry(3);
+#[cfg(feature = "write-error-skip-frame")]
+const WRITE_ERROR_POLICY: WriteErrorPolicy = WriteErrorPolicy::SkipFrame;
+
+/// The bus driving the strip of a section: a SPI bus, or the RMT channels of its outputs. Both
+/// complete their writes once the DMA or the RMT sent them, so that the inputs are handled
+/// meanwhile.
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+type Bus<'a> = SpiDmaBus<'a, Async>;
+#[cfg(feature = "streaming")]
+type Bus<'a> = DmaChunks<'a>;
+#[cfg(feature = "rmt")]
+pub(crate) type Bus<'a> = [Channel<'a, Async, Tx>; OUTPUTS_PER_SECTION];
+
+/// The words of the buffers of the drivers: SPI bytes, or RMT pulse codes.
+#[cfg(not(feature = "rmt"))]
+type Word = u8;
+#[cfg(feature = "rmt")]
+type Word = PulseCode;
+
+/// The DMA buffers of a section, which are given back when its bus is created again.
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+type DmaBuffers = (DmaRxBuf, DmaTxBuf);
+#[cfg(feature = "streaming")]
+type DmaBuffers = [DmaTxBuf; 2];
+
+/// The driver of a section, for RGB or RGBW strips.
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+type Driver<'a, 'b> = Strip<
+    Ws2812<'a, &'a mut SpiDmaBus<'b, Async>, RGB8>,
+    Ws2812<'a, &'a mut SpiDmaBus<'b, Async>, RGBW<u8>>,
+>;
+#[cfg(feature = "rmt")]
+type Driver<'a, 'b> = Strip<
+    RmtWs2812<'a, 'b, OUTPUTS_PER_SECTION, RGB8>,
+    RmtWs2812<'a, 'b, OUTPUTS_PER_SECTION, RGBW<u8>>,
+>;
+#[cfg(feature = "streaming")]
+type Driver<'a, 'b> = Strip<
+    StreamingWs2812<&'a mut DmaChunks<'b>, RGB8>,
+    StreamingWs2812<&'a mut DmaChunks<'b>, RGBW<u8>>,
+>;
+
+// The APA102 and SK9822 strips take their data with the clock of the SPI bus, and replace the strip
+// of their section when its feature is enabled.
+#[cfg(not(feature = "apa102-first-section"))]
+type FirstSectionDriver<'a, 'b> = Driver<'a, 'b>;
+#[cfg(feature = "apa102-first-section")]
+type FirstSectionDriver<'a, 'b> = Apa102<'a, &'a mut SpiDmaBus<'b, Async>>;
+
+#[cfg(not(feature = "apa102-second-section"))]
+type SecondSectionDriver<'a, 'b> = Driver<'a, 'b>;
+#[cfg(feature = "apa102-second-section")]
+type SecondSectionDriver<'a, 'b> = Apa102<'a, &'a mut SpiDmaBus<'b, Async>>;
+
+/// A driver created on the bus of a section.
+trait SectionDriver<'a, 'b> {
+    fn new(bus: &'a mut Bus<'b>, buffer: &'a mut [Word], kind: StripKind) -> Self;
+}
+
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+impl<'a, 'b> SectionDriver<'a, 'b> for Driver<'a, 'b> {
+    fn new(spi: &'a mut Bus<'b>, buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: Ws2812::new(spi, buffer),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: Ws2812::new(spi, buffer),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "rmt")]
+impl<'a, 'b> SectionDriver<'a, 'b> for Driver<'a, 'b> {
+    fn new(channels: &'a mut Bus<'b>, buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: RmtWs2812::new(channels, buffer),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: RmtWs2812::new(channels, buffer),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "streaming")]
+impl<'a, 'b> SectionDriver<'a, 'b> for Driver<'a, 'b> {
+    fn new(chunks: &'a mut Bus<'b>, _buffer: &'a mut [Word], kind: StripKind) -> Self {
+        match kind {
+            StripKind::Rgb(order) => Strip::Rgb {
+                driver: StreamingWs2812::new(chunks),
+                order,
+            },
+            StripKind::Rgbw(order, white_mode) => Strip::Rgbw {
+                driver: StreamingWs2812::new(chunks),
+                order,
+                white_mode,
+            },
+        }
+    }
+}
+
+#[cfg(any(feature = "apa102-first-section", feature = "apa102-second-section"))]
+impl<'a, 'b> SectionDriver<'a, 'b> for Apa102<'a, &'a mut SpiDmaBus<'b, Async>> {
+    fn new(spi: &'a mut Bus<'b>, buffer: &'a mut [Word], _kind: StripKind) -> Self {
+        Apa102::new(spi, buffer)
+    }
+}
+
+/// The configuration of a section, from which its bus is created.
+#[cfg(not(feature = "rmt"))]
+pub(crate) type SectionConfig<'a> = SpiConfig<'a>;
+#[cfg(feature = "rmt")]
+pub(crate) type SectionConfig<'a> = Bus<'a>;
+
+#[cfg(not(feature = "rmt"))]
 pub(crate) struct SpiConfig<'a> {
     pub spi: AnySpi<'a>,
     pub dma_channel: AnySpiDmaChannel<'a>,
     pub led_pin: AnyPin<'a>,
+
+    /// The clock of APA102 strips, which the WS2812 strips don't use.
+    pub sck: Option<AnyPin<'a>>,
 }
 
+/// Writes the frames computed on the APP core to the LEDs.
 #[embassy_executor::task]
 pub async fn led_task(
-    spi_config_1: SpiConfig<'static>, spi_config_2: SpiConfig<'static>, analog_default_value: u16,
-    analog_maximum_value: u16,
+    config_1: SectionConfig<'static>, config_2: SectionConfig<'static>,
+    mut frames: FrameReceiver<'static>,
 ) {
     info!("Starting LED task...");
 
-    let (dma_rx_buffer_1, dma_tx_buffer_1, dma_rx_buffer_2, dma_tx_buffer_2) = create_dma_buffers();
-
-    let mut buffer_1 = [0; FIRST_SECTION_BUFFERS_SIZE];
-    let mut ws2812_1 = create_ws2812_driver(
-        spi_config_1,
-        dma_rx_buffer_1,
-        dma_tx_buffer_1,
-        &mut buffer_1,
-    );
-
-    let mut buffer_2 = [0; SECOND_SECTION_BUFFERS_SIZE];
-    let mut ws2812_2 = create_ws2812_driver(
-        spi_config_2,
-        dma_rx_buffer_2,
-        dma_tx_buffer_2,
-        &mut buffer_2,
-    );
-
-    // Setup Pseudo Random Number Generator
-    let rng = Rng::new();
-    let mut prng = SmallRng::seed_from_u64(u64::from(rng.random()));
-
-    let mut data = create_data();
-
-    let mut active_kind = AnimationKind::MultiColorStrand;
-    let mut active_animation = Animation::new(active_kind, &mut prng);
-
-    info!("Creating default animation settings");
-    let mut settings = Settings::new(
-        COLORS_INDEX_DEFAULT,
-        analog_default_value,
-        analog_default_value,
-        analog_maximum_value,
-        COLORS_TOTAL,
-    );
+    #[cfg(not(feature = "rmt"))]
+    let (dma_buffers_1, dma_buffers_2) = create_dma_buffers();
 
-    let mut delay = Delay;
+    #[cfg(not(feature = "rmt"))]
+    let mut config_1 = config_1;
+    #[cfg(not(feature = "rmt"))]
+    let mut bus_1 = create_spi_bus(&mut config_1, dma_buffers_1);
+    #[cfg(feature = "rmt")]
+    let mut bus_1 = config_1;
+    let mut buffer_1 = [Word::default(); FIRST_SECTION_BUFFERS_SIZE];
+    let mut leds_1: FirstSectionDriver =
+        create_driver(&mut bus_1, &mut buffer_1, FIRST_SECTION_STRIP);
 
-    loop {
-        if let Some(()) = ANIMATION_CHANGED_SIGNAL.try_take() {
-            info!("Animation changed signal received");
-            active_kind = active_kind.next();
-            active_animation = Animation::new(active_kind, &mut prng);
-            reset_data(&mut data);
-        }
+    #[cfg(not(feature = "rmt"))]
+    let mut config_2 = config_2;
+    #[cfg(not(feature = "rmt"))]
+    let mut bus_2 = create_spi_bus(&mut config_2, dma_buffers_2);
+    #[cfg(feature = "rmt")]
+    let mut bus_2 = config_2;
+    let mut buffer_2 = [Word::default(); SECOND_SECTION_BUFFERS_SIZE];
+    let mut leds_2: SecondSectionDriver =
+        create_driver(&mut bus_2, &mut buffer_2, SECOND_SECTION_STRIP);
 
-        if let Some(brightness) = BRIGHTNESS_READ_SIGNAL.try_take() {
-            settings.set_brightness(brightness);
-        }
+    let mut dithering = Dithering::new();
+    let mut delay = Delay;
+    let mut write_error_handler = WriteErrorHandler::new(WRITE_ERROR_POLICY);
 
-        if let Some(()) = COLOR_CHANGED_SIGNAL.try_take() {
-            info!("Color changed signal received");
-            settings.set_color_index((settings.color_index() + 1) % COLORS_TOTAL);
-        }
+    loop {
+        // The APP core computes the next frames while this one is sent.
+        debug!("Rendering animation");
+        loop {
+            let result = frames
+                .render(&mut leds_1, &mut leds_2, &mut dithering, &mut delay)
+                .await;
 
-        if let Some(delay) = DELAY_READ_SIGNAL.try_take() {
-            settings.set_delay(delay);
+            match write_error_handler.handle(result) {
+                WriteErrorAction::NextFrame => break,
+                WriteErrorAction::Retry => {}
+                WriteErrorAction::Reinitialize(error) => {
+                    // The RMT channels stay configured, and only their driver is created again.
+                    if error.failed(Section::First) {
+                        drop(leds_1);
+                        #[cfg(not(feature = "rmt"))]
+                        {
+                            let dma_buffers = release_spi_bus(bus_1);
+                            bus_1 = create_spi_bus(&mut config_1, dma_buffers);
+                        }
+                        leds_1 = create_driver(&mut bus_1, &mut buffer_1, FIRST_SECTION_STRIP);
+                    }
+                    if error.failed(Section::Second) {
+                        drop(leds_2);
+                        #[cfg(not(feature = "rmt"))]
+                        {
+                            let dma_buffers = release_spi_bus(bus_2);
+                            bus_2 = create_spi_bus(&mut config_2, dma_buffers);
+                        }
+                        leds_2 = create_driver(&mut bus_2, &mut buffer_2, SECOND_SECTION_STRIP);
+                    }
+                    break;
+                }
+            }
         }
-
-        debug!("Updating animation data");
-        active_animation.update(&mut data, &settings);
-
-        debug!("Rendering animation");
-        active_animation
-            .render(&data, &mut ws2812_1, &mut ws2812_2, &mut delay, &settings)
-            .await;
+        frames.next_frame();
     }
 }
 
-fn create_dma_buffers() -> (DmaRxBuf, DmaTxBuf, DmaRxBuf, DmaTxBuf) {
+/// Creates the driver of a section for its kind of strip.
+fn create_driver<'a, 'b, D: SectionDriver<'a, 'b>>(
+    bus: &'a mut Bus<'b>, buffer: &'a mut [Word], kind: StripKind,
+) -> D {
+    D::new(bus, buffer, kind)
+}
+
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+fn create_dma_buffers() -> (DmaBuffers, DmaBuffers) {
     debug!("Creating DMA buffers");
 
     // 4 is the smallest size allowed due to the required byte alignment. This is true even if the
@@ -127,25 +382,69 @@ fn create_dma_buffers() -> (DmaRxBuf, DmaTxBuf, DmaRxBuf, DmaTxBuf) {
     let dma_tx_buffer_2 = DmaTxBuf::new(tx_descriptors, tx_buffer).unwrap();
 
     (
-        dma_rx_buffer_1,
-        dma_tx_buffer_1,
-        dma_rx_buffer_2,
-        dma_tx_buffer_2,
+        (dma_rx_buffer_1, dma_tx_buffer_1),
+        (dma_rx_buffer_2, dma_tx_buffer_2),
+    )
+}
+
+#[cfg(feature = "streaming")]
+fn create_dma_buffers() -> (DmaBuffers, DmaBuffers) {
+    debug!("Creating DMA buffers");
+
+    // Each macro call has its own static buffer.
+    (
+        [
+            dma_tx_buffer!(CHUNK_SIZE).unwrap(),
+            dma_tx_buffer!(CHUNK_SIZE).unwrap(),
+        ],
+        [
+            dma_tx_buffer!(CHUNK_SIZE).unwrap(),
+            dma_tx_buffer!(CHUNK_SIZE).unwrap(),
+        ],
     )
 }
 
-fn create_ws2812_driver<'a>(
-    spi_config: SpiConfig<'a>, dma_rx_buffer: DmaRxBuf, dma_tx_buffer: DmaTxBuf,
-    buffer: &'a mut [u8],
-) -> Ws2812<'a, SpiDmaBus<'a, Blocking>> {
-    let spi = Spi::new(
-        spi_config.spi,
+/// Creates the SPI bus of a section, on peripherals borrowed from its configuration so that it can
+/// be created again.
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+fn create_spi_bus<'a>(spi_config: &'a mut SpiConfig<'static>, dma_buffers: DmaBuffers) -> Bus<'a> {
+    let (dma_rx_buffer, dma_tx_buffer) = dma_buffers;
+    create_spi_dma(spi_config)
+        .with_buffers(dma_rx_buffer, dma_tx_buffer)
+        .into_async()
+}
+
+/// Creates the SPI bus of a section, on peripherals borrowed from its configuration so that it can
+/// be created again. The chunks of the streaming driver are sent from both DMA buffers in turns.
+#[cfg(feature = "streaming")]
+fn create_spi_bus<'a>(spi_config: &'a mut SpiConfig<'static>, dma_buffers: DmaBuffers) -> Bus<'a> {
+    DmaChunks::new(create_spi_dma(spi_config).into_async(), dma_buffers)
+}
+
+#[cfg(not(feature = "rmt"))]
+fn create_spi_dma<'a>(spi_config: &'a mut SpiConfig<'static>) -> SpiDma<'a, Blocking> {
+    let mut spi = Spi::new(
+        spi_config.spi.reborrow(),
         Config::default().with_frequency(SPI_FREQUENCY),
     )
     .unwrap()
-    .with_mosi(spi_config.led_pin)
-    .with_dma(spi_config.dma_channel)
-    .with_buffers(dma_rx_buffer, dma_tx_buffer);
+    .with_mosi(spi_config.led_pin.reborrow());
+    if let Some(sck) = spi_config.sck.as_mut() {
+        spi = spi.with_sck(sck.reborrow());
+    }
+
+    spi.with_dma(spi_config.dma_channel.reborrow())
+}
+
+/// Releases the SPI bus of a section, once its last transfer is done.
+#[cfg(not(any(feature = "rmt", feature = "streaming")))]
+fn release_spi_bus(bus: Bus<'_>) -> DmaBuffers {
+    let (_, dma_rx_buffer, dma_tx_buffer) = bus.split();
+    (dma_rx_buffer, dma_tx_buffer)
+}
 
-    Ws2812::new(spi, buffer)
+/// Releases the SPI bus of a section, once its last chunk is sent.
+#[cfg(feature = "streaming")]
+fn release_spi_bus(bus: Bus<'_>) -> DmaBuffers {
+    bus.split()
 }
@@ -1,26 +1,48 @@
 #![no_std]
 #![no_main]
 
+use crate::animation::animation_task;
+#[cfg(feature = "console")]
+use crate::console::console_task;
+#[cfg(not(feature = "network"))]
+use crate::input::analog_sensors_task;
 use crate::input::{
-    ANALOG_DEFAULT_VALUE, ANALOG_MAXIMUM_VALUE, BrightnessPin, DelayPin, analog_sensors_task,
-    animation_button_task, color_button_task,
+    ANALOG_DEFAULT_VALUE, ANALOG_MAXIMUM_VALUE, BrightnessPin, DelayPin, animation_button_task,
+    color_button_task,
 };
+use crate::led::SectionConfig;
+#[cfg(not(feature = "rmt"))]
 use crate::led::SpiConfig;
-use defmt::{info, unwrap};
+use cookie_monster_common::exchange::{FrameExchange, FrameReceiver, FrameSender};
+use defmt::{info, unwrap, warn};
 use embassy_executor::Spawner;
 use embassy_time::Delay;
 use embedded_hal_async::delay::DelayNs;
 use esp_hal::clock::CpuClock;
+#[cfg(not(feature = "rmt"))]
 use esp_hal::dma::AnySpiDmaChannel;
 use esp_hal::gpio::{AnyPin, Pin};
-use esp_hal::interrupt::software::SoftwareInterruptControl;
-use esp_hal::peripherals::ADC2;
+use esp_hal::interrupt::software::{SoftwareInterrupt, SoftwareInterruptControl};
+#[cfg(feature = "rmt")]
+use esp_hal::peripherals::RMT;
+use esp_hal::peripherals::{ADC2, CPU_CTRL};
+#[cfg(feature = "rmt")]
+use esp_hal::rmt::{Rmt, TxChannelCreator};
+#[cfg(not(feature = "rmt"))]
 use esp_hal::spi::master::AnySpi;
+use esp_hal::system::Stack;
 use esp_hal::timer::timg::TimerGroup;
+use esp_rtos::embassy::Executor;
 use {esp_backtrace as _, esp_println as _};
 
 esp_bootloader_esp_idf::esp_app_desc!();
 
+/// The size of the stack of the APP core, which only runs the animation task.
+const APP_CORE_STACK_SIZE: usize = 16 * 1024;
+
+/// The frames handed over from the APP core computing them to the PRO core writing them.
+static FRAME_EXCHANGE: FrameExchange = FrameExchange::new();
+
 #[esp_rtos::main]
 async fn main(spawner: Spawner) {
     // TODO: Check if the CPU clock could be lowered to save power
@@ -33,6 +55,21 @@ async fn main(spawner: Spawner) {
 
     info!("Embassy initialized!");
 
+    // The WiFi allocates its buffers, from the RAM of the bootloader and from a part of the rest.
+    #[cfg(feature = "network")]
+    {
+        esp_alloc::heap_allocator!(#[esp_hal::ram(reclaimed)] size: 64 * 1024);
+        esp_alloc::heap_allocator!(size: 36 * 1024);
+        network::start(&spawner, peripherals.WIFI);
+    }
+
+    let (frame_sender, frame_receiver) = unwrap!(FRAME_EXCHANGE.split());
+    start_app_core(
+        peripherals.CPU_CTRL,
+        software_interrupt.software_interrupt1,
+        frame_sender,
+    );
+
     let pins = Pins {
         // GPIO02 is the Q3 pin on the board, it's pull high. Which means a button should be
         // connected to a ground pin. A potentiometer shouldn't be connected to anything higher than
@@ -57,21 +94,54 @@ async fn main(spawner: Spawner) {
         // Pin that's labeled LED1 on the board.
         led_1: peripherals.GPIO16.degrade(),
 
-        // Pin that's labeled LED2 on the board.
+        // Pin that's labeled LED2 on the board. It's also the RX pin of UART0, so the console
+        // moves the second section to the pin labeled LED4.
+        #[cfg(not(feature = "console"))]
         led_2: peripherals.GPIO3.degrade(),
+        #[cfg(feature = "console")]
+        led_2: peripherals.GPIO4.degrade(),
+
+        // Pin that's labeled LED3 on the board. It only clocks the second section when it's an
+        // APA102 strip, or drives half of the first section over the RMT. It's also the TX pin of
+        // UART0, so the logs aren't output anymore.
+        #[cfg(not(feature = "console"))]
+        led_3: (cfg!(feature = "apa102-second-section") || cfg!(feature = "rmt"))
+            .then(|| peripherals.GPIO1.degrade()),
+        #[cfg(feature = "console")]
+        led_3: None,
+
+        // Pin that's labeled LED4 on the board. It only clocks the first section when it's an
+        // APA102 strip, or drives half of the second section over the RMT.
+        #[cfg(not(feature = "console"))]
+        led_4: (cfg!(feature = "apa102-first-section") || cfg!(feature = "rmt"))
+            .then(|| peripherals.GPIO4.degrade()),
+        #[cfg(feature = "console")]
+        led_4: None,
     };
 
-    spawn_all_tasks(
-        &spawner,
-        peripherals.ADC2,
+    // The console takes the pins of UART0, which is the serial port of the USB.
+    #[cfg(feature = "console")]
+    spawner.spawn(unwrap!(console_task(
+        peripherals.UART0,
+        peripherals.GPIO3.degrade(),
+        peripherals.GPIO1.degrade()
+    )));
+
+    #[cfg(not(feature = "rmt"))]
+    let outputs = Outputs {
         // On ESP32 there are four SPIs, but SPI0 and SPI1 are internally reserved for SPI flash
         // memory. That leaves only SPI2 and SPI3 available.
-        peripherals.SPI2.into(),
-        peripherals.DMA_SPI2.into(),
-        peripherals.SPI3.into(),
-        peripherals.DMA_SPI3.into(),
-        pins,
-    );
+        spi_1: peripherals.SPI2.into(),
+        dma_channel_1: peripherals.DMA_SPI2.into(),
+        spi_2: peripherals.SPI3.into(),
+        dma_channel_2: peripherals.DMA_SPI3.into(),
+    };
+    #[cfg(feature = "rmt")]
+    let outputs = Outputs {
+        rmt: peripherals.RMT,
+    };
+
+    spawn_all_tasks(&spawner, peripherals.ADC2, outputs, pins, frame_receiver);
 
     let mut delay = Delay;
 
@@ -88,13 +158,98 @@ struct Pins<'a> {
     delay: DelayPin<'a>,
     led_1: AnyPin<'a>,
     led_2: AnyPin<'a>,
+    led_3: Option<AnyPin<'a>>,
+    led_4: Option<AnyPin<'a>>,
 }
 
-/// Spawns all the tasks for the inputs and LEDs.
+/// Represents the peripherals driving the LED outputs.
+#[cfg(not(feature = "rmt"))]
+struct Outputs<'a> {
+    spi_1: AnySpi<'a>,
+    dma_channel_1: AnySpiDmaChannel<'a>,
+    spi_2: AnySpi<'a>,
+    dma_channel_2: AnySpiDmaChannel<'a>,
+}
+
+/// Represents the peripherals driving the LED outputs.
+#[cfg(feature = "rmt")]
+struct Outputs<'a> {
+    rmt: RMT<'a>,
+}
+
+/// Creates the configuration of each section from the outputs and their pins.
+#[cfg(not(feature = "rmt"))]
+fn create_section_configs(
+    outputs: Outputs<'static>, led_1: AnyPin<'static>, led_2: AnyPin<'static>,
+    led_3: Option<AnyPin<'static>>, led_4: Option<AnyPin<'static>>,
+) -> (SectionConfig<'static>, SectionConfig<'static>) {
+    (
+        SpiConfig {
+            spi: outputs.spi_1,
+            dma_channel: outputs.dma_channel_1,
+            led_pin: led_1,
+            sck: led_4,
+        },
+        SpiConfig {
+            spi: outputs.spi_2,
+            dma_channel: outputs.dma_channel_2,
+            led_pin: led_2,
+            sck: led_3,
+        },
+    )
+}
+
+/// Creates the configuration of each section from the outputs and their pins. Each section is
+/// split over two outputs, on the even channels since each one also takes the RAM of the next one.
+#[cfg(feature = "rmt")]
+fn create_section_configs(
+    outputs: Outputs<'static>, led_1: AnyPin<'static>, led_2: AnyPin<'static>,
+    led_3: Option<AnyPin<'static>>, led_4: Option<AnyPin<'static>>,
+) -> (SectionConfig<'static>, SectionConfig<'static>) {
+    // The channels refill their RAM from the interrupt of the RMT.
+    let rmt = unwrap!(Rmt::new(outputs.rmt, rmt::FREQUENCY)).into_async();
+    let config = rmt::channel_config();
+
+    (
+        [
+            unwrap!(rmt.channel0.configure_tx(&config)).with_pin(led_1),
+            unwrap!(rmt.channel2.configure_tx(&config)).with_pin(unwrap!(led_3)),
+        ],
+        [
+            unwrap!(rmt.channel4.configure_tx(&config)).with_pin(led_2),
+            unwrap!(rmt.channel6.configure_tx(&config)).with_pin(unwrap!(led_4)),
+        ],
+    )
+}
+
+/// Starts the APP core, which computes the frames of the animation so that the heavy ones don't
+/// slow down the LEDs and inputs of the PRO core.
+fn start_app_core(
+    cpu_control: CPU_CTRL<'static>, software_interrupt: SoftwareInterrupt<'static, 1>,
+    frames: FrameSender<'static>,
+) {
+    static mut APP_CORE_STACK: Stack<APP_CORE_STACK_SIZE> = Stack::new();
+    static mut APP_CORE_EXECUTOR: Executor = Executor::new();
+
+    // SAFETY: The APP core is only started once, and it's the only user of its stack.
+    let stack = unsafe { &mut *(&raw mut APP_CORE_STACK) };
+    esp_rtos::start_second_core(cpu_control, software_interrupt, stack, move || {
+        // SAFETY: The executor only runs on the APP core, which never returns from it.
+        let executor = unsafe { &mut *(&raw mut APP_CORE_EXECUTOR) };
+        executor.run(|spawner| {
+            spawner.spawn(unwrap!(animation_task(
+                frames,
+                ANALOG_DEFAULT_VALUE,
+                ANALOG_MAXIMUM_VALUE
+            )));
+        });
+    });
+}
+
+/// Spawns all the tasks for the inputs and LEDs, which run on the PRO core.
 fn spawn_all_tasks(
-    spawner: &Spawner, adc: ADC2<'static>, spi_1: AnySpi<'static>,
-    dma_channel_1: AnySpiDmaChannel<'static>, spi_2: AnySpi<'static>,
-    dma_channel_2: AnySpiDmaChannel<'static>, pins: Pins<'static>,
+    spawner: &Spawner, adc: ADC2<'static>, outputs: Outputs<'static>, pins: Pins<'static>,
+    frames: FrameReceiver<'static>,
 ) {
     info!("Spawning all tasks...");
 
@@ -104,29 +259,35 @@ fn spawn_all_tasks(
     // Spawn the color button task
     spawner.spawn(unwrap!(color_button_task(pins.color)));
 
-    // Spawn the analog sensors task
+    // Spawn the analog sensors task. The WiFi takes ADC2, so the potentiometers aren't read while
+    // it receives the universes.
+    #[cfg(not(feature = "network"))]
     spawner.spawn(unwrap!(analog_sensors_task(
         adc,
         pins.brightness,
         pins.delay
     )));
+    #[cfg(feature = "network")]
+    let _ = (adc, pins.brightness, pins.delay);
 
-    // Spawn the LED task
-    spawner.spawn(unwrap!(led::led_task(
-        SpiConfig {
-            spi: spi_1,
-            dma_channel: dma_channel_1,
-            led_pin: pins.led_1,
-        },
-        SpiConfig {
-            spi: spi_2,
-            dma_channel: dma_channel_2,
-            led_pin: pins.led_2,
-        },
-        ANALOG_DEFAULT_VALUE,
-        ANALOG_MAXIMUM_VALUE
-    )));
+    // Spawn the LED task. The logs go out on UART0 until its TX pin is taken by the LEDs.
+    if pins.led_3.is_some() {
+        warn!("The LED3 output is the TX pin of UART0, the logs stop here");
+    }
+    let (config_1, config_2) =
+        create_section_configs(outputs, pins.led_1, pins.led_2, pins.led_3, pins.led_4);
+    spawner.spawn(unwrap!(led::led_task(config_1, config_2, frames)));
 }
 
+mod animation;
+#[cfg(feature = "console")]
+mod console;
+#[cfg(feature = "streaming")]
+mod dma_chunks;
+mod flash;
 mod input;
 mod led;
+#[cfg(feature = "network")]
+mod network;
+#[cfg(feature = "rmt")]
+mod rmt;
@@ -0,0 +1,392 @@
+#[cfg(feature = "artnet")]
+use cookie_monster_common::artnet;
+#[cfg(feature = "ddp")]
+use cookie_monster_common::ddp;
+#[cfg(feature = "e131")]
+use cookie_monster_common::e131;
+#[cfg(feature = "opc")]
+use cookie_monster_common::opc;
+#[cfg(feature = "tpm2net")]
+use cookie_monster_common::tpm2;
+use defmt::{debug, error, info, unwrap, warn};
+use embassy_executor::Spawner;
+#[cfg(feature = "e131")]
+use embassy_net::Ipv4Address;
+#[cfg(feature = "opc")]
+use embassy_net::tcp::TcpSocket;
+use embassy_net::udp::{PacketMetadata, UdpSocket};
+use embassy_net::{Config, Runner, Stack, StackResources};
+use embassy_time::{Duration, Instant, Timer};
+use esp_hal::peripherals::WIFI;
+use esp_hal::rng::Rng;
+use esp_radio::wifi::sta::StationConfig;
+use esp_radio::wifi::{ControllerConfig, Interface, WifiController};
+
+#[cfg(not(any(
+    feature = "e131",
+    feature = "artnet",
+    feature = "ddp",
+    feature = "opc",
+    feature = "tpm2net"
+)))]
+compile_error!(
+    "The `network` feature is enabled by the receivers, like `e131`, `artnet`, `ddp`, `opc` and \
+     `tpm2net`"
+);
+
+/// Where the E1.31 universes are mapped on the LEDs: from the first channel of universe 1.
+#[cfg(feature = "e131")]
+const E131_MAPPING: e131::Mapping = e131::Mapping::new(1, 1);
+
+/// Where the Art-Net port addresses are mapped on the LEDs: from the first channel of universe 1
+/// of net 0 and subnet 0.
+#[cfg(feature = "artnet")]
+const ARTNET_MAPPING: artnet::Mapping = artnet::Mapping::new(artnet::port_address(0, 0, 1), 1);
+
+/// The WiFi network joined by the board, given when building.
+const SSID: &str = env!("WIFI_SSID");
+const PASSWORD: &str = env!("WIFI_PASSWORD");
+
+/// The largest packet of the universes, an E1.31 one with 512 channels.
+const PACKET_SIZE: usize = 638;
+
+/// The packets kept by a socket while the receiver writes the previous ones, enough for a frame.
+const PACKETS_BUFFERED: usize = 8;
+
+/// The sockets of the network stack: the DHCP one and the one of each receiver.
+const SOCKETS: usize = 1
+    + cfg!(feature = "e131") as usize
+    + cfg!(feature = "artnet") as usize
+    + cfg!(feature = "ddp") as usize
+    + cfg!(feature = "opc") as usize
+    + cfg!(feature = "tpm2net") as usize;
+
+/// The delay before connecting again after the network was lost, or couldn't be joined.
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// How long an idle OPC client goes before it's asked whether it's still there, and how long it has
+/// to answer before its connection is closed, so that a client that vanished frees the server.
+#[cfg(feature = "opc")]
+const OPC_KEEP_ALIVE: Duration = Duration::from_secs(10);
+#[cfg(feature = "opc")]
+const OPC_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Starts the WiFi and the network stack, and spawns the tasks receiving the universes.
+pub fn start(spawner: &Spawner, wifi: WIFI<'static>) {
+    static mut RESOURCES: StackResources<SOCKETS> = StackResources::new();
+
+    let station = StationConfig::default()
+        .with_ssid(SSID)
+        .with_password(PASSWORD.into());
+    let config =
+        ControllerConfig::default().with_initial_config(esp_radio::wifi::Config::Station(station));
+    let (controller, interfaces) = match esp_radio::wifi::new(wifi, config) {
+        Ok(wifi) => wifi,
+        Err(e) => {
+            error!("Cannot start the WiFi: {}", e);
+            return;
+        }
+    };
+    #[cfg(feature = "artnet")]
+    let mac = interfaces.station.mac_address();
+
+    let rng = Rng::new();
+    let seed = u64::from(rng.random()) << 32 | u64::from(rng.random());
+    // SAFETY: The network is only started once, and the stack is the only user of its resources.
+    let resources = unsafe { &mut *(&raw mut RESOURCES) };
+    let (stack, runner) = embassy_net::new(
+        interfaces.station,
+        Config::dhcpv4(Default::default()),
+        resources,
+        seed,
+    );
+
+    spawner.spawn(unwrap!(connection_task(controller)));
+    spawner.spawn(unwrap!(network_task(runner)));
+    #[cfg(feature = "e131")]
+    spawner.spawn(unwrap!(e131_task(stack)));
+    #[cfg(feature = "artnet")]
+    spawner.spawn(unwrap!(artnet_task(stack, mac)));
+    #[cfg(feature = "ddp")]
+    spawner.spawn(unwrap!(ddp_task(stack)));
+    #[cfg(feature = "opc")]
+    spawner.spawn(unwrap!(opc_task(stack)));
+    #[cfg(feature = "tpm2net")]
+    spawner.spawn(unwrap!(tpm2net_task(stack)));
+}
+
+/// Task that keeps the board connected to the WiFi network.
+#[embassy_executor::task]
+async fn connection_task(mut controller: WifiController<'static>) {
+    info!("Starting WiFi connection task...");
+
+    loop {
+        match controller.connect_async().await {
+            Ok(_) => {
+                info!("Connected to {}", SSID);
+                if let Err(e) = controller.wait_for_disconnect_async().await {
+                    warn!("Cannot wait for the disconnection: {}", e);
+                }
+                warn!("Disconnected from {}", SSID);
+            }
+            Err(e) => warn!("Cannot connect to {}: {}", SSID, e),
+        }
+
+        Timer::after(RECONNECT_DELAY).await;
+    }
+}
+
+/// Task that runs the network stack, which handles the packets of the WiFi interface.
+#[embassy_executor::task]
+async fn network_task(mut runner: Runner<'static, Interface<'static>>) -> ! {
+    runner.run().await
+}
+
+/// Task that receives the E1.31 universes of the LEDs, and writes them to the stream shown by the
+/// animation task. The local animations come back once the universes stop.
+#[cfg(feature = "e131")]
+#[embassy_executor::task]
+async fn e131_task(stack: Stack<'static>) {
+    info!("Starting E1.31 task...");
+
+    stack.wait_config_up().await;
+    if let Some(config) = stack.config_v4() {
+        info!("Receiving E1.31 on {}", config.address);
+    }
+    for universe in E131_MAPPING.universes() {
+        let address = Ipv4Address::from(e131::multicast_address(universe));
+        if let Err(e) = stack.join_multicast_group(address) {
+            error!(
+                "Cannot join the multicast group of universe {}: {}",
+                universe, e
+            );
+        }
+    }
+
+    let mut rx_meta = [PacketMetadata::EMPTY; PACKETS_BUFFERED];
+    let mut rx_buffer = [0; PACKET_SIZE * PACKETS_BUFFERED];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; 0];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    if let Err(e) = socket.bind(e131::PORT) {
+        error!("Cannot bind the E1.31 port: {}", e);
+        return;
+    }
+
+    let mut receiver = e131::Receiver::new(E131_MAPPING);
+    let mut packet = [0; PACKET_SIZE];
+
+    loop {
+        let length = match socket.recv_from(&mut packet).await {
+            Ok((length, _)) => length,
+            Err(e) => {
+                debug!("Cannot receive an E1.31 packet: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = receiver.receive(&packet[..length], Instant::now()) {
+            debug!("E1.31 packet dropped: {}", e);
+        }
+    }
+}
+
+/// Task of the Art-Net node, which receives the universes of the LEDs and writes them to the stream
+/// shown by the animation task, and answers the ArtPoll of the consoles.
+#[cfg(feature = "artnet")]
+#[embassy_executor::task]
+async fn artnet_task(stack: Stack<'static>, mac: [u8; 6]) {
+    info!("Starting Art-Net task...");
+
+    stack.wait_config_up().await;
+
+    let mut rx_meta = [PacketMetadata::EMPTY; PACKETS_BUFFERED];
+    let mut rx_buffer = [0; PACKET_SIZE * PACKETS_BUFFERED];
+    let mut tx_meta = [PacketMetadata::EMPTY; 2];
+    let mut tx_buffer = [0; artnet::POLL_REPLY_SIZE * 2];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    if let Err(e) = socket.bind(artnet::PORT) {
+        error!("Cannot bind the Art-Net port: {}", e);
+        return;
+    }
+
+    let mut node = artnet::Node::new(ARTNET_MAPPING, mac);
+    let mut packet = [0; PACKET_SIZE];
+
+    loop {
+        let (length, metadata) = match socket.recv_from(&mut packet).await {
+            Ok(received) => received,
+            Err(e) => {
+                debug!("Cannot receive an Art-Net packet: {}", e);
+                continue;
+            }
+        };
+
+        match node.receive(&packet[..length], Instant::now()) {
+            Ok(artnet::Packet::Poll) => {
+                // The address can change when the DHCP lease is renewed.
+                let Some(config) = stack.config_v4() else {
+                    continue;
+                };
+                let address = config.address.address().octets();
+                for reply in node.poll_replies(address) {
+                    let controller = (metadata.endpoint.addr, artnet::PORT);
+                    if let Err(e) = socket.send_to(&reply, controller).await {
+                        warn!("Cannot answer the ArtPoll: {}", e);
+                    }
+                }
+            }
+            Ok(artnet::Packet::Dmx(_)) => {}
+            Err(e) => debug!("Art-Net packet dropped: {}", e),
+        }
+    }
+}
+
+/// Task that receives the DDP packets, and writes them to the stream shown by the animation task.
+/// The local animations come back once the packets stop.
+#[cfg(feature = "ddp")]
+#[embassy_executor::task]
+async fn ddp_task(stack: Stack<'static>) {
+    info!("Starting DDP task...");
+
+    stack.wait_config_up().await;
+
+    // A frame of the LEDs takes two packets of the largest size.
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; ddp::PACKET_SIZE * 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; 0];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    if let Err(e) = socket.bind(ddp::PORT) {
+        error!("Cannot bind the DDP port: {}", e);
+        return;
+    }
+
+    let mut packet = [0; ddp::PACKET_SIZE];
+
+    loop {
+        let length = match socket.recv_from(&mut packet).await {
+            Ok((length, _)) => length,
+            Err(e) => {
+                debug!("Cannot receive a DDP packet: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = ddp::receive(&packet[..length], Instant::now()) {
+            debug!("DDP packet dropped: {}", e);
+        }
+    }
+}
+
+/// Task that receives the TPM2.net packets, and writes their frames to the stream shown by the
+/// animation task. The local animations come back once the packets stop.
+#[cfg(feature = "tpm2net")]
+#[embassy_executor::task]
+async fn tpm2net_task(stack: Stack<'static>) {
+    info!("Starting TPM2.net task...");
+
+    stack.wait_config_up().await;
+
+    // A frame of the LEDs takes two packets of the largest size.
+    let mut rx_meta = [PacketMetadata::EMPTY; 4];
+    let mut rx_buffer = [0; tpm2::NET_PACKET_SIZE * 4];
+    let mut tx_meta = [PacketMetadata::EMPTY; 1];
+    let mut tx_buffer = [0; 0];
+    let mut socket = UdpSocket::new(
+        stack,
+        &mut rx_meta,
+        &mut rx_buffer,
+        &mut tx_meta,
+        &mut tx_buffer,
+    );
+    if let Err(e) = socket.bind(tpm2::NET_PORT) {
+        error!("Cannot bind the TPM2.net port: {}", e);
+        return;
+    }
+
+    let mut assembler = tpm2::NetAssembler::new();
+    let mut packet = [0; tpm2::NET_PACKET_SIZE];
+
+    loop {
+        let length = match socket.recv_from(&mut packet).await {
+            Ok((length, _)) => length,
+            Err(e) => {
+                debug!("Cannot receive a TPM2.net packet: {}", e);
+                continue;
+            }
+        };
+
+        if let Err(e) = assembler.receive(&packet[..length], Instant::now()) {
+            debug!("TPM2.net packet dropped: {}", e);
+        }
+    }
+}
+
+/// Task of the Open Pixel Control server, which serves one client at a time and writes its
+/// messages to the stream shown by the animation task. The local animations come back once the
+/// messages stop.
+#[cfg(feature = "opc")]
+#[embassy_executor::task]
+async fn opc_task(stack: Stack<'static>) {
+    info!("Starting OPC task...");
+
+    stack.wait_config_up().await;
+
+    // A frame of the LEDs, so that the client sends the next one while this one is parsed.
+    let mut rx_buffer = [0; 4096];
+    let mut tx_buffer = [0; 0];
+    let mut received = [0; 1024];
+
+    loop {
+        let mut socket = TcpSocket::new(stack, &mut rx_buffer, &mut tx_buffer);
+        socket.set_keep_alive(Some(OPC_KEEP_ALIVE));
+        socket.set_timeout(Some(OPC_TIMEOUT));
+        if let Err(e) = socket.accept(opc::PORT).await {
+            warn!("Cannot accept an OPC client: {}", e);
+            continue;
+        }
+        info!("OPC client connected");
+
+        let mut parser = opc::Parser::new();
+        loop {
+            match socket.read(&mut received).await {
+                Ok(0) => break,
+                Ok(length) => {
+                    let now = Instant::now();
+                    for &byte in &received[..length] {
+                        parser.receive(byte, now);
+                    }
+                }
+                Err(e) => {
+                    debug!("Cannot receive from the OPC client: {}", e);
+                    break;
+                }
+            }
+        }
+
+        socket.abort();
+        if let Err(e) = socket.flush().await {
+            debug!("Cannot close the OPC connection: {}", e);
+        }
+        info!("OPC client disconnected");
+    }
+}
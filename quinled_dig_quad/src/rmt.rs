@@ -0,0 +1,176 @@
+//! Driver of the WS2812 and SK6812 strips over the RMT peripheral, which frees the SPI buses.
+//!
+//! The RMT has 8 channels, so a section can be split over several outputs, which transmit in
+//! parallel. Its LEDs are written to the outputs in order, each output taking the same number of
+//! LEDs. Each bit of the colors is a pulse code of 4 bytes in the buffer, 96 bytes per RGB LED,
+//! which is refilled into the RAM of the channels while they transmit.
+//!
+//! The channels are async: each one interrupts once half of its RAM was sent, and its transmission
+//! is refilled when the executor polls it again. The executor of the LED task mustn't be blocked
+//! for longer than the 80 µs that the other half of the RAM lasts, or the strip latches early, so
+//! the network tasks aren't built with this driver.
+
+use core::marker::PhantomData;
+use defmt::unwrap;
+use embassy_futures::join::join_array;
+use esp_hal::Async;
+use esp_hal::gpio::Level;
+use esp_hal::rmt::{Channel, PulseCode, Tx, TxChannelConfig};
+use esp_hal::time::Rate;
+use smart_leds_trait::{RGB8, RGBW, SmartLedsWriteAsync};
+
+/// The frequency of the RMT peripheral, which the channels divide to get their ticks.
+pub(crate) const FREQUENCY: Rate = Rate::from_mhz(80);
+
+/// The ticks last 25 ns, which is precise enough for the timings of the WS2812.
+const CLOCK_DIVIDER: u8 = 2;
+
+/// Each channel uses two of the 8 blocks of RAM, so channels 0, 2, 4 and 6 can be used together.
+/// A block holds 64 pulse codes, and half of the RAM is refilled at once, which leaves 80 µs to
+/// refill it.
+const MEMORY_BLOCKS: u8 = 2;
+
+/// The high and low times of the bits, in ticks of 25 ns.
+const ZERO: PulseCode = PulseCode::new(Level::High, 16, Level::Low, 34);
+const ONE: PulseCode = PulseCode::new(Level::High, 32, Level::Low, 18);
+
+/// The line stays low for 80 µs to latch the colors, then the zero length ends the transmission.
+const LATCH: PulseCode = PulseCode::new(Level::Low, 3_200, Level::Low, 0);
+
+/// The configuration of the channels driving the strips, whose line stays low when idle.
+pub(crate) fn channel_config() -> TxChannelConfig {
+    TxChannelConfig::default()
+        .with_clk_divider(CLOCK_DIVIDER)
+        .with_idle_output(true)
+        .with_idle_output_level(Level::Low)
+        .with_memsize(MEMORY_BLOCKS)
+}
+
+/// The size of the buffer needed to write the given number of LEDs over the outputs.
+pub(crate) const fn buffer_size(leds: usize, channels: usize, outputs: usize) -> usize {
+    outputs * output_size(leds.div_ceil(outputs), channels * 8)
+}
+
+/// The pulse codes of an output: one per bit, and the latch.
+const fn output_size(leds: usize, bits: usize) -> usize {
+    leds * bits + 1
+}
+
+#[derive(Debug)]
+pub(crate) enum Error {
+    /// The buffer is too small for the number of LEDs, see [`buffer_size`].
+    OutOfBounds,
+
+    Rmt(esp_hal::rmt::Error),
+}
+
+/// A strip split over the given outputs, with colors of type `C`.
+pub(crate) struct RmtWs2812<'a, 'b, const OUTPUTS: usize, C> {
+    channels: &'a mut [Channel<'b, Async, Tx>; OUTPUTS],
+    buffer: &'a mut [PulseCode],
+    color: PhantomData<C>,
+}
+
+impl<'a, 'b, const OUTPUTS: usize, C> RmtWs2812<'a, 'b, OUTPUTS, C> {
+    /// The buffer must hold the LEDs of all the outputs, see [`buffer_size`].
+    pub(crate) fn new(
+        channels: &'a mut [Channel<'b, Async, Tx>; OUTPUTS], buffer: &'a mut [PulseCode],
+    ) -> Self {
+        Self {
+            channels,
+            buffer,
+            color: PhantomData,
+        }
+    }
+
+    /// Encodes the channels of the LEDs, then transmits them on all the outputs at once.
+    async fn write_channels<const CHANNELS: usize>(
+        &mut self, leds: impl Iterator<Item = [u8; CHANNELS]>,
+    ) -> Result<(), Error> {
+        let bits = CHANNELS * 8;
+        let size = self.buffer.len() / OUTPUTS;
+        let leds_per_output = size.saturating_sub(1) / bits;
+        let mut leds = leds.peekable();
+
+        let mut lengths = [0; OUTPUTS];
+        for (output, length) in self.buffer.chunks_exact_mut(size).zip(&mut lengths) {
+            let mut written = 0;
+            for (channels, codes) in leds
+                .by_ref()
+                .take(leds_per_output)
+                .zip(output.chunks_exact_mut(bits))
+            {
+                encode(&channels, codes);
+                written += 1;
+            }
+
+            *length = output_size(written, bits);
+            output[*length - 1] = LATCH;
+        }
+
+        if leds.peek().is_some() {
+            return Err(Error::OutOfBounds);
+        }
+
+        let mut outputs = self
+            .channels
+            .iter_mut()
+            .zip(self.buffer.chunks_exact(size))
+            .zip(lengths);
+        let transmissions: [_; OUTPUTS] = core::array::from_fn(|_| {
+            let ((channel, output), length) = unwrap!(outputs.next());
+            channel.transmit(&output[..length])
+        });
+
+        join_array(transmissions)
+            .await
+            .into_iter()
+            .find_map(Result::err)
+            .map_or(Ok(()), |e| Err(Error::Rmt(e)))
+    }
+}
+
+/// Encodes the channels of a LED, most significant bit first.
+fn encode(channels: &[u8], codes: &mut [PulseCode]) {
+    for (byte, codes) in channels.iter().zip(codes.chunks_exact_mut(8)) {
+        for (bit, code) in codes.iter_mut().enumerate() {
+            *code = if byte & (0x80 >> bit) == 0 { ZERO } else { ONE };
+        }
+    }
+}
+
+// The write completes once all the outputs are done, and the other section or the inputs are
+// handled while they transmit.
+impl<const OUTPUTS: usize> SmartLedsWriteAsync for RmtWs2812<'_, '_, OUTPUTS, RGB8> {
+    type Color = RGB8;
+    type Error = Error;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b]
+        }))
+        .await
+    }
+}
+
+impl<const OUTPUTS: usize> SmartLedsWriteAsync for RmtWs2812<'_, '_, OUTPUTS, RGBW<u8>> {
+    type Color = RGBW<u8>;
+    type Error = Error;
+
+    async fn write<T, I>(&mut self, iterator: T) -> Result<(), Self::Error>
+    where
+        T: IntoIterator<Item = I>,
+        I: Into<Self::Color>,
+    {
+        self.write_channels(iterator.into_iter().map(|color| {
+            let color = color.into();
+            [color.g, color.r, color.b, color.a.0]
+        }))
+        .await
+    }
+}